@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    animation::{Animation, PlayMode},
+    run,
+    types::{Color, Vec2},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 4;
+const PIXELS_HEIGHT: u32 = 768 / 4;
+
+pub struct Demo {
+    ctx: Context,
+    animations: Vec<Animation>,
+    walker: Option<Animation>,
+    walker_x: f32,
+    flash: Duration,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            animations: Vec::new(),
+            walker: None,
+            walker_x: 40.0,
+            flash: Duration::ZERO,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        let resource_manager = &mut engine.resource_manager;
+        let slime = resource_manager
+            .try_load_aseprite(Path::new("resources/images/slime.aseprite"))
+            .unwrap();
+        let sheet = resource_manager.get_sprite_sheet(slime).unwrap();
+        // one animation per Aseprite tag, plus the whole file played once at half speed
+        for tag in sheet.tags() {
+            self.animations
+                .push(Animation::from_tag(slime, sheet, &tag.name).unwrap());
+        }
+        let mut once = Animation::from_sheet(slime, sheet, PlayMode::Once);
+        once.speed = 0.5;
+        self.animations.push(once);
+
+        let ball = resource_manager
+            .try_load_sprite_sheet_json(Path::new("resources/images/sprite_sheet.json"))
+            .unwrap();
+        let sheet = resource_manager.get_sprite_sheet(ball).unwrap();
+        let mut walker = Animation::from_sheet(ball, sheet, PlayMode::Loop);
+        walker.add_event(0, "land");
+        self.walker = Some(walker);
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
+        let screen = &mut engine.screen;
+        screen.clear(Color::new(40, 40, 60, 255));
+        let resource_manager = &engine.resource_manager;
+        for (i, animation) in self.animations.iter_mut().enumerate() {
+            animation.update(elapsed_time);
+            animation
+                .draw(resource_manager, screen, Vec2::new(40 + i as i32 * 32, 40))
+                .unwrap();
+        }
+
+        // walk back and forth, flashing whenever the ball lands
+        let walker = self.walker.as_mut().unwrap();
+        let direction = if walker.flip.horizontal { -1.0 } else { 1.0 };
+        self.walker_x += direction * 40.0 * elapsed_time.as_secs_f32();
+        if self.walker_x > PIXELS_WIDTH as f32 - 40.0 || self.walker_x < 40.0 {
+            walker.flip.horizontal = !walker.flip.horizontal;
+            self.walker_x = self.walker_x.clamp(40.0, PIXELS_WIDTH as f32 - 40.0);
+        }
+        for event in walker.update(elapsed_time) {
+            if event.name == "land" {
+                self.flash = Duration::from_millis(60);
+            }
+        }
+        self.flash = self.flash.saturating_sub(elapsed_time);
+        walker.tint = if self.flash.is_zero() {
+            Color::new(255, 255, 255, 255)
+        } else {
+            Color::new(255, 120, 120, 255)
+        };
+        walker
+            .draw(
+                resource_manager,
+                screen,
+                Vec2::new(self.walker_x as i32, 140),
+            )
+            .unwrap();
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
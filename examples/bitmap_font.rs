@@ -0,0 +1,110 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    resource::BitmapFontHandle,
+    run,
+    text::{HorizontalAlign, TextBox, TextStyle},
+    types::{Color, Vec2},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 2;
+const PIXELS_HEIGHT: u32 = 768 / 2;
+
+pub struct Demo {
+    ctx: Context,
+    fnt_font: Option<BitmapFontHandle>,
+    grid_font: Option<BitmapFontHandle>,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            fnt_font: None,
+            grid_font: None,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        let resource_manager = &mut engine.resource_manager;
+        self.fnt_font = Some(
+            resource_manager
+                .try_load_bitmap_font(Path::new("resources/fonts/pixel_mono.fnt"))
+                .unwrap(),
+        );
+        let chars = (' '..='~').collect::<String>();
+        self.grid_font = Some(
+            resource_manager
+                .try_load_grid_font(
+                    Path::new("resources/fonts/pixel_mono_grid.png"),
+                    8,
+                    16,
+                    &chars,
+                )
+                .unwrap(),
+        );
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.screen.clear(Color::new(20, 20, 40, 255));
+        let fnt_font = self.fnt_font.unwrap();
+        let grid_font = self.grid_font.unwrap();
+        let font_helper = &mut engine.font_helper;
+        font_helper.draw_text(
+            &engine.resource_manager,
+            fnt_font,
+            "AVATAR Today (BMFont, kerned)",
+            &TextStyle::new(13.0, Color::new(255, 255, 255, 255)),
+            &TextBox::at(Vec2 { x: 8, y: 8 }),
+            &mut engine.screen,
+        );
+        font_helper.draw_text(
+            &engine.resource_manager,
+            grid_font,
+            "Fixed grid font\nwith a second line",
+            &TextStyle::new(16.0, Color::new(120, 220, 120, 255)),
+            &TextBox::at(Vec2 { x: 8, y: 40 }),
+            &mut engine.screen,
+        );
+        font_helper.draw_text(
+            &engine.resource_manager,
+            fnt_font,
+            "Scaled up by whole pixels and wrapped to stay crisp",
+            &TextStyle::new(26.0, Color::new(255, 200, 80, 255)),
+            &TextBox {
+                horizontal_align: HorizontalAlign::Center,
+                ..TextBox::sized(Vec2 { x: 16, y: 120 }, PIXELS_WIDTH - 32, 200)
+            },
+            &mut engine.screen,
+        );
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
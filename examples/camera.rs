@@ -0,0 +1,151 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    camera::Camera2D,
+    drawing::Transform,
+    resource::SpriteSheetHandle,
+    run,
+    tilemap::Tilemap,
+    types::{Color, Rect, Vec2, Vec2F, VirtualKeyCode},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 4;
+const PIXELS_HEIGHT: u32 = 768 / 4;
+const WALK_SPEED: f32 = 80.0;
+
+pub struct Demo {
+    ctx: Context,
+    map: Option<Tilemap>,
+    slime: Option<SpriteSheetHandle>,
+    camera: Camera2D,
+    player: Vec2F,
+    time: Duration,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        let mut camera = Camera2D::new(PIXELS_WIDTH, PIXELS_HEIGHT);
+        camera.deadzone = Vec2F::new(48.0, 32.0);
+        camera.follow_speed = 6.0;
+        Self {
+            ctx,
+            map: None,
+            slime: None,
+            camera,
+            player: Vec2F::new(64.0, 64.0),
+            time: Duration::ZERO,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        let resource_manager = &mut engine.resource_manager;
+        let map =
+            Tilemap::open(Path::new("resources/maps/orthogonal.tmx"), resource_manager).unwrap();
+        let (width, height) = map.pixel_size();
+        self.camera.bounds = Some(Rect::new(Vec2::new(0, 0), width, height));
+        self.map = Some(map);
+        self.slime = Some(
+            resource_manager
+                .try_load_aseprite(Path::new("resources/images/slime.aseprite"))
+                .unwrap(),
+        );
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!(
+            "{}ms, arrows to walk, z/x to zoom, q/e to rotate, space to shake",
+            elapsed_time.as_millis()
+        ));
+        self.time += elapsed_time;
+        let seconds = elapsed_time.as_secs_f32();
+        let input = &engine.input;
+        let mut walk = Vec2F::new(0.0, 0.0);
+        if input.key_held(VirtualKeyCode::Left) {
+            walk.x -= 1.0;
+        }
+        if input.key_held(VirtualKeyCode::Right) {
+            walk.x += 1.0;
+        }
+        if input.key_held(VirtualKeyCode::Up) {
+            walk.y -= 1.0;
+        }
+        if input.key_held(VirtualKeyCode::Down) {
+            walk.y += 1.0;
+        }
+        self.player += walk.normalize() * WALK_SPEED * seconds;
+        if input.key_held(VirtualKeyCode::Z) {
+            self.camera.zoom = (self.camera.zoom * (1.0 + seconds)).min(4.0);
+        }
+        if input.key_held(VirtualKeyCode::X) {
+            self.camera.zoom = (self.camera.zoom / (1.0 + seconds)).max(0.5);
+        }
+        if input.key_held(VirtualKeyCode::Q) {
+            self.camera.rotation -= seconds;
+        }
+        if input.key_held(VirtualKeyCode::E) {
+            self.camera.rotation += seconds;
+        }
+        if input.key_pressed(VirtualKeyCode::Space) {
+            self.camera.shake(4.0, Duration::from_millis(400));
+        }
+        self.camera.follow(self.player, elapsed_time);
+        self.camera.update(elapsed_time);
+
+        let map = self.map.as_ref().unwrap();
+        let background = map.background_color.unwrap_or(Color::new(20, 20, 30, 255));
+        let resource_manager = &engine.resource_manager;
+        let screen = &mut engine.screen;
+        screen.clear(background);
+        self.camera
+            .draw_tilemap(map, resource_manager, screen, self.time);
+
+        let sheet = resource_manager
+            .get_sprite_sheet(self.slime.unwrap())
+            .unwrap();
+        let image = resource_manager.get_image(sheet.image).unwrap();
+        let frame = sheet.frame(0).unwrap();
+        self.camera
+            .draw_frame(image, frame, screen, self.player, &Transform::default());
+
+        // a line from the player to whatever the mouse points at in the world
+        if let Some(mouse) = engine
+            .mouse_position()
+            .and_then(|mouse| self.camera.mouse_position(mouse))
+        {
+            self.camera.draw_line(
+                self.player,
+                mouse,
+                &mut engine.screen,
+                Color::new(255, 255, 0, 255),
+            );
+        }
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
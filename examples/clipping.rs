@@ -0,0 +1,128 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    canvas::Canvas,
+    drawing::{draw_line, draw_rectangle, fill_circle, fill_rectangle},
+    resource::BitmapFontHandle,
+    run,
+    text::{TextBox, TextStyle},
+    types::{Color, Rect, Vec2, VirtualKeyCode},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 2;
+const PIXELS_HEIGHT: u32 = 768 / 2;
+const SCROLL_SPEED: f32 = 120.0;
+const LINE_HEIGHT: i32 = 18;
+
+pub struct Demo {
+    ctx: Context,
+    font: Option<BitmapFontHandle>,
+    scroll: f32,
+    time: f32,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            font: None,
+            scroll: 0.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        self.font = Some(
+            engine
+                .resource_manager
+                .try_load_bitmap_font(Path::new("resources/fonts/pixel_mono.fnt"))
+                .unwrap(),
+        );
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!(
+            "{}ms, up/down to scroll",
+            elapsed_time.as_millis()
+        ));
+        self.time += elapsed_time.as_secs_f32();
+        let step = SCROLL_SPEED * elapsed_time.as_secs_f32();
+        if engine.input.key_held(VirtualKeyCode::Up) {
+            self.scroll -= step;
+        }
+        if engine.input.key_held(VirtualKeyCode::Down) {
+            self.scroll += step;
+        }
+        self.scroll = self.scroll.clamp(0.0, (50 * LINE_HEIGHT) as f32);
+
+        engine.screen.clear(Color::new(30, 30, 40, 255));
+        let font = self.font.unwrap();
+        let mut canvas = Canvas::new(&mut engine.screen);
+
+        // a scroll view: a long list cut off at the panel edges
+        let panel = Rect::new(Vec2::new(20, 20), 220, 300);
+        fill_rectangle(panel, &mut canvas, Color::new(50, 50, 70, 255));
+        canvas.with_clip(panel, |canvas| {
+            for i in 0..60 {
+                let y = panel.top() + 4 + i * LINE_HEIGHT - self.scroll as i32;
+                engine.font_helper.draw_text(
+                    &engine.resource_manager,
+                    font,
+                    &format!("Item number {}", i + 1),
+                    &TextStyle::new(16.0, Color::new(230, 230, 230, 255)),
+                    &TextBox::at(Vec2::new(panel.left() + 6, y)),
+                    canvas,
+                );
+            }
+        });
+        draw_rectangle(panel, &mut canvas, Color::new(200, 200, 220, 255));
+
+        // split screen: the same scene drawn into two halves, each clipped to its own
+        for (i, offset) in [0.0_f32, 1.5].into_iter().enumerate() {
+            let view = Rect::new(Vec2::new(260 + i as i32 * 120, 20), 110, 300);
+            canvas.with_clip(view, |canvas| {
+                fill_rectangle(view, canvas, Color::new(20, 40, 30, 255));
+                let t = self.time + offset;
+                let center = Vec2::new(
+                    view.left() + 55 + (t.cos() * 70.0) as i32,
+                    view.top() + 150 + (t.sin() * 120.0) as i32,
+                );
+                fill_circle(center, 30, canvas, Color::new(240, 160, 60, 255));
+                draw_line(
+                    view.top_left,
+                    center,
+                    canvas,
+                    Color::new(120, 220, 120, 255),
+                );
+            });
+        }
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
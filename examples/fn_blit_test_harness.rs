@@ -32,6 +32,7 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: false,
+            fixed_timestep: None,
         };
         Self {
             ctx,
@@ -105,9 +106,7 @@ impl From<u8> for TestFn {
 
 impl GameState for Demo {
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
         let screen = &mut engine.screen;
         if engine.input.key_released(VirtualKeyCode::M) {
             self.current_func = ((self.current_func as u8 + 1) % 5).into();
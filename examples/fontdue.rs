@@ -4,9 +4,14 @@ use std::time::Duration;
 use fontdue::FontSettings;
 
 use engine::{
-    drawing::draw_text,
+    drawing::draw_rectangle,
     resource::FontHandle,
+    rich_text::{draw_rich_text, FontSet, RichText},
     run,
+    text::{
+        draw_text_cached, HorizontalAlign, TextBox, TextOutline, TextShadow, TextStyle,
+        VerticalAlign,
+    },
     types::{Color, Vec2},
     Context, Engine, GameState,
 };
@@ -17,6 +22,8 @@ const SCREEN_HEIGHT: u32 = 768;
 pub struct Demo {
     ctx: Context,
     font_handle_1: Option<FontHandle>,
+    dialogue: Option<RichText>,
+    time: f32,
 }
 
 impl Default for Demo {
@@ -31,10 +38,13 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: false,
+            fixed_timestep: None,
         };
         Self {
             ctx,
             font_handle_1: None,
+            dialogue: None,
+            time: 0.0,
         }
     }
 }
@@ -49,29 +59,79 @@ impl GameState for Demo {
             Path::new("resources/fonts/JetbrainsMonoRegular.ttf"),
             settings,
         ));
+        let fonts = FontSet::new(self.font_handle_1.unwrap());
+        self.dialogue = Some(
+            RichText::parse(
+                "Press [color=#ff0]A[/color] to [wave]jump[/wave], \
+                [color=#f008][shake=2]careful![/shake][/color] [size=20](it's slippery)[/size]",
+                &fonts,
+            )
+            .unwrap(),
+        );
         true
     }
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
         let screen = &mut engine.screen;
         screen.clear(Color::new(0, 0, 0, 255));
-        let font = engine
-            .resource_manager
-            .get_font(self.font_handle_1.unwrap())
-            .unwrap();
-        let layout = &mut engine.font_helper.default_layout;
         let text = &format!("Render time: {}ms", elapsed_time.as_millis());
-        draw_text(
-            font,
-            layout,
+        draw_text_cached(
+            &engine.resource_manager,
+            &mut engine.font_helper,
+            self.font_handle_1.unwrap(),
             text,
             40.0,
             Color::new(255, 255, 255, 255),
             screen,
             Vec2 { x: 10, y: 10 },
         );
+        let text_box = TextBox {
+            horizontal_align: HorizontalAlign::Center,
+            vertical_align: VerticalAlign::Middle,
+            ..TextBox::sized(Vec2 { x: 200, y: 200 }, 600, 400)
+        };
+        let bounds = engine.font_helper.draw_text(
+            &engine.resource_manager,
+            self.font_handle_1.unwrap(),
+            "Long text is wrapped to fit inside its box.\nEach line is centered, and the \
+            whole paragraph sits in the middle.",
+            &TextStyle {
+                line_spacing: 1.2,
+                ..TextStyle::new(32.0, Color::new(255, 200, 100, 255))
+            },
+            &text_box,
+            &mut engine.screen,
+        );
+        draw_rectangle(bounds, &mut engine.screen, Color::new(80, 80, 80, 255));
+        engine.font_helper.draw_text(
+            &engine.resource_manager,
+            self.font_handle_1.unwrap(),
+            "Outlined with a soft shadow",
+            &TextStyle {
+                outline: Some(TextOutline {
+                    thickness: 2,
+                    color: Color::new(40, 60, 200, 255),
+                }),
+                shadow: Some(TextShadow {
+                    offset: Vec2 { x: 4, y: 4 },
+                    color: Color::new(120, 120, 120, 160),
+                    blur: 3,
+                }),
+                ..TextStyle::new(40.0, Color::new(255, 255, 255, 255))
+            },
+            &TextBox::at(Vec2 { x: 10, y: 100 }),
+            &mut engine.screen,
+        );
+        self.time += elapsed_time.as_secs_f32();
+        draw_rich_text(
+            &mut engine.font_helper,
+            &engine.resource_manager,
+            self.dialogue.as_ref().unwrap(),
+            &TextStyle::new(32.0, Color::new(255, 255, 255, 255)),
+            &TextBox::at(Vec2 { x: 10, y: 680 }),
+            self.time,
+            &mut engine.screen,
+        );
         true
     }
     fn context(&self) -> &Context {
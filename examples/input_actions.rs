@@ -0,0 +1,156 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    drawing::fill_circle,
+    input::{AxisBinding, Binding},
+    run,
+    types::{Color, MouseButton, Vec2, VirtualKeyCode},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 4;
+const PIXELS_HEIGHT: u32 = 768 / 4;
+const BINDINGS_PATH: &str = "bindings.json";
+
+pub struct Demo {
+    ctx: Context,
+    position: (f32, f32),
+    jump: f32,
+    radius: u32,
+    rebinding: bool,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            position: (PIXELS_WIDTH as f32 / 2.0, PIXELS_HEIGHT as f32 / 2.0),
+            jump: 0.0,
+            radius: 8,
+            rebinding: false,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        let actions = &mut engine.actions;
+        actions.bind("jump", Binding::Key(VirtualKeyCode::Space));
+        actions.bind("jump", Binding::Mouse(MouseButton::Left));
+        actions.bind("rebind_jump", Binding::Key(VirtualKeyCode::R));
+        actions.bind("grow", Binding::Key(VirtualKeyCode::Equals));
+        actions.bind("grow", Binding::WheelUp);
+        actions.bind("shrink", Binding::Key(VirtualKeyCode::Minus));
+        actions.bind("shrink", Binding::WheelDown);
+        actions.bind("save", Binding::Key(VirtualKeyCode::F5));
+        actions.bind("load", Binding::Key(VirtualKeyCode::F9));
+        for (axis, negative, positive) in [
+            ("move_x", VirtualKeyCode::Left, VirtualKeyCode::Right),
+            ("move_x", VirtualKeyCode::A, VirtualKeyCode::D),
+            ("move_y", VirtualKeyCode::Up, VirtualKeyCode::Down),
+            ("move_y", VirtualKeyCode::W, VirtualKeyCode::S),
+        ] {
+            actions.bind_axis(
+                axis,
+                AxisBinding::Buttons {
+                    negative: Binding::Key(negative),
+                    positive: Binding::Key(positive),
+                },
+            );
+        }
+        // bindings saved by an earlier run replace the defaults above
+        if Path::new(BINDINGS_PATH).exists() {
+            if let Err(why) = actions.load(Path::new(BINDINGS_PATH)) {
+                eprintln!("{}", why);
+            }
+        }
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        let jump_keys = engine
+            .actions
+            .bindings("jump")
+            .iter()
+            .map(|binding| binding.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let title = if self.rebinding {
+            "press anything to jump with".to_string()
+        } else {
+            format!(
+                "{}ms, jump with {}, r to rebind, f5 to save, f9 to load",
+                elapsed_time.as_millis(),
+                jump_keys
+            )
+        };
+        engine.set_title(&title);
+
+        let actions = &mut engine.actions;
+        if self.rebinding {
+            if let Some(binding) = Binding::capture(&engine.input) {
+                actions.rebind("jump", &[binding]);
+                self.rebinding = false;
+            }
+        } else if actions.pressed("rebind_jump") {
+            self.rebinding = true;
+        }
+        if actions.pressed("save") {
+            if let Err(why) = actions.save(Path::new(BINDINGS_PATH)) {
+                eprintln!("{}", why);
+            }
+        }
+        if actions.pressed("load") {
+            if let Err(why) = actions.load(Path::new(BINDINGS_PATH)) {
+                eprintln!("{}", why);
+            }
+        }
+
+        let seconds = elapsed_time.as_secs_f32();
+        self.position.0 += actions.axis("move_x") * 80.0 * seconds;
+        self.position.1 += actions.axis("move_y") * 80.0 * seconds;
+        if actions.pressed("jump") && self.jump <= 0.0 {
+            self.jump = 1.0;
+        }
+        self.jump = (self.jump - seconds * 2.0).max(0.0);
+        if actions.repeated("grow") {
+            self.radius = (self.radius + 1).min(30);
+        }
+        if actions.repeated("shrink") {
+            self.radius = self.radius.saturating_sub(1).max(2);
+        }
+
+        engine.screen.clear(Color::new(30, 30, 40, 255));
+        let hop = (self.jump * std::f32::consts::PI).sin() * 30.0;
+        fill_circle(
+            Vec2::new(self.position.0 as i32, (self.position.1 - hop) as i32),
+            self.radius,
+            &mut engine.screen,
+            Color::new(120, 200, 255, 255),
+        );
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
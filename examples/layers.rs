@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use engine::{
+    drawing::{fill_circle, fill_rectangle, BlendMode},
+    layer::LayerHandle,
+    run,
+    types::{Color, Rect, Vec2, VirtualKeyCode},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 4;
+const PIXELS_HEIGHT: u32 = 768 / 4;
+const TILE: i32 = 16;
+
+pub struct Demo {
+    ctx: Context,
+    background: Option<LayerHandle>,
+    lighting: Option<LayerHandle>,
+    ui: Option<LayerHandle>,
+    scroll: i32,
+    drawn_scroll: Option<i32>,
+    time: f32,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            background: None,
+            lighting: None,
+            ui: None,
+            scroll: 0,
+            drawn_scroll: None,
+            time: 0.0,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        let layers = &mut engine.layers;
+        // under the screen, which is left transparent so it shows through
+        self.background = Some(layers.add(-1));
+        let lighting = layers.add(1);
+        layers.get_mut(lighting).unwrap().blend_mode = BlendMode::Multiply;
+        self.lighting = Some(lighting);
+        let ui = layers.add(2);
+        let layer = layers.get_mut(ui).unwrap();
+        layer.opacity = 0.8;
+        fill_rectangle(
+            Rect::new(Vec2::new(0, PIXELS_HEIGHT as i32 - 20), PIXELS_WIDTH, 20),
+            &mut layer.surface,
+            Color::new(20, 20, 60, 255),
+        );
+        self.ui = Some(ui);
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!(
+            "{}ms, left/right to scroll, l to toggle lighting",
+            elapsed_time.as_millis()
+        ));
+        self.time += elapsed_time.as_secs_f32();
+        if engine.input.key_held(VirtualKeyCode::Left) {
+            self.scroll -= 1;
+        }
+        if engine.input.key_held(VirtualKeyCode::Right) {
+            self.scroll += 1;
+        }
+        let layers = &mut engine.layers;
+        if engine.input.key_pressed(VirtualKeyCode::L) {
+            let lighting = layers.get_mut(self.lighting.unwrap()).unwrap();
+            lighting.visible = !lighting.visible;
+        }
+
+        // the checkerboard is only redrawn when it actually moved
+        if self.drawn_scroll != Some(self.scroll) {
+            let background = layers.get_mut(self.background.unwrap()).unwrap();
+            for y in 0..PIXELS_HEIGHT as i32 / TILE + 1 {
+                for x in -1..PIXELS_WIDTH as i32 / TILE + 1 {
+                    let shade = if (x + y + self.scroll.div_euclid(TILE)).rem_euclid(2) == 0 {
+                        70
+                    } else {
+                        90
+                    };
+                    let left = x * TILE - self.scroll.rem_euclid(TILE);
+                    fill_rectangle(
+                        Rect::new(Vec2::new(left, y * TILE), TILE as u32, TILE as u32),
+                        &mut background.surface,
+                        Color::new(shade, shade, shade + 20, 255),
+                    );
+                }
+            }
+            self.drawn_scroll = Some(self.scroll);
+        }
+
+        // a dark lighting layer with two lights moving across it
+        let lighting = layers.get_mut(self.lighting.unwrap()).unwrap();
+        lighting.clear(Color::new(60, 60, 90, 255));
+        for (speed, color) in [
+            (1.0_f32, Color::new(255, 230, 180, 255)),
+            (-0.7, Color::new(180, 220, 255, 255)),
+        ] {
+            let center = Vec2::new(
+                PIXELS_WIDTH as i32 / 2 + ((self.time * speed).cos() * 80.0) as i32,
+                PIXELS_HEIGHT as i32 / 2 + ((self.time * speed).sin() * 40.0) as i32,
+            );
+            fill_circle(center, 36, &mut lighting.surface, color);
+        }
+
+        // the screen itself only holds the moving sprite
+        engine.screen.clear(Color::new(0, 0, 0, 0));
+        let x = ((self.time * 2.0).sin() * 60.0) as i32 + PIXELS_WIDTH as i32 / 2;
+        fill_circle(
+            Vec2::new(x, PIXELS_HEIGHT as i32 / 2),
+            10,
+            &mut engine.screen,
+            Color::new(240, 80, 80, 255),
+        );
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
@@ -1,9 +1,13 @@
 use std::time::Duration;
 
 use engine::{
-    drawing::{draw_line, draw_rectangle_unchecked, draw_triangle, fill_rectangle_unchecked},
+    drawing::{
+        draw_arc, draw_circle, draw_ellipse, draw_line, draw_line_aa, draw_polyline,
+        draw_rectangle_unchecked, draw_triangle, fill_arc, fill_circle, fill_ellipse, fill_polygon,
+        fill_rectangle_unchecked, fill_triangle, LineCap, LineJoin, LineStyle,
+    },
     run,
-    types::{Color, Rect, Vec2},
+    types::{Color, Rect, Vec2, Vec2F},
     Context, Engine, GameState,
 };
 
@@ -26,6 +30,7 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: false,
+            fixed_timestep: None,
         };
         Self { ctx }
     }
@@ -33,9 +38,7 @@ impl Demo {
 
 impl GameState for Demo {
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
         let screen = &mut engine.screen;
         let r1 = Rect::new(Vec2 { x: 100, y: 100 }, 200, 400);
         let r2 = Rect::new(Vec2 { x: 150, y: 150 }, 300, 100);
@@ -53,6 +56,93 @@ impl GameState for Demo {
             screen,
             Color::new(255, 255, 0, 255),
         );
+        fill_triangle(
+            Vec2 { x: 600, y: 50 },
+            Vec2 { x: 700, y: 150 },
+            Vec2 { x: 550, y: 200 },
+            screen,
+            Color::new(255, 0, 255, 255),
+        );
+        fill_circle(
+            Vec2 { x: 800, y: 120 },
+            60,
+            screen,
+            Color::new(0, 200, 200, 255),
+        );
+        draw_circle(
+            Vec2 { x: 800, y: 120 },
+            70,
+            screen,
+            Color::new(255, 255, 255, 255),
+        );
+        fill_ellipse(
+            Vec2 { x: 600, y: 400 },
+            120,
+            50,
+            screen,
+            Color::new(200, 100, 0, 255),
+        );
+        draw_ellipse(
+            Vec2 { x: 600, y: 400 },
+            130,
+            60,
+            screen,
+            Color::new(255, 255, 255, 255),
+        );
+        fill_arc(
+            Vec2 { x: 850, y: 550 },
+            80,
+            0.0,
+            std::f32::consts::PI * 1.5,
+            screen,
+            Color::new(255, 200, 0, 255),
+        );
+        draw_arc(
+            Vec2 { x: 850, y: 550 },
+            90,
+            std::f32::consts::PI,
+            std::f32::consts::TAU,
+            screen,
+            Color::new(255, 255, 255, 255),
+        );
+        fill_polygon(
+            &[
+                Vec2 { x: 350, y: 500 },
+                Vec2 { x: 400, y: 600 },
+                Vec2 { x: 500, y: 600 },
+                Vec2 { x: 420, y: 660 },
+                Vec2 { x: 450, y: 750 },
+                Vec2 { x: 350, y: 690 },
+                Vec2 { x: 250, y: 750 },
+                Vec2 { x: 280, y: 660 },
+                Vec2 { x: 200, y: 600 },
+                Vec2 { x: 300, y: 600 },
+            ],
+            screen,
+            Color::new(255, 255, 100, 255),
+        );
+        draw_line_aa(
+            Vec2F::new(20.0, 740.0),
+            Vec2F::new(180.0, 520.0),
+            screen,
+            Color::new(255, 255, 255, 255),
+        );
+        draw_polyline(
+            &[
+                Vec2F::new(550.0, 520.0),
+                Vec2F::new(600.0, 700.0),
+                Vec2F::new(650.0, 560.0),
+                Vec2F::new(700.0, 720.0),
+            ],
+            &LineStyle {
+                width: 12.0,
+                cap: LineCap::Round,
+                join: LineJoin::Miter,
+                ..LineStyle::default()
+            },
+            screen,
+            Color::new(100, 200, 255, 160),
+        );
         true
     }
     fn context(&self) -> &Context {
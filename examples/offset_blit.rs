@@ -42,6 +42,7 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: true,
+            fixed_timestep: None,
         };
         let sprite_width = 16;
         let sprite_height = 16;
@@ -77,9 +78,7 @@ impl GameState for Demo {
         true
     }
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
         let screen = &mut engine.screen;
         let buf = screen.get_buf_mut();
         for i in (0..buf.len()).step_by(PIXEL_SIZE as usize) {
@@ -5,7 +5,7 @@ use engine::{
     drawing::draw_line,
     run,
     types::{Color, Vec2F as Vec2},
-    Context, Engine, GameState,
+    Context, Engine, FixedTimestep, GameState,
 };
 
 const SCREEN_WIDTH: u32 = 1024;
@@ -31,6 +31,7 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: false,
+            fixed_timestep: Some(FixedTimestep::from_hz(60)),
         };
         let num_drops: u32 = 2000;
         let mut rng = thread_rng();
@@ -62,16 +63,17 @@ impl Demo {
 
 impl GameState for Demo {
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
+        // drops only move in on_fixed_update, extrapolate them the rest of the way for smooth motion
+        let step = self.ctx.fixed_timestep.unwrap().step().as_secs_f32();
+        let fall = Vec2::new(0.0, GRAVITY * step * engine.interpolation());
         let screen = &mut engine.screen;
         screen.clear(Color::new(0, 0, 0, 255));
         for i in 0..700 {
             let droplet = self.raindrops[i];
             draw_line(
-                droplet.0.into(),
-                droplet.1.into(),
+                (droplet.0 + fall * 1.1).into(),
+                (droplet.1 + fall * 1.1).into(),
                 screen,
                 Color::new(100, 100, 150, 255),
             );
@@ -79,15 +81,18 @@ impl GameState for Demo {
         for i in 300..self.num_drops as usize {
             let droplet = self.raindrops[i];
             draw_line(
-                droplet.0.into(),
-                droplet.1.into(),
+                (droplet.0 + fall).into(),
+                (droplet.1 + fall).into(),
                 screen,
                 Color::new(50, 50, 100, 255),
             );
         }
+        true
+    }
+    fn on_fixed_update(&mut self, step: Duration, _engine: &mut Engine) -> bool {
         for i in 0..700 {
             let droplet = &mut self.raindrops[i];
-            let gravity = GRAVITY * elapsed_time.as_secs_f32() * 1.1;
+            let gravity = GRAVITY * step.as_secs_f32() * 1.1;
             droplet.0.y += gravity;
             droplet.1.y += gravity;
             if droplet.0.y >= SCREEN_HEIGHT as f32 {
@@ -99,7 +104,7 @@ impl GameState for Demo {
         }
         for i in 300..self.num_drops as usize {
             let droplet = &mut self.raindrops[i];
-            let gravity = GRAVITY * elapsed_time.as_secs_f32();
+            let gravity = GRAVITY * step.as_secs_f32();
             droplet.0.y += gravity;
             droplet.1.y += gravity;
             if droplet.0.y >= SCREEN_HEIGHT as f32 {
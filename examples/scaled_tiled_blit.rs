@@ -2,10 +2,10 @@ use std::path::Path;
 use std::time::Duration;
 
 use engine::{
-    drawing::blit,
+    drawing::{blit, draw_rectangle},
     resource::{ImageHandle, ImageResource},
     run,
-    types::Vec2,
+    types::{Color, Rect, Vec2},
     Context, Engine, GameState,
 };
 
@@ -31,6 +31,7 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: false,
+            fixed_timestep: None,
         };
         Self {
             ctx,
@@ -50,9 +51,16 @@ impl GameState for Demo {
         true
     }
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        let mouse = engine.mouse_position();
+        let delta = engine.mouse_delta();
+        engine.set_title(&format!(
+            "{}ms, mouse {:?} moved {:.1}, {:.1} wheel {}",
+            elapsed_time.as_millis(),
+            mouse.map(|mouse| (mouse.x as i32, mouse.y as i32)),
+            delta.x,
+            delta.y,
+            engine.mouse_wheel()
+        ));
         let screen = &mut engine.screen;
         let image_1 = engine
             .resource_manager
@@ -70,6 +78,19 @@ impl GameState for Demo {
                 );
             }
         }
+        // outline the tile under the cursor, which stays put however the window is sized
+        if let Some(mouse) = mouse {
+            let (width, height) = (image_1.width(), image_1.height());
+            let tile = Vec2::new(
+                mouse.x as i32 / width as i32 * width as i32,
+                mouse.y as i32 / height as i32 * height as i32,
+            );
+            draw_rectangle(
+                Rect::new(tile, width, height),
+                screen,
+                Color::new(255, 255, 0, 255),
+            );
+        }
         true
     }
     fn context(&self) -> &Context {
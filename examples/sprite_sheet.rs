@@ -0,0 +1,134 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    drawing::{draw_line, Transform},
+    resource::SpriteSheetHandle,
+    run,
+    sprite::{draw_frame, draw_frame_transformed, Grid},
+    types::{Color, Vec2, Vec2F},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 4;
+const PIXELS_HEIGHT: u32 = 768 / 4;
+
+pub struct Demo {
+    ctx: Context,
+    named_sheet: Option<SpriteSheetHandle>,
+    grid_sheet: Option<SpriteSheetHandle>,
+    aseprite_sheet: Option<SpriteSheetHandle>,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            named_sheet: None,
+            grid_sheet: None,
+            aseprite_sheet: None,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        let resource_manager = &mut engine.resource_manager;
+        self.named_sheet = Some(
+            resource_manager
+                .try_load_sprite_sheet_json(Path::new("resources/images/sprite_sheet.json"))
+                .unwrap(),
+        );
+        let grid = Grid {
+            pivot: Vec2F::new(8.0, 8.0),
+            ..Grid::new(16, 16)
+        };
+        self.grid_sheet = Some(
+            resource_manager
+                .try_load_sprite_sheet_grid(Path::new("resources/images/sprite_sheet.png"), &grid)
+                .unwrap(),
+        );
+        self.aseprite_sheet = Some(
+            resource_manager
+                .try_load_aseprite(Path::new("resources/images/slime.aseprite"))
+                .unwrap(),
+        );
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
+        let screen = &mut engine.screen;
+        screen.clear(Color::new(40, 40, 60, 255));
+        let resource_manager = &engine.resource_manager;
+        // frames by name, standing on their bottom center pivot
+        let named = resource_manager
+            .get_sprite_sheet(self.named_sheet.unwrap())
+            .unwrap();
+        let image = resource_manager.get_image(named.image).unwrap();
+        for (i, name) in ["walk_0", "walk_1", "walk_2", "walk_3"].iter().enumerate() {
+            let frame = named.frame_by_name(name).unwrap();
+            draw_frame(image, frame, screen, Vec2::new(40 + i as i32 * 24, 60));
+        }
+        // frames by index, scaled up around their center pivot
+        let grid = resource_manager
+            .get_sprite_sheet(self.grid_sheet.unwrap())
+            .unwrap();
+        let image = resource_manager.get_image(grid.image).unwrap();
+        for (i, frame) in grid.frames().iter().enumerate() {
+            let transform = Transform {
+                scale: Vec2F::new(3.0, 3.0),
+                ..Transform::default()
+            };
+            draw_frame_transformed(
+                image,
+                frame,
+                screen,
+                Vec2::new(40 + i as i32 * 56, 130),
+                &transform,
+            );
+        }
+        // every frame of the flattened Aseprite file, with each tag's frames underlined
+        let slime = resource_manager
+            .get_sprite_sheet(self.aseprite_sheet.unwrap())
+            .unwrap();
+        let image = resource_manager.get_image(slime.image).unwrap();
+        for (i, frame) in slime.frames().iter().enumerate() {
+            draw_frame(image, frame, screen, Vec2::new(40 + i as i32 * 24, 160));
+        }
+        for (row, tag) in slime.tags().iter().enumerate() {
+            let y = 180 + row as i32 * 3;
+            let left = 40 + tag.from as i32 * 24;
+            let right = 40 + tag.to as i32 * 24 + 15;
+            draw_line(
+                Vec2::new(left, y),
+                Vec2::new(right, y),
+                screen,
+                Color::new(255, 255, 255, 255),
+            );
+        }
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
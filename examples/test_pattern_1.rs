@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use engine::{resource::ImageResource, run, Context, Engine, GameState};
+use engine::{resource::ImageResource, run, types::VirtualKeyCode, Context, Engine, GameState};
 
 const SCREEN_WIDTH: u32 = 1024;
 const SCREEN_HEIGHT: u32 = 768;
@@ -21,16 +21,19 @@ impl Demo {
             screen_width: SCREEN_WIDTH,
             screen_height: SCREEN_HEIGHT,
             vsync_enabled: false,
+            fixed_timestep: None,
         };
         Self { ctx }
     }
 }
 
 impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.screenshot_key = Some(VirtualKeyCode::F12);
+        true
+    }
     fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
-        engine
-            .window
-            .set_title(&format!("{}ms", elapsed_time.as_millis()));
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
         let buf = engine.screen.get_buf_mut();
         for (i, pixel) in buf.chunks_exact_mut(4).enumerate() {
             let rgba: [u8; 4] = [
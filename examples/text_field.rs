@@ -0,0 +1,128 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    resource::FontHandle,
+    run,
+    text::{TextBox, TextStyle},
+    text_input::TextField,
+    types::{Color, FontSettings, Rect, Vec2},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 2;
+const PIXELS_HEIGHT: u32 = 768 / 2;
+const LINE_HEIGHT: i32 = 16;
+
+pub struct Demo {
+    ctx: Context,
+    font: Option<FontHandle>,
+    name: TextField,
+    console: TextField,
+    log: Vec<String>,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        let mut name = TextField::new(Rect::new(Vec2::new(80, 20), 160, 20));
+        name.max_length = Some(16);
+        name.focused = true;
+        let console = TextField::new(Rect::new(
+            Vec2::new(10, PIXELS_HEIGHT as i32 - 30),
+            PIXELS_WIDTH - 20,
+            20,
+        ));
+        Self {
+            ctx,
+            font: None,
+            name,
+            console,
+            log: Vec::new(),
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        self.font = Some(
+            engine
+                .resource_manager
+                .try_load_font(
+                    Path::new("resources/fonts/JetbrainsMonoRegular.ttf"),
+                    FontSettings::default(),
+                )
+                .unwrap(),
+        );
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!(
+            "{}ms, click a field to type, enter to submit",
+            elapsed_time.as_millis()
+        ));
+        if self.name.update(engine) {
+            self.log.push(format!("hello, {}", self.name.text()));
+        }
+        if self.console.update(engine) {
+            self.log.push(format!("> {}", self.console.text()));
+            self.console.set_text("");
+        }
+
+        engine.screen.clear(Color::new(30, 30, 40, 255));
+        let font = self.font.unwrap();
+        let style = TextStyle::new(14.0, Color::new(230, 230, 230, 255));
+        engine.font_helper.draw_text(
+            &engine.resource_manager,
+            font,
+            "Name:",
+            &style,
+            &TextBox::at(Vec2::new(20, 22)),
+            &mut engine.screen,
+        );
+        for field in [&self.name, &self.console] {
+            field.draw(
+                &mut engine.font_helper,
+                &engine.resource_manager,
+                font,
+                &style,
+                &mut engine.screen,
+            );
+        }
+        // the most recent lines, oldest at the top
+        let lines = self.log.iter().rev().take(15).rev();
+        for (i, line) in lines.enumerate() {
+            engine.font_helper.draw_text(
+                &engine.resource_manager,
+                font,
+                line,
+                &style,
+                &TextBox::at(Vec2::new(10, 60 + i as i32 * LINE_HEIGHT)),
+                &mut engine.screen,
+            );
+        }
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
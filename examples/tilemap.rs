@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    run,
+    tilemap::Tilemap,
+    types::{Color, Vec2, VirtualKeyCode},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 4;
+const PIXELS_HEIGHT: u32 = 768 / 4;
+const SCROLL_SPEED: f32 = 60.0;
+
+pub struct Demo {
+    ctx: Context,
+    maps: Vec<Tilemap>,
+    current: usize,
+    camera: (f32, f32),
+    time: Duration,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            maps: Vec::new(),
+            current: 0,
+            camera: (-32.0, -32.0),
+            time: Duration::ZERO,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        for path in [
+            "resources/maps/orthogonal.tmx",
+            "resources/maps/isometric.tmx",
+        ] {
+            self.maps
+                .push(Tilemap::open(Path::new(path), &mut engine.resource_manager).unwrap());
+        }
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!(
+            "{}ms, arrows to scroll, space to switch maps",
+            elapsed_time.as_millis()
+        ));
+        self.time += elapsed_time;
+        let input = &engine.input;
+        if input.key_pressed(VirtualKeyCode::Space) {
+            self.current = (self.current + 1) % self.maps.len();
+        }
+        let step = SCROLL_SPEED * elapsed_time.as_secs_f32();
+        if input.key_held(VirtualKeyCode::Left) {
+            self.camera.0 -= step;
+        }
+        if input.key_held(VirtualKeyCode::Right) {
+            self.camera.0 += step;
+        }
+        if input.key_held(VirtualKeyCode::Up) {
+            self.camera.1 -= step;
+        }
+        if input.key_held(VirtualKeyCode::Down) {
+            self.camera.1 += step;
+        }
+
+        let map = &self.maps[self.current];
+        let background = map.background_color.unwrap_or(Color::new(20, 20, 30, 255));
+        engine.screen.clear(background);
+        // only the tiles under the screen get drawn, so the camera can go anywhere
+        let camera = Vec2::new(self.camera.0 as i32, self.camera.1 as i32);
+        map.draw(
+            &engine.resource_manager,
+            &mut engine.screen,
+            camera,
+            self.time,
+        );
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
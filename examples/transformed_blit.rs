@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::time::Duration;
+
+use engine::{
+    drawing::{blit_transformed, Flip, Sampling, Transform},
+    resource::{ImageHandle, ImageResource},
+    run,
+    types::{Color, Rect, Vec2, Vec2F},
+    Context, Engine, GameState,
+};
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+const PIXELS_WIDTH: u32 = 1024 / 2;
+const PIXELS_HEIGHT: u32 = 768 / 2;
+
+pub struct Demo {
+    ctx: Context,
+    image_handle_1: Option<ImageHandle>,
+    angle: f32,
+}
+
+impl Default for Demo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        let ctx = Context {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            vsync_enabled: true,
+            fixed_timestep: None,
+        };
+        Self {
+            ctx,
+            image_handle_1: None,
+            angle: 0.0,
+        }
+    }
+}
+
+impl GameState for Demo {
+    fn on_create(&mut self, engine: &mut Engine) -> bool {
+        engine.resize_buffer(PIXELS_WIDTH, PIXELS_HEIGHT);
+        self.image_handle_1 = Some(
+            engine
+                .resource_manager
+                .load_image(Path::new("resources/images/test_logo.png")),
+        );
+        true
+    }
+    fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+        engine.set_title(&format!("{}ms", elapsed_time.as_millis()));
+        self.angle += elapsed_time.as_secs_f32();
+        let screen = &mut engine.screen;
+        screen.clear(Color::new(40, 40, 60, 255));
+        let image_1 = engine
+            .resource_manager
+            .get_image(self.image_handle_1.unwrap())
+            .unwrap();
+        let rect = Rect::new(Vec2::new(0, 0), image_1.width(), image_1.height());
+        let pivot = Vec2F::new(image_1.width() as f32 / 2.0, image_1.height() as f32 / 2.0);
+        let pulse = 1.0 + self.angle.sin() * 0.5;
+        // left spins with nearest neighbour sampling, right is squashed, flipped and bilinear
+        blit_transformed(
+            image_1,
+            rect,
+            screen,
+            Vec2::new((PIXELS_WIDTH / 4) as i32, (PIXELS_HEIGHT / 2) as i32),
+            &Transform {
+                rotation: self.angle,
+                pivot,
+                ..Transform::default()
+            },
+        );
+        blit_transformed(
+            image_1,
+            rect,
+            screen,
+            Vec2::new((PIXELS_WIDTH * 3 / 4) as i32, (PIXELS_HEIGHT / 2) as i32),
+            &Transform {
+                rotation: -self.angle,
+                scale: Vec2F::new(pulse, 2.0 - pulse),
+                pivot,
+                flip: Flip {
+                    horizontal: true,
+                    vertical: false,
+                },
+                sampling: Sampling::Bilinear,
+                ..Transform::default()
+            },
+        );
+        true
+    }
+    fn context(&self) -> &Context {
+        &self.ctx
+    }
+}
+
+fn main() {
+    let game_state = Demo::new();
+    run(game_state);
+}
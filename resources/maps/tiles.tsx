@@ -0,0 +1,19 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="tiles" tilewidth="16" tileheight="16" spacing="1" margin="1" tilecount="8" columns="4">
+ <properties>
+  <property name="author" value="engine"/>
+ </properties>
+ <image source="tiles.png" trans="ff00ff" width="69" height="35"/>
+ <tile id="1" type="wall">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="friction" type="float" value="0.5"/>
+  </properties>
+ </tile>
+ <tile id="3">
+  <animation>
+   <frame tileid="3" duration="200"/>
+   <frame tileid="4" duration="200"/>
+  </animation>
+ </tile>
+</tileset>
@@ -0,0 +1,405 @@
+use std::time::Duration;
+
+use crate::drawing::{Flip, Transform};
+use crate::resource::{ImageResource, ResourceError, ResourceManager, SpriteSheetHandle};
+use crate::sprite::{draw_frame, draw_frame_transformed, AnimationDirection, SpriteSheet};
+use crate::timer::Timer;
+use crate::types::{Color, Vec2};
+
+/// Used for frames that were not exported with a duration of their own
+pub const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(100);
+
+/// What happens when an animation runs past its last frame
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum PlayMode {
+    #[default]
+    Loop,
+    /// Stop on the last frame
+    Once,
+    /// Play back and forth, without repeating the end frames
+    PingPong,
+}
+
+/// Fired by `Animation::update` whenever the frame an event was added to is shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationEvent {
+    pub name: String,
+    /// Position in the animation's frame sequence, not the sprite sheet index
+    pub step: usize,
+}
+
+/// Steps through frames of a sprite sheet, showing each for its own duration
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub sheet: SpriteSheetHandle,
+    pub mode: PlayMode,
+    /// Multiplies elapsed time, 2.0 plays twice as fast, 0.0 or below holds the current frame
+    pub speed: f32,
+    pub flip: Flip,
+    /// Multiplied into every drawn pixel, white leaves the frames as they are
+    pub tint: Color,
+    frames: Vec<usize>,
+    durations: Vec<Duration>,
+    events: Vec<(usize, String)>,
+    timer: Timer,
+    step: usize,
+    backwards: bool,
+    paused: bool,
+    finished: bool,
+    /// Whether the events of the current step still need to be fired
+    entered: bool,
+}
+
+impl Animation {
+    /// Play the sheet frames at the given indices in order. Durations come from the frames,
+    /// falling back to `DEFAULT_FRAME_DURATION`. Indices that are not on the sheet are dropped.
+    pub fn new(
+        handle: SpriteSheetHandle,
+        sheet: &SpriteSheet,
+        frames: impl IntoIterator<Item = usize>,
+        mode: PlayMode,
+    ) -> Self {
+        let (frames, durations): (Vec<_>, Vec<_>) = frames
+            .into_iter()
+            .filter_map(|index| {
+                let frame = sheet.frame(index)?;
+                Some((index, frame.duration.unwrap_or(DEFAULT_FRAME_DURATION)))
+            })
+            .unzip();
+        let first = durations.first().copied();
+        Self {
+            sheet: handle,
+            mode,
+            speed: 1.0,
+            flip: Flip::default(),
+            tint: Color::new(255, 255, 255, 255),
+            frames,
+            durations,
+            events: Vec::new(),
+            timer: Timer::new(frame_length(first), false),
+            step: 0,
+            backwards: false,
+            paused: false,
+            finished: false,
+            entered: true,
+        }
+    }
+    /// Every frame of the sheet, in order
+    pub fn from_sheet(handle: SpriteSheetHandle, sheet: &SpriteSheet, mode: PlayMode) -> Self {
+        Self::new(handle, sheet, 0..sheet.len(), mode)
+    }
+    /// The frames of a sheet tag, in its direction. Reverse tags loop backwards, ping-pong tags
+    /// play in `PlayMode::PingPong`. Tags that repeat a fixed number of times still loop.
+    pub fn from_tag(handle: SpriteSheetHandle, sheet: &SpriteSheet, tag: &str) -> Option<Self> {
+        let tag = sheet.tag(tag)?;
+        let range = tag.from..=tag.to;
+        Some(match tag.direction {
+            AnimationDirection::Forward => Self::new(handle, sheet, range, PlayMode::Loop),
+            AnimationDirection::Reverse => Self::new(handle, sheet, range.rev(), PlayMode::Loop),
+            AnimationDirection::PingPong => Self::new(handle, sheet, range, PlayMode::PingPong),
+            AnimationDirection::PingPongReverse => {
+                Self::new(handle, sheet, range.rev(), PlayMode::PingPong)
+            }
+        })
+    }
+    /// Fire an event named `name` every time the frame at `step` of the sequence is shown
+    pub fn add_event(&mut self, step: usize, name: &str) {
+        self.events.push((step, name.to_owned()));
+    }
+    /// Override how long the frame at `step` is shown
+    pub fn set_duration(&mut self, step: usize, duration: Duration) {
+        if let Some(old) = self.durations.get_mut(step) {
+            *old = duration;
+            if step == self.step {
+                self.timer.length = frame_length(Some(duration));
+            }
+        }
+    }
+    /// Advance by `elapsed`, scaled by `speed`, returning the events of every frame shown on the
+    /// way in order. Frames shorter than `elapsed` are skipped over but still fire their events.
+    pub fn update(&mut self, elapsed: Duration) -> Vec<AnimationEvent> {
+        let mut fired = Vec::new();
+        if self.frames.is_empty() || self.paused {
+            return fired;
+        }
+        if self.entered {
+            self.entered = false;
+            self.fire(&mut fired);
+        }
+        if self.finished || self.speed <= 0.0 {
+            return fired;
+        }
+        // scaled in whole nanoseconds so frame boundaries are hit exactly
+        let scaled = elapsed.as_nanos() as f64 * self.speed as f64;
+        self.timer
+            .update(Duration::from_nanos(scaled.round() as u64));
+        while self.timer.done {
+            if !self.advance() {
+                self.finished = true;
+                self.timer.acc = self.timer.length;
+                break;
+            }
+            self.timer.acc -= self.timer.length;
+            self.timer.length = frame_length(Some(self.durations[self.step]));
+            self.timer.done = self.timer.acc >= self.timer.length;
+            self.fire(&mut fired);
+        }
+        fired
+    }
+    fn fire(&self, fired: &mut Vec<AnimationEvent>) {
+        fired.extend(
+            self.events
+                .iter()
+                .filter(|(step, _)| *step == self.step)
+                .map(|(step, name)| AnimationEvent {
+                    name: name.clone(),
+                    step: *step,
+                }),
+        );
+    }
+    /// Move to the next step, returning false when a `Once` animation has run out of frames
+    fn advance(&mut self) -> bool {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            PlayMode::Loop => self.step = if self.step >= last { 0 } else { self.step + 1 },
+            PlayMode::Once if self.step >= last => return false,
+            PlayMode::Once => self.step += 1,
+            PlayMode::PingPong if last == 0 => {}
+            PlayMode::PingPong => {
+                if self.step == last {
+                    self.backwards = true;
+                } else if self.step == 0 {
+                    self.backwards = false;
+                }
+                self.step = if self.backwards {
+                    self.step - 1
+                } else {
+                    self.step + 1
+                };
+            }
+        }
+        true
+    }
+    /// Go back to the first frame and play from there
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.backwards = false;
+        self.finished = false;
+        self.paused = false;
+        self.entered = true;
+        self.timer = Timer::new(frame_length(self.durations.first().copied()), false);
+    }
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Whether a `Once` animation has shown its last frame for its full duration
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    /// Position in the frame sequence
+    pub fn step(&self) -> usize {
+        self.step
+    }
+    /// Number of steps in the frame sequence
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    /// Sprite sheet index of the frame being shown
+    pub fn current_frame(&self) -> Option<usize> {
+        self.frames.get(self.step).copied()
+    }
+    /// Draw the current frame with its pivot at `position`, flipped around the pivot and tinted
+    pub fn draw(
+        &self,
+        resource_manager: &ResourceManager,
+        dst: &mut impl ImageResource,
+        position: Vec2,
+    ) -> Result<(), ResourceError> {
+        let sheet = resource_manager.try_get_sprite_sheet(self.sheet)?;
+        let image = resource_manager.try_get_image(sheet.image)?;
+        let frame = match self.current_frame().and_then(|index| sheet.frame(index)) {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        if self.flip == Flip::default() && self.tint == Color::new(255, 255, 255, 255) {
+            draw_frame(image, frame, dst, position);
+            return Ok(());
+        }
+        // the pivot is given in flipped coordinates, mirror it to keep the sprite in place
+        let mut frame = frame.clone();
+        if self.flip.horizontal {
+            frame.pivot.x = frame.rect.width as f32 - frame.pivot.x;
+        }
+        if self.flip.vertical {
+            frame.pivot.y = frame.rect.height as f32 - frame.pivot.y;
+        }
+        let transform = Transform {
+            flip: self.flip,
+            tint: self.tint,
+            ..Transform::default()
+        };
+        draw_frame_transformed(image, &frame, dst, position, &transform);
+        Ok(())
+    }
+}
+
+/// Timer length for a frame, zero length frames would never let `update` finish
+fn frame_length(duration: Option<Duration>) -> Duration {
+    duration
+        .unwrap_or(DEFAULT_FRAME_DURATION)
+        .max(Duration::from_millis(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Image;
+    use crate::sprite::{AnimationTag, Frame};
+    use crate::types::Rect;
+    use crate::types::Vec2F;
+
+    /// Four 1x1 frames of a 4x1 image, 10ms each, colored red, green, blue and white
+    fn sheet(resource_manager: &mut ResourceManager) -> SpriteSheetHandle {
+        let buf = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 255, 255],
+        ]
+        .concat();
+        let image = resource_manager.add_image(Image::new(4, 1, buf));
+        let mut sheet = SpriteSheet::new(image);
+        for x in 0..4 {
+            sheet.add_frame(Frame {
+                rect: Rect::new(Vec2::new(x, 0), 1, 1),
+                pivot: Vec2F::new(0.0, 0.0),
+                duration: Some(Duration::from_millis(10)),
+            });
+        }
+        sheet.add_tag(AnimationTag {
+            name: "back".to_owned(),
+            from: 1,
+            to: 3,
+            direction: AnimationDirection::PingPongReverse,
+            repeat: 0,
+        });
+        resource_manager.add_sprite_sheet(sheet)
+    }
+
+    fn steps(animation: &mut Animation, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                animation.update(Duration::from_millis(10));
+                animation.current_frame().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_play_modes() {
+        let mut resource_manager = ResourceManager::new();
+        let handle = sheet(&mut resource_manager);
+        let sheet = resource_manager.get_sprite_sheet(handle).unwrap();
+
+        let mut looping = Animation::from_sheet(handle, sheet, PlayMode::Loop);
+        assert_eq!(steps(&mut looping, 6), [1, 2, 3, 0, 1, 2]);
+
+        let mut once = Animation::new(handle, sheet, [2, 0, 1], PlayMode::Once);
+        assert_eq!(steps(&mut once, 2), [0, 1]);
+        assert!(!once.is_finished());
+        assert_eq!(steps(&mut once, 2), [1, 1]);
+        assert!(once.is_finished());
+        once.restart();
+        assert_eq!(once.current_frame(), Some(2));
+
+        let mut ping_pong = Animation::from_tag(handle, sheet, "back").unwrap();
+        assert_eq!(ping_pong.current_frame(), Some(3));
+        assert_eq!(steps(&mut ping_pong, 6), [2, 1, 2, 3, 2, 1]);
+        assert!(Animation::from_tag(handle, sheet, "nope").is_none());
+    }
+
+    #[test]
+    fn test_speed_pause_and_events() {
+        let mut resource_manager = ResourceManager::new();
+        let handle = sheet(&mut resource_manager);
+        let sheet = resource_manager.get_sprite_sheet(handle).unwrap();
+        let mut animation = Animation::from_sheet(handle, sheet, PlayMode::Loop);
+        animation.add_event(0, "start");
+        animation.add_event(2, "step");
+        animation.set_duration(1, Duration::from_millis(30));
+
+        let names = |events: Vec<AnimationEvent>| {
+            events
+                .into_iter()
+                .map(|event| event.name)
+                .collect::<Vec<_>>()
+        };
+        // the first frame fires when first updated, then 10ms at double speed skips a frame
+        animation.speed = 2.0;
+        assert_eq!(names(animation.update(Duration::from_millis(5))), ["start"]);
+        assert_eq!(animation.step(), 1);
+        assert_eq!(names(animation.update(Duration::from_millis(15))), ["step"]);
+        assert_eq!(animation.step(), 2);
+        animation.pause();
+        assert!(animation.update(Duration::from_secs(1)).is_empty());
+        assert_eq!(animation.step(), 2);
+        animation.resume();
+        animation.speed = 1.0;
+        assert_eq!(
+            names(animation.update(Duration::from_millis(20))),
+            ["start"]
+        );
+        assert_eq!(animation.step(), 0);
+        animation.speed = 0.0;
+        animation.update(Duration::from_secs(1));
+        assert_eq!(animation.step(), 0);
+    }
+
+    #[test]
+    fn test_draw_flipped_and_tinted() {
+        let mut resource_manager = ResourceManager::new();
+        let image = resource_manager.add_image(Image::new(
+            2,
+            1,
+            [[255, 255, 255, 255], [255, 0, 0, 255]].concat(),
+        ));
+        let mut sheet = SpriteSheet::new(image);
+        sheet.add_frame(Frame {
+            rect: Rect::new(Vec2::new(0, 0), 2, 1),
+            pivot: Vec2F::new(0.0, 0.0),
+            duration: None,
+        });
+        let handle = resource_manager.add_sprite_sheet(sheet);
+        let sheet = resource_manager.get_sprite_sheet(handle).unwrap();
+        let mut animation = Animation::from_sheet(handle, sheet, PlayMode::Once);
+
+        let mut dst = Image::blank(2, 1);
+        animation
+            .draw(&resource_manager, &mut dst, Vec2::new(0, 0))
+            .unwrap();
+        assert_eq!(dst.buf, [255, 255, 255, 255, 255, 0, 0, 255]);
+
+        // flipping mirrors the frame around its pivot on the left edge
+        animation.flip.horizontal = true;
+        animation.tint = Color::new(0, 255, 255, 255);
+        let mut dst = Image::blank(2, 1);
+        animation
+            .draw(&resource_manager, &mut dst, Vec2::new(2, 0))
+            .unwrap();
+        assert_eq!(dst.buf, [0, 0, 0, 255, 0, 255, 255, 255]);
+
+        resource_manager.delete_sprite_sheet(handle);
+        assert!(animation
+            .draw(&resource_manager, &mut dst, Vec2::new(0, 0))
+            .is_err());
+    }
+}
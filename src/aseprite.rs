@@ -0,0 +1,819 @@
+use std::fs::read;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::resource::{Image, ImageHandle, ResourceError};
+use crate::sprite::{AnimationDirection, AnimationTag, Frame, SpriteSheet};
+use crate::types::{Rect, Vec2, Vec2F};
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_OLD_PALETTE_6BIT: u16 = 0x0011;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+/// Layer blend modes, in the order Aseprite stores them
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AsepriteBlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+    Addition,
+    Subtract,
+    Divide,
+}
+
+impl AsepriteBlendMode {
+    fn from_u16(mode: u16) -> Option<Self> {
+        use AsepriteBlendMode::*;
+        let modes = [
+            Normal, Multiply, Screen, Overlay, Darken, Lighten, ColorDodge, ColorBurn, HardLight,
+            SoftLight, Difference, Exclusion, Hue, Saturation, Color, Luminosity, Addition,
+            Subtract, Divide,
+        ];
+        modes.get(mode as usize).copied()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsepriteLayerKind {
+    Image,
+    /// Only affects the visibility of the layers inside it
+    Group,
+    /// Tilemap layers are skipped when flattening
+    Tilemap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteLayer {
+    pub name: String,
+    pub kind: AsepriteLayerKind,
+    /// Visible in the file, taking hidden parent groups into account
+    pub visible: bool,
+    pub background: bool,
+    pub blend_mode: AsepriteBlendMode,
+    pub opacity: u8,
+}
+
+/// A frame with all visible layers flattened into one image
+#[derive(Clone)]
+pub struct AsepriteFrame {
+    pub image: Image,
+    pub duration: Duration,
+}
+
+/// The contents of an `.ase`/`.aseprite` file
+#[derive(Clone)]
+pub struct Aseprite {
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<AsepriteLayer>,
+    pub frames: Vec<AsepriteFrame>,
+    pub tags: Vec<AnimationTag>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorDepth {
+    Rgba,
+    Grayscale,
+    Indexed,
+}
+
+impl ColorDepth {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba => 4,
+            Self::Grayscale => 2,
+            Self::Indexed => 1,
+        }
+    }
+}
+
+/// Cel pixels as stored in the file, converted to RGBA while flattening
+#[derive(Debug, Clone)]
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    z_index: i16,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], &'static str> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("unexpected end of data")?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+    fn skip(&mut self, count: usize) -> Result<(), &'static str> {
+        self.bytes(count).map(|_| ())
+    }
+    fn byte(&mut self) -> Result<u8, &'static str> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn word(&mut self) -> Result<u16, &'static str> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    fn short(&mut self) -> Result<i16, &'static str> {
+        Ok(self.word()? as i16)
+    }
+    fn dword(&mut self) -> Result<u32, &'static str> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    fn string(&mut self) -> Result<String, &'static str> {
+        let length = self.word()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec()).map_err(|_| "invalid utf-8 in string")
+    }
+}
+
+impl Aseprite {
+    pub fn open(path: &Path) -> Result<Self, ResourceError> {
+        let data = read(path).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&data).map_err(|reason| ResourceError::Parse {
+            path: path.to_path_buf(),
+            reason: reason.to_owned(),
+        })
+    }
+    pub fn parse(data: &[u8]) -> Result<Self, &'static str> {
+        let mut header = Reader::new(data);
+        header.skip(4)?;
+        if header.word()? != HEADER_MAGIC {
+            return Err("not an aseprite file");
+        }
+        let frame_count = header.word()? as usize;
+        let width = header.word()? as u32;
+        let height = header.word()? as u32;
+        let depth = match header.word()? {
+            32 => ColorDepth::Rgba,
+            16 => ColorDepth::Grayscale,
+            8 => ColorDepth::Indexed,
+            _ => return Err("unsupported color depth"),
+        };
+        let layer_opacity_valid = header.dword()? & 1 != 0;
+        header.skip(2 + 4 + 4)?;
+        let transparent_index = header.byte()?;
+        header.skip(HEADER_SIZE - header.position)?;
+
+        let mut layers = Vec::new();
+        // effective visibility of the most recent layer at each child level
+        let mut visible_at_level: Vec<bool> = Vec::new();
+        let mut palette = vec![[0, 0, 0, 255]; 256];
+        let mut has_new_palette = false;
+        let mut tags = Vec::new();
+        let mut durations = Vec::with_capacity(frame_count.min(u16::MAX as usize));
+        let mut frame_cels: Vec<Vec<Cel>> = Vec::with_capacity(durations.capacity());
+
+        let mut reader = header;
+        for _ in 0..frame_count {
+            let frame_start = reader.position;
+            let frame_size = reader.dword()? as usize;
+            if reader.word()? != FRAME_MAGIC {
+                return Err("bad frame magic number");
+            }
+            let old_chunk_count = reader.word()? as u32;
+            durations.push(Duration::from_millis(reader.word()? as u64));
+            reader.skip(2)?;
+            let chunk_count = match reader.dword()? {
+                0 => old_chunk_count,
+                count => count,
+            };
+            let mut cels = Vec::new();
+            for _ in 0..chunk_count {
+                let chunk_start = reader.position;
+                let chunk_size = reader.dword()? as usize;
+                let chunk_type = reader.word()?;
+                if chunk_size < 6 {
+                    return Err("chunk too small");
+                }
+                let mut chunk = Reader::new(reader.bytes(chunk_size - 6)?);
+                match chunk_type {
+                    CHUNK_LAYER => {
+                        let flags = chunk.word()?;
+                        let kind = match chunk.word()? {
+                            0 => AsepriteLayerKind::Image,
+                            1 => AsepriteLayerKind::Group,
+                            _ => AsepriteLayerKind::Tilemap,
+                        };
+                        let level = chunk.word()? as usize;
+                        chunk.skip(4)?;
+                        let blend_mode = AsepriteBlendMode::from_u16(chunk.word()?)
+                            .ok_or("unknown blend mode")?;
+                        let opacity = chunk.byte()?;
+                        chunk.skip(3)?;
+                        let name = chunk.string()?;
+                        let parent_visible = match level {
+                            0 => true,
+                            level => *visible_at_level
+                                .get(level - 1)
+                                .ok_or("layer without a parent group")?,
+                        };
+                        let visible = flags & 1 != 0 && parent_visible;
+                        visible_at_level.truncate(level);
+                        visible_at_level.push(visible);
+                        layers.push(AsepriteLayer {
+                            name,
+                            kind,
+                            visible,
+                            background: flags & 8 != 0,
+                            blend_mode,
+                            opacity: if layer_opacity_valid { opacity } else { 255 },
+                        });
+                    }
+                    CHUNK_CEL => {
+                        if let Some(cel) = parse_cel(&mut chunk, depth, &frame_cels)? {
+                            cels.push(cel);
+                        }
+                    }
+                    CHUNK_TAGS => {
+                        let count = chunk.word()?;
+                        chunk.skip(8)?;
+                        for _ in 0..count {
+                            let from = chunk.word()? as usize;
+                            let to = chunk.word()? as usize;
+                            let direction = match chunk.byte()? {
+                                0 => AnimationDirection::Forward,
+                                1 => AnimationDirection::Reverse,
+                                2 => AnimationDirection::PingPong,
+                                3 => AnimationDirection::PingPongReverse,
+                                _ => return Err("unknown tag direction"),
+                            };
+                            let repeat = chunk.word()? as u32;
+                            chunk.skip(6 + 3 + 1)?;
+                            let name = chunk.string()?;
+                            if from > to || to >= frame_count {
+                                return Err("tag frames out of range");
+                            }
+                            tags.push(AnimationTag {
+                                name,
+                                from,
+                                to,
+                                direction,
+                                repeat,
+                            });
+                        }
+                    }
+                    CHUNK_PALETTE => {
+                        has_new_palette = true;
+                        let size = chunk.dword()? as usize;
+                        let first = chunk.dword()? as usize;
+                        let last = chunk.dword()? as usize;
+                        chunk.skip(8)?;
+                        if first > last || last >= size.max(256) {
+                            return Err("palette range out of bounds");
+                        }
+                        palette.resize(palette.len().max(size), [0, 0, 0, 255]);
+                        for entry in &mut palette[first..=last] {
+                            let flags = chunk.word()?;
+                            entry.copy_from_slice(chunk.bytes(4)?);
+                            if flags & 1 != 0 {
+                                chunk.string()?;
+                            }
+                        }
+                    }
+                    CHUNK_OLD_PALETTE | CHUNK_OLD_PALETTE_6BIT if !has_new_palette => {
+                        let scale = |value: u8| match chunk_type {
+                            CHUNK_OLD_PALETTE => value,
+                            _ => (value.min(63) as u32 * 255 / 63) as u8,
+                        };
+                        let mut index = 0;
+                        for _ in 0..chunk.word()? {
+                            index += chunk.byte()? as usize;
+                            let count = match chunk.byte()? {
+                                0 => 256,
+                                count => count as usize,
+                            };
+                            for _ in 0..count {
+                                let rgb = chunk.bytes(3)?;
+                                if let Some(entry) = palette.get_mut(index) {
+                                    *entry = [scale(rgb[0]), scale(rgb[1]), scale(rgb[2]), 255];
+                                }
+                                index += 1;
+                            }
+                        }
+                    }
+                    // slices, user data, color profiles and the like don't affect the pixels
+                    _ => {}
+                }
+                reader.position = chunk_start + chunk_size;
+            }
+            if reader.position - frame_start != frame_size {
+                return Err("frame size does not match its chunks");
+            }
+            frame_cels.push(cels);
+        }
+
+        let frames = frame_cels
+            .iter()
+            .zip(durations)
+            .map(|(cels, duration)| {
+                let image = flatten(
+                    width,
+                    height,
+                    &layers,
+                    cels,
+                    depth,
+                    &palette,
+                    transparent_index,
+                );
+                AsepriteFrame { image, duration }
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            layers,
+            frames,
+            tags,
+        })
+    }
+    /// All frames side by side, left to right, as laid out by `sprite_sheet`
+    pub fn sheet_image(&self) -> Image {
+        let sheet_width = (self.width * self.frames.len() as u32) as usize;
+        let row_size = self.width as usize * 4;
+        let mut buf = vec![0; sheet_width * self.height as usize * 4];
+        for (i, frame) in self.frames.iter().enumerate() {
+            for (y, row) in frame.image.buf.chunks_exact(row_size).enumerate() {
+                let start = (y * sheet_width + i * self.width as usize) * 4;
+                buf[start..start + row_size].copy_from_slice(row);
+            }
+        }
+        Image::new(sheet_width as u32, self.height, buf)
+    }
+    /// Frames and tags for `image`, which is expected to hold `sheet_image`
+    pub fn sprite_sheet(&self, image: ImageHandle) -> SpriteSheet {
+        let mut sheet = SpriteSheet::new(image);
+        for (i, frame) in self.frames.iter().enumerate() {
+            sheet.add_frame(Frame {
+                rect: Rect::new(
+                    Vec2::new((i as u32 * self.width) as i32, 0),
+                    self.width,
+                    self.height,
+                ),
+                pivot: Vec2F::new(0.0, 0.0),
+                duration: Some(frame.duration),
+            });
+        }
+        for tag in &self.tags {
+            sheet.add_tag(tag.clone());
+        }
+        sheet
+    }
+}
+
+fn parse_cel(
+    chunk: &mut Reader,
+    depth: ColorDepth,
+    previous_frames: &[Vec<Cel>],
+) -> Result<Option<Cel>, &'static str> {
+    let layer = chunk.word()? as usize;
+    let x = chunk.short()? as i32;
+    let y = chunk.short()? as i32;
+    let opacity = chunk.byte()?;
+    let cel_type = chunk.word()?;
+    let z_index = chunk.short()?;
+    chunk.skip(5)?;
+    let (width, height, pixels) = match cel_type {
+        0 => {
+            let (width, height) = (chunk.word()? as u32, chunk.word()? as u32);
+            let size = width as usize * height as usize * depth.bytes_per_pixel();
+            (width, height, chunk.bytes(size)?.to_vec())
+        }
+        1 => {
+            let frame = chunk.word()? as usize;
+            let linked = previous_frames
+                .get(frame)
+                .and_then(|cels| cels.iter().find(|cel| cel.layer == layer))
+                .ok_or("linked cel not found")?;
+            return Ok(Some(linked.clone()));
+        }
+        2 => {
+            let (width, height) = (chunk.word()? as u32, chunk.word()? as u32);
+            let size = width as usize * height as usize * depth.bytes_per_pixel();
+            let compressed = chunk.bytes(chunk.data.len() - chunk.position)?;
+            let pixels = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(compressed, size)
+                .map_err(|_| "could not decompress cel")?;
+            if pixels.len() != size {
+                return Err("cel size does not match its pixels");
+            }
+            (width, height, pixels)
+        }
+        // compressed tilemaps
+        _ => return Ok(None),
+    };
+    Ok(Some(Cel {
+        layer,
+        x,
+        y,
+        opacity,
+        z_index,
+        width,
+        height,
+        pixels,
+    }))
+}
+
+fn flatten(
+    width: u32,
+    height: u32,
+    layers: &[AsepriteLayer],
+    cels: &[Cel],
+    depth: ColorDepth,
+    palette: &[[u8; 4]],
+    transparent_index: u8,
+) -> Image {
+    let mut buf = vec![0; width as usize * height as usize * 4];
+    let mut cels = cels.iter().collect::<Vec<_>>();
+    // z-index moves a cel up or down the layer stack
+    cels.sort_by_key(|cel| (cel.layer as i64 + cel.z_index as i64, cel.z_index));
+    for cel in cels {
+        let layer = match layers.get(cel.layer) {
+            Some(layer) if layer.visible && layer.kind == AsepriteLayerKind::Image => layer,
+            _ => continue,
+        };
+        let opacity = mul_un8(cel.opacity, layer.opacity);
+        let bytes_per_pixel = depth.bytes_per_pixel();
+        for cel_y in 0..cel.height as i32 {
+            let y = cel.y + cel_y;
+            if y < 0 || y >= height as i32 {
+                continue;
+            }
+            for cel_x in 0..cel.width as i32 {
+                let x = cel.x + cel_x;
+                if x < 0 || x >= width as i32 {
+                    continue;
+                }
+                let offset =
+                    (cel_y as usize * cel.width as usize + cel_x as usize) * bytes_per_pixel;
+                let pixel = &cel.pixels[offset..offset + bytes_per_pixel];
+                let src = match depth {
+                    ColorDepth::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                    ColorDepth::Grayscale => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    ColorDepth::Indexed if pixel[0] == transparent_index && !layer.background => {
+                        [0; 4]
+                    }
+                    ColorDepth::Indexed => palette
+                        .get(pixel[0] as usize)
+                        .copied()
+                        .unwrap_or([0, 0, 0, 255]),
+                };
+                let index = (y as usize * width as usize + x as usize) * 4;
+                let backdrop = [buf[index], buf[index + 1], buf[index + 2], buf[index + 3]];
+                let result = blend(layer.blend_mode, backdrop, src, opacity);
+                buf[index..index + 4].copy_from_slice(&result);
+            }
+        }
+    }
+    Image::new(width, height, buf)
+}
+
+fn mul_un8(a: u8, b: u8) -> u8 {
+    let t = a as u32 * b as u32 + 0x80;
+    (((t >> 8) + t) >> 8) as u8
+}
+
+/// Aseprite's blending: the blend mode picks the source colour, which is then composited over
+/// the backdrop like a normal layer
+fn blend(mode: AsepriteBlendMode, backdrop: [u8; 4], src: [u8; 4], opacity: u8) -> [u8; 4] {
+    use AsepriteBlendMode::*;
+    if src[3] == 0 {
+        return backdrop;
+    }
+    let color = if backdrop[3] == 0 {
+        [src[0], src[1], src[2]]
+    } else {
+        let channel = |f: fn(u8, u8) -> u8| {
+            [
+                f(backdrop[0], src[0]),
+                f(backdrop[1], src[1]),
+                f(backdrop[2], src[2]),
+            ]
+        };
+        match mode {
+            Normal => [src[0], src[1], src[2]],
+            Multiply => channel(mul_un8),
+            Screen => channel(screen),
+            Overlay => channel(|b, s| hard_light(s, b)),
+            Darken => channel(|b, s| b.min(s)),
+            Lighten => channel(|b, s| b.max(s)),
+            ColorDodge => channel(color_dodge),
+            ColorBurn => channel(color_burn),
+            HardLight => channel(hard_light),
+            SoftLight => channel(soft_light),
+            Difference => channel(|b, s| b.abs_diff(s)),
+            Exclusion => {
+                channel(|b, s| (b as i32 + s as i32 - 2 * mul_un8(b, s) as i32).clamp(0, 255) as u8)
+            }
+            Addition => channel(|b, s| b.saturating_add(s)),
+            Subtract => channel(|b, s| b.saturating_sub(s)),
+            Divide => channel(|b, s| match (b, s) {
+                (0, _) => 0,
+                (b, s) if b >= s => 255,
+                (b, s) => (b as u32 * 255 / s as u32) as u8,
+            }),
+            Hue | Saturation | Color | Luminosity => {
+                let to_f = |c: [u8; 4]| [c[0] as f64, c[1] as f64, c[2] as f64].map(|c| c / 255.0);
+                let (b, s) = (to_f(backdrop), to_f(src));
+                let result = match mode {
+                    Hue => set_lum(set_sat(s, sat(b)), lum(b)),
+                    Saturation => set_lum(set_sat(b, sat(s)), lum(b)),
+                    Color => set_lum(s, lum(b)),
+                    _ => set_lum(b, lum(s)),
+                };
+                result.map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+            }
+        }
+    };
+    normal(backdrop, [color[0], color[1], color[2], src[3]], opacity)
+}
+
+fn normal(backdrop: [u8; 4], src: [u8; 4], opacity: u8) -> [u8; 4] {
+    let src_alpha = mul_un8(src[3], opacity);
+    if backdrop[3] == 0 {
+        return [src[0], src[1], src[2], src_alpha];
+    }
+    if src_alpha == 0 {
+        return backdrop;
+    }
+    let backdrop_alpha = backdrop[3] as i32;
+    let result_alpha = src_alpha as i32 + backdrop_alpha - mul_un8(backdrop[3], src_alpha) as i32;
+    let mix =
+        |b: u8, s: u8| (b as i32 + (s as i32 - b as i32) * src_alpha as i32 / result_alpha) as u8;
+    [
+        mix(backdrop[0], src[0]),
+        mix(backdrop[1], src[1]),
+        mix(backdrop[2], src[2]),
+        result_alpha as u8,
+    ]
+}
+
+fn screen(b: u8, s: u8) -> u8 {
+    (b as i32 + s as i32 - mul_un8(b, s) as i32) as u8
+}
+
+fn hard_light(b: u8, s: u8) -> u8 {
+    if s < 128 {
+        mul_un8(b, s << 1)
+    } else {
+        screen(b, ((s as i32) << 1).wrapping_sub(255) as u8)
+    }
+}
+
+fn color_dodge(b: u8, s: u8) -> u8 {
+    if b == 0 {
+        return 0;
+    }
+    let s = 255 - s;
+    if b >= s {
+        255
+    } else {
+        (b as u32 * 255 / s as u32) as u8
+    }
+}
+
+fn color_burn(b: u8, s: u8) -> u8 {
+    if b == 255 {
+        return 255;
+    }
+    let b = 255 - b;
+    if b >= s {
+        0
+    } else {
+        255 - (b as u32 * 255 / s as u32) as u8
+    }
+}
+
+fn soft_light(b: u8, s: u8) -> u8 {
+    let (b, s) = (b as f64 / 255.0, s as f64 / 255.0);
+    let d = if b <= 0.25 {
+        ((16.0 * b - 12.0) * b + 4.0) * b
+    } else {
+        b.sqrt()
+    };
+    let r = if s <= 0.5 {
+        b - (1.0 - 2.0 * s) * b * (1.0 - b)
+    } else {
+        b + (2.0 * s - 1.0) * (d - b)
+    };
+    (r * 255.0 + 0.5) as u8
+}
+
+fn lum(c: [f64; 3]) -> f64 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn set_lum(c: [f64; 3], l: f64) -> [f64; 3] {
+    let d = l - lum(c);
+    let c = c.map(|v| v + d);
+    // clip back into range while keeping the luminosity
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    c.map(|v| {
+        let v = if n < 0.0 {
+            l + (v - l) * l / (l - n)
+        } else {
+            v
+        };
+        if x > 1.0 {
+            l + (v - l) * (1.0 - l) / (x - l)
+        } else {
+            v
+        }
+    })
+}
+
+fn sat(c: [f64; 3]) -> f64 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f64; 3], s: f64) -> [f64; 3] {
+    let min = c[0].min(c[1]).min(c[2]);
+    let max = c[0].max(c[1]).max(c[2]);
+    if max > min {
+        c.map(|v| (v - min) * s / (max - min))
+    } else {
+        [0.0; 3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{ImageResource, ResourceManager};
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * image.width() + x) * 4) as usize;
+        [
+            image.buf[i],
+            image.buf[i + 1],
+            image.buf[i + 2],
+            image.buf[i + 3],
+        ]
+    }
+
+    fn chunk(chunk_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut chunk = ((data.len() + 6) as u32).to_le_bytes().to_vec();
+        chunk.extend_from_slice(&chunk_type.to_le_bytes());
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    /// A single frame 2x1 indexed file with one layer and one raw cel
+    fn indexed_file(cel_pixels: [u8; 2]) -> Vec<u8> {
+        let mut layer = vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0];
+        layer.extend_from_slice(&[1, 0, b'a']);
+        let mut palette = vec![3, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        for rgba in [[0, 0, 0, 0], [255, 0, 0, 255], [0, 0, 255, 128]] {
+            palette.extend_from_slice(&[0, 0]);
+            palette.extend_from_slice(&rgba);
+        }
+        let mut cel = vec![0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        cel.extend_from_slice(&[2, 0, 1, 0]);
+        cel.extend_from_slice(&cel_pixels);
+        let chunks = [
+            chunk(CHUNK_PALETTE, &palette),
+            chunk(CHUNK_LAYER, &layer),
+            chunk(CHUNK_CEL, &cel),
+        ]
+        .concat();
+        let mut frame = ((16 + chunks.len()) as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&FRAME_MAGIC.to_le_bytes());
+        frame.extend_from_slice(&[3, 0, 50, 0, 0, 0, 3, 0, 0, 0]);
+        frame.extend_from_slice(&chunks);
+        let mut file = vec![0; HEADER_SIZE];
+        file[0..4].copy_from_slice(&((HEADER_SIZE + frame.len()) as u32).to_le_bytes());
+        file[4..6].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+        file[6..14].copy_from_slice(&[1, 0, 2, 0, 1, 0, 8, 0]);
+        file.extend_from_slice(&frame);
+        file
+    }
+
+    #[test]
+    fn test_load_aseprite_file() {
+        let mut resource_manager = ResourceManager::new();
+        let handle = resource_manager
+            .try_load_aseprite(Path::new("resources/images/slime.aseprite"))
+            .unwrap();
+        let sheet = resource_manager.get_sprite_sheet(handle).unwrap();
+        let durations = sheet
+            .frames()
+            .iter()
+            .map(|frame| frame.duration.unwrap().as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(durations, [100, 150, 100, 200]);
+        assert_eq!(
+            sheet.frame(3).unwrap().rect,
+            Rect::new(Vec2::new(48, 0), 16, 16)
+        );
+        let bounce = sheet.tag("bounce").unwrap();
+        assert_eq!((bounce.from, bounce.to), (1, 3));
+        assert_eq!(bounce.direction, AnimationDirection::PingPong);
+        assert_eq!(
+            sheet.tag("back").unwrap().direction,
+            AnimationDirection::Reverse
+        );
+
+        let image = resource_manager.get_image(sheet.image).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 16));
+        // the hidden red layer stays out, the multiply layer darkens the bottom of the body
+        assert_eq!(pixel(image, 0, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(image, 8, 10), [80, 200, 90, 255]);
+        assert_eq!(pixel(image, 8, 14), [40, 100, 45, 255]);
+        // frame 2 links both cels of frame 0
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(pixel(image, x, y), pixel(image, x + 32, y));
+            }
+        }
+
+        let bad =
+            resource_manager.try_load_aseprite(Path::new("resources/images/sprite_sheet.png"));
+        assert!(matches!(bad, Err(ResourceError::Parse { .. })));
+    }
+
+    #[test]
+    fn test_indexed_and_truncated() {
+        let aseprite = Aseprite::parse(&indexed_file([1, 0])).unwrap();
+        assert_eq!(aseprite.frames[0].duration, Duration::from_millis(50));
+        let image = &aseprite.frames[0].image;
+        assert_eq!(pixel(image, 0, 0), [255, 0, 0, 255]);
+        // index 0 is the transparent index
+        assert_eq!(pixel(image, 1, 0), [0, 0, 0, 0]);
+
+        let file = indexed_file([2, 2]);
+        assert_eq!(
+            pixel(&Aseprite::parse(&file).unwrap().frames[0].image, 0, 0),
+            [0, 0, 255, 128]
+        );
+        for length in [0, 20, HEADER_SIZE + 10, file.len() - 1] {
+            assert!(Aseprite::parse(&file[..length]).is_err(), "{}", length);
+        }
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let backdrop = [200, 100, 0, 255];
+        let src = [100, 100, 100, 255];
+        assert_eq!(blend(AsepriteBlendMode::Normal, backdrop, src, 255), src);
+        assert_eq!(
+            blend(AsepriteBlendMode::Normal, backdrop, src, 128),
+            [150, 100, 50, 255]
+        );
+        assert_eq!(
+            blend(AsepriteBlendMode::Screen, backdrop, src, 255),
+            [222, 161, 100, 255]
+        );
+        assert_eq!(
+            blend(AsepriteBlendMode::Difference, backdrop, src, 255),
+            [100, 0, 100, 255]
+        );
+        assert_eq!(
+            blend(AsepriteBlendMode::Addition, backdrop, src, 255),
+            [255, 200, 100, 255]
+        );
+        let luminosity = blend(AsepriteBlendMode::Luminosity, [255, 0, 0, 255], src, 255);
+        assert!(luminosity[0] > luminosity[1] && luminosity[1] == luminosity[2]);
+        // blend modes only apply over something, onto transparency the source is used as is
+        assert_eq!(blend(AsepriteBlendMode::Multiply, [0; 4], src, 255), src);
+    }
+}
@@ -0,0 +1,549 @@
+use std::collections::HashMap;
+use std::fs::read;
+use std::path::Path;
+
+use crate::resource::{Image, ImageResource, ResourceError};
+use crate::text::{HorizontalAlign, TextBox, TextStyle, VerticalAlign, WrapStyle};
+use crate::types::{Rect, Vec2};
+
+/// Where a character lives on a page and how it sits on the line
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BitmapGlyph {
+    pub page: usize,
+    pub rect: Rect,
+    /// From the pen position at the top of the line to the top left of `rect`
+    pub offset: Vec2,
+    pub advance: i32,
+}
+
+/// A font drawn from prerendered glyph images rather than outlines, so it stays crisp at its
+/// native size. Larger text sizes scale it up by whole pixels.
+#[derive(Clone)]
+pub struct BitmapFont {
+    /// The size the glyphs were rendered at
+    pub size: f32,
+    pub line_height: i32,
+    /// Distance from the top of a line to the baseline
+    pub base: i32,
+    pub pages: Vec<Image>,
+    glyphs: HashMap<char, BitmapGlyph>,
+    kerning: HashMap<(char, char), i32>,
+}
+
+/// What a `.fnt` file describes, before its pages are loaded
+#[derive(Debug, Default)]
+struct FntFile {
+    size: f32,
+    line_height: i32,
+    base: i32,
+    page_files: Vec<String>,
+    glyphs: HashMap<char, BitmapGlyph>,
+    kerning: HashMap<(char, char), i32>,
+}
+
+impl BitmapFont {
+    /// Load an AngelCode BMFont `.fnt`, text or binary, with its pages from the same directory
+    pub fn open_fnt(path: &Path) -> Result<Self, ResourceError> {
+        let data = read(path).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let fnt = parse_fnt(&data).map_err(|reason| ResourceError::FontParse {
+            path: path.to_path_buf(),
+            reason,
+        })?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let pages = fnt
+            .page_files
+            .iter()
+            .map(|file| Image::open(&directory.join(file)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if fnt.glyphs.values().any(|glyph| {
+            !matches!(pages.get(glyph.page), Some(page)
+                if glyph.rect.right() as u32 <= page.width()
+                    && glyph.rect.bottom() as u32 <= page.height())
+        }) {
+            return Err(ResourceError::FontParse {
+                path: path.to_path_buf(),
+                reason: "glyph outside of its page",
+            });
+        }
+        Ok(Self {
+            size: fnt.size,
+            line_height: fnt.line_height,
+            base: fnt.base,
+            pages,
+            glyphs: fnt.glyphs,
+            kerning: fnt.kerning,
+        })
+    }
+    /// A monospaced font from an image of equally sized cells, read left to right and top to
+    /// bottom, holding `chars` in order
+    pub fn from_grid(image: Image, cell_width: u32, cell_height: u32, chars: &str) -> Self {
+        let columns = (image.width() / cell_width.max(1)).max(1);
+        let glyphs = chars
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let top_left = Vec2::new(
+                    (i as u32 % columns * cell_width) as i32,
+                    (i as u32 / columns * cell_height) as i32,
+                );
+                let glyph = BitmapGlyph {
+                    page: 0,
+                    rect: Rect::new(top_left, cell_width, cell_height),
+                    offset: Vec2::new(0, 0),
+                    advance: cell_width as i32,
+                };
+                (c, glyph)
+            })
+            .filter(|(_, glyph)| {
+                glyph.rect.right() as u32 <= image.width()
+                    && glyph.rect.bottom() as u32 <= image.height()
+            })
+            .collect();
+        Self {
+            size: cell_height as f32,
+            line_height: cell_height as i32,
+            base: cell_height as i32,
+            pages: vec![image],
+            glyphs,
+            kerning: HashMap::new(),
+        }
+    }
+    pub fn glyph(&self, c: char) -> Option<&BitmapGlyph> {
+        self.glyphs.get(&c)
+    }
+    /// Extra horizontal distance between `left` and `right` when `right` follows `left`
+    pub fn kerning(&self, left: char, right: char) -> i32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0)
+    }
+    pub fn set_kerning(&mut self, left: char, right: char, amount: i32) {
+        self.kerning.insert((left, right), amount);
+    }
+    /// Whole pixel factor the glyphs are drawn at for a text size
+    pub fn scale(&self, size: f32) -> i32 {
+        ((size / self.size).round() as i32).max(1)
+    }
+    /// Unscaled width of a single line of text
+    pub(crate) fn line_width(&self, line: &[char]) -> i32 {
+        self.pen_positions(line).last().copied().unwrap_or(0)
+    }
+    /// Unscaled pen position of every character of `line`, kerning with the one before applied,
+    /// followed by where the pen ends up
+    pub(crate) fn pen_positions(&self, line: &[char]) -> Vec<i32> {
+        let mut pens = Vec::with_capacity(line.len() + 1);
+        let mut pen = 0;
+        let mut previous = None;
+        for c in line {
+            if let Some(previous) = previous {
+                pen += self.kerning(previous, *c);
+            }
+            previous = Some(*c);
+            pens.push(pen);
+            pen += self.glyph(*c).map_or(0, |glyph| glyph.advance);
+        }
+        pens.push(pen);
+        pens
+    }
+    /// Split `text` into lines, wrapping at `max_width` unscaled pixels
+    fn break_lines(
+        &self,
+        text: &str,
+        max_width: Option<i32>,
+        text_box: &TextBox,
+    ) -> Vec<Vec<char>> {
+        let mut lines = Vec::new();
+        let mut current: Vec<char> = Vec::new();
+        for c in text.chars() {
+            if c == '\n' && !text_box.ignore_newlines {
+                lines.push(std::mem::take(&mut current));
+                continue;
+            }
+            current.push(if c == '\n' { ' ' } else { c });
+            let max_width = match max_width {
+                Some(max_width) => max_width,
+                None => continue,
+            };
+            if current.len() < 2 || self.line_width(&current) <= max_width {
+                continue;
+            }
+            let last_space = current.iter().rposition(|c| *c == ' ');
+            match (text_box.wrap_style, last_space) {
+                // a space that overflows just ends the line
+                (WrapStyle::Word, Some(space)) if space == current.len() - 1 => {
+                    current.pop();
+                    lines.push(std::mem::take(&mut current));
+                }
+                (WrapStyle::Word, Some(space)) => {
+                    let rest = current.split_off(space + 1);
+                    current.pop();
+                    lines.push(std::mem::replace(&mut current, rest));
+                }
+                _ => {
+                    let overflow = current.pop().unwrap();
+                    lines.push(std::mem::replace(&mut current, vec![overflow]));
+                }
+            }
+        }
+        lines.push(current);
+        lines
+    }
+    /// Position the glyphs of `text` inside `text_box`, along with the area the text covers:
+    /// the line boxes vertically and the glyphs horizontally
+    pub(crate) fn layout(
+        &self,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+    ) -> (Vec<(BitmapGlyph, Vec2)>, Rect) {
+        let scale = self.scale(style.size);
+        let max_width = text_box.max_width.map(|width| width as i32 / scale);
+        let lines = self.break_lines(text, max_width, text_box);
+        let line_advance = (self.line_height as f32 * style.line_spacing).round() as i32;
+        let height = (self.line_height + line_advance * (lines.len() as i32 - 1)) * scale;
+        let free_height = text_box
+            .max_height
+            .map_or(0, |max_height| max_height as i32 - height);
+        let top = text_box.position.y
+            + match text_box.vertical_align {
+                VerticalAlign::Top => 0,
+                VerticalAlign::Middle => free_height / 2,
+                VerticalAlign::Bottom => free_height,
+            };
+        let mut placed = Vec::new();
+        let mut left = i32::MAX;
+        let mut right = i32::MIN;
+        for (i, line) in lines.iter().enumerate() {
+            let free_width = text_box.max_width.map_or(0, |max_width| {
+                max_width as i32 - self.line_width(line) * scale
+            });
+            let origin = Vec2::new(
+                text_box.position.x
+                    + match text_box.horizontal_align {
+                        HorizontalAlign::Left => 0,
+                        HorizontalAlign::Center => free_width / 2,
+                        HorizontalAlign::Right => free_width,
+                    },
+                top + i as i32 * line_advance * scale,
+            );
+            for (c, pen) in line.iter().zip(self.pen_positions(line)) {
+                let glyph = match self.glyph(*c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                if glyph.rect.area() > 0 {
+                    let position = Vec2::new(
+                        origin.x + (pen + glyph.offset.x) * scale,
+                        origin.y + glyph.offset.y * scale,
+                    );
+                    left = left.min(position.x);
+                    right = right.max(position.x + glyph.rect.width as i32 * scale);
+                    placed.push((*glyph, position));
+                }
+            }
+        }
+        if placed.is_empty() {
+            left = text_box.position.x;
+            right = left;
+        }
+        let bounds = Rect::new(
+            Vec2::new(left, top),
+            (right - left) as u32,
+            height.max(0) as u32,
+        );
+        (placed, bounds)
+    }
+}
+
+fn parse_fnt(data: &[u8]) -> Result<FntFile, &'static str> {
+    if data.starts_with(b"BMF") {
+        parse_binary_fnt(data)
+    } else {
+        let text = std::str::from_utf8(data).map_err(|_| "not a BMFont file")?;
+        parse_text_fnt(text)
+    }
+}
+
+/// Split a `tag key=value key="quoted value"` line into its tag and pairs
+fn fnt_line(line: &str) -> (&str, HashMap<&str, &str>) {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+    let mut pairs = HashMap::new();
+    loop {
+        rest = rest.trim_start();
+        let (key, after_key) = match rest.split_once('=') {
+            Some(split) => split,
+            None => break,
+        };
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+            }
+            None => after_key.split_once(' ').unwrap_or((after_key, "")),
+        };
+        pairs.insert(key.trim(), value);
+        rest = after_value;
+    }
+    (tag, pairs)
+}
+
+/// More pages than any real font has, for files that don't say how many they have
+const MAX_FNT_PAGES: usize = 256;
+
+fn parse_text_fnt(text: &str) -> Result<FntFile, &'static str> {
+    let mut fnt = FntFile::default();
+    let mut has_common = false;
+    let mut page_count = MAX_FNT_PAGES;
+    for line in text.lines() {
+        let (tag, pairs) = fnt_line(line);
+        let int = |key: &str| -> Result<i32, &'static str> {
+            pairs
+                .get(key)
+                .and_then(|value| value.parse().ok())
+                .ok_or("missing or invalid value in BMFont file")
+        };
+        let unsigned = |key: &str| -> Result<u32, &'static str> {
+            u32::try_from(int(key)?).map_err(|_| "negative value in BMFont file")
+        };
+        let page = |key: &str| -> Result<usize, &'static str> {
+            let page = unsigned(key)? as usize;
+            if page >= page_count {
+                return Err("page id out of range in BMFont file");
+            }
+            Ok(page)
+        };
+        match tag {
+            "info" => fnt.size = int("size")?.abs() as f32,
+            "common" => {
+                fnt.line_height = int("lineHeight")?;
+                fnt.base = int("base")?;
+                if pairs.contains_key("pages") {
+                    page_count = (unsigned("pages")? as usize).min(MAX_FNT_PAGES);
+                }
+                has_common = true;
+            }
+            "page" => {
+                let id = page("id")?;
+                let file = pairs.get("file").ok_or("page without a file")?;
+                if fnt.page_files.len() <= id {
+                    fnt.page_files.resize(id + 1, String::new());
+                }
+                fnt.page_files[id] = file.to_string();
+            }
+            "char" => {
+                let c = char::from_u32(int("id")? as u32).ok_or("invalid character id")?;
+                let glyph = BitmapGlyph {
+                    page: page("page")?,
+                    rect: Rect::new(
+                        Vec2::new(int("x")?, int("y")?),
+                        unsigned("width")?,
+                        unsigned("height")?,
+                    ),
+                    offset: Vec2::new(int("xoffset")?, int("yoffset")?),
+                    advance: int("xadvance")?,
+                };
+                fnt.glyphs.insert(c, glyph);
+            }
+            "kerning" => {
+                let first = char::from_u32(int("first")? as u32).ok_or("invalid character id")?;
+                let second = char::from_u32(int("second")? as u32).ok_or("invalid character id")?;
+                fnt.kerning.insert((first, second), int("amount")?);
+            }
+            _ => {}
+        }
+    }
+    if !has_common {
+        return Err("not a BMFont file");
+    }
+    finish_fnt(fnt)
+}
+
+fn parse_binary_fnt(data: &[u8]) -> Result<FntFile, &'static str> {
+    const TRUNCATED: &str = "truncated BMFont file";
+    if data.get(3) != Some(&3) {
+        return Err("unsupported BMFont version");
+    }
+    let u16_at = |block: &[u8], at: usize| -> Result<u16, &'static str> {
+        let bytes = block.get(at..at + 2).ok_or(TRUNCATED)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    };
+    let u32_at = |block: &[u8], at: usize| -> Result<u32, &'static str> {
+        let bytes = block.get(at..at + 4).ok_or(TRUNCATED)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let mut fnt = FntFile::default();
+    let mut has_common = false;
+    let mut at = 4;
+    while at < data.len() {
+        let kind = data[at];
+        let length = u32_at(data, at + 1)? as usize;
+        let block = data.get(at + 5..at + 5 + length).ok_or(TRUNCATED)?;
+        at += 5 + length;
+        match kind {
+            1 => fnt.size = (u16_at(block, 0)? as i16).unsigned_abs() as f32,
+            2 => {
+                fnt.line_height = u16_at(block, 0)? as i32;
+                fnt.base = u16_at(block, 2)? as i32;
+                has_common = true;
+            }
+            3 => {
+                fnt.page_files = block
+                    .split(|byte| *byte == 0)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+                    .collect();
+            }
+            4 => {
+                for record in block.chunks_exact(20) {
+                    let c = char::from_u32(u32_at(record, 0)?).ok_or("invalid character id")?;
+                    let glyph = BitmapGlyph {
+                        page: record[18] as usize,
+                        rect: Rect::new(
+                            Vec2::new(u16_at(record, 4)? as i32, u16_at(record, 6)? as i32),
+                            u16_at(record, 8)? as u32,
+                            u16_at(record, 10)? as u32,
+                        ),
+                        offset: Vec2::new(
+                            u16_at(record, 12)? as i16 as i32,
+                            u16_at(record, 14)? as i16 as i32,
+                        ),
+                        advance: u16_at(record, 16)? as i16 as i32,
+                    };
+                    fnt.glyphs.insert(c, glyph);
+                }
+            }
+            5 => {
+                for record in block.chunks_exact(10) {
+                    let first = char::from_u32(u32_at(record, 0)?).ok_or("invalid character id")?;
+                    let second =
+                        char::from_u32(u32_at(record, 4)?).ok_or("invalid character id")?;
+                    let amount = u16_at(record, 8)? as i16 as i32;
+                    fnt.kerning.insert((first, second), amount);
+                }
+            }
+            _ => return Err("unknown BMFont block"),
+        }
+    }
+    if !has_common {
+        return Err("BMFont file has no common block");
+    }
+    finish_fnt(fnt)
+}
+
+fn finish_fnt(mut fnt: FntFile) -> Result<FntFile, &'static str> {
+    if fnt.page_files.is_empty() || fnt.page_files.iter().any(|file| file.is_empty()) {
+        return Err("BMFont file is missing pages");
+    }
+    if fnt.size <= 0.0 {
+        fnt.size = fnt.line_height as f32;
+    }
+    Ok(fnt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_and_binary_fnt_match() {
+        let text = BitmapFont::open_fnt(Path::new("resources/fonts/pixel_mono.fnt")).unwrap();
+        let binary =
+            BitmapFont::open_fnt(Path::new("resources/fonts/pixel_mono_binary.fnt")).unwrap();
+        assert_eq!((text.line_height, text.base, text.size), (16, 13, 13.0));
+        assert_eq!(
+            (binary.line_height, binary.base, binary.size),
+            (text.line_height, text.base, text.size)
+        );
+        assert_eq!(text.glyphs, binary.glyphs);
+        assert_eq!(text.kerning, binary.kerning);
+        assert_eq!(text.kerning('A', 'V'), -1);
+        assert_eq!(text.kerning('V', 'V'), 0);
+        assert_eq!(text.pages[0].get_buf(), binary.pages[0].get_buf());
+        assert_eq!(
+            text.glyph('!').unwrap().rect,
+            Rect::new(Vec2::new(1, 0), 3, 11)
+        );
+    }
+
+    #[test]
+    fn test_fnt_errors() {
+        assert!(parse_fnt(b"hello").is_err());
+        assert!(parse_fnt(b"BMF\x03\x02\x09\x00").is_err());
+        assert!(parse_text_fnt("common lineHeight=16 base=13\nchars count=0\n").is_err());
+        // page ids have to fit the page count, or a sane one when there isn't any
+        for page in [
+            "page id=-1 file=\"a.png\"",
+            "page id=4294967295 file=\"a.png\"",
+            "page id=1 file=\"a.png\"",
+        ] {
+            let fnt = format!("common lineHeight=16 base=13 pages=1\n{}\n", page);
+            assert!(parse_text_fnt(&fnt).is_err());
+        }
+        assert!(
+            parse_text_fnt("common lineHeight=16 base=13\npage id=99999 file=\"a.png\"\n").is_err()
+        );
+        let char_line = |page, width| {
+            format!(
+                "common lineHeight=16 base=13 pages=1\npage id=0 file=\"a.png\"\n\
+                 char id=65 x=0 y=0 width={} height=8 xoffset=0 yoffset=0 xadvance=8 page={}\n",
+                width, page
+            )
+        };
+        assert!(parse_text_fnt(&char_line(0, 8)).is_ok());
+        assert!(parse_text_fnt(&char_line(-1, 8)).is_err());
+        assert!(parse_text_fnt(&char_line(0, -8)).is_err());
+        let (tag, pairs) = fnt_line("page id=0 file=\"with space.png\"");
+        assert_eq!(tag, "page");
+        assert_eq!(pairs["file"], "with space.png");
+        assert_eq!(pairs["id"], "0");
+    }
+
+    #[test]
+    fn test_layout_wraps_and_kerns() {
+        let mut font =
+            BitmapFont::from_grid(Image::new(32, 8, vec![255; 32 * 8 * 4]), 8, 8, "AV T");
+        font.set_kerning('A', 'V', -2);
+        let style = TextStyle::new(8.0, crate::types::Color::new(255, 255, 255, 255));
+        let (glyphs, bounds) = font.layout("AV", &style, &TextBox::at(Vec2::new(1, 2)));
+        let positions = glyphs
+            .iter()
+            .map(|(_, position)| *position)
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [Vec2::new(1, 2), Vec2::new(7, 2)]);
+        assert_eq!(bounds, Rect::new(Vec2::new(1, 2), 14, 8));
+
+        let wrapping = TextBox {
+            max_width: Some(20),
+            ..TextBox::default()
+        };
+        let (glyphs, bounds) = font.layout("TT TT T", &style, &wrapping);
+        let rows = glyphs
+            .iter()
+            .map(|(_, position)| position.y / 8)
+            .collect::<Vec<_>>();
+        assert_eq!(rows, [0, 0, 1, 1, 2]);
+        assert_eq!(bounds.height, 24);
+        let letters = TextBox {
+            wrap_style: WrapStyle::Letter,
+            ..wrapping
+        };
+        let (glyphs, _) = font.layout("TTTTT", &style, &letters);
+        let rows = glyphs
+            .iter()
+            .map(|(_, position)| position.y / 8)
+            .collect::<Vec<_>>();
+        assert_eq!(rows, [0, 0, 1, 1, 2]);
+
+        let doubled = TextStyle::new(16.0, style.color);
+        let right = TextBox {
+            max_width: Some(40),
+            horizontal_align: HorizontalAlign::Right,
+            ..TextBox::default()
+        };
+        let (glyphs, bounds) = font.layout("T", &doubled, &right);
+        assert_eq!(glyphs[0].1, Vec2::new(24, 0));
+        assert_eq!(bounds, Rect::new(Vec2::new(24, 0), 16, 16));
+    }
+}
@@ -0,0 +1,445 @@
+use std::time::Duration;
+
+use crate::drawing::{blit_transformed, draw_line, Transform};
+use crate::resource::{Image, ImageResource, ResourceManager};
+use crate::sprite::Frame;
+use crate::tilemap::Tilemap;
+use crate::types::{Color, Rect, Vec2, Vec2F};
+
+/// A view into the world, with `position` being the world point shown at the centre of the
+/// viewport.
+///
+/// World coordinates are turned into screen coordinates by moving, rotating and then zooming
+/// around that point. Drawing through the camera does the same to whatever is drawn.
+#[derive(Debug, Clone)]
+pub struct Camera2D {
+    pub position: Vec2F,
+    /// Screen pixels per world unit, must be above 0
+    pub zoom: f32,
+    /// Radians, clockwise, the world appears rotated the other way
+    pub rotation: f32,
+    /// Size of the screen area the camera draws to
+    pub viewport: Vec2F,
+    /// World area the view is kept inside of, centred on it when the view is the larger one
+    pub bounds: Option<Rect>,
+    /// Size of the box around `position` that a followed target can move in freely
+    pub deadzone: Vec2F,
+    /// How quickly `follow` catches up, each call covers `1 - e^(-speed * seconds)` of the
+    /// distance. 0 snaps straight to the target.
+    pub follow_speed: f32,
+    shake_magnitude: f32,
+    shake_duration: Duration,
+    shake_remaining: Duration,
+    shake_offset: Vec2F,
+    /// Offscreen image `draw_tilemap` draws into when zoomed or rotated
+    view: Option<Image>,
+}
+
+impl Camera2D {
+    pub fn new(viewport_width: u32, viewport_height: u32) -> Self {
+        Self {
+            position: Vec2F::new(viewport_width as f32 / 2.0, viewport_height as f32 / 2.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport: Vec2F::new(viewport_width as f32, viewport_height as f32),
+            bounds: None,
+            deadzone: Vec2F::new(0.0, 0.0),
+            follow_speed: 0.0,
+            shake_magnitude: 0.0,
+            shake_duration: Duration::ZERO,
+            shake_remaining: Duration::ZERO,
+            shake_offset: Vec2F::new(0.0, 0.0),
+            view: None,
+        }
+    }
+    /// The point actually shown at the viewport centre, `position` moved by any shake
+    pub fn center(&self) -> Vec2F {
+        self.position + self.shake_offset
+    }
+    /// Move towards `target` until it is back inside the deadzone, then keep to `bounds`
+    pub fn follow(&mut self, target: Vec2F, elapsed: Duration) {
+        let half = self.deadzone / 2.0;
+        let distance = target - self.position;
+        let outside = |distance: f32, half: f32| {
+            if distance > half {
+                distance - half
+            } else if distance < -half {
+                distance + half
+            } else {
+                0.0
+            }
+        };
+        let step = Vec2F::new(outside(distance.x, half.x), outside(distance.y, half.y));
+        if self.follow_speed <= 0.0 {
+            self.position += step;
+        } else {
+            let t = 1.0 - (-self.follow_speed * elapsed.as_secs_f32()).exp();
+            self.position += step * t;
+        }
+        self.clamp_to_bounds();
+    }
+    /// Shake by up to `magnitude` world units, fading out over `duration`. A weaker shake
+    /// doesn't cut a stronger one short.
+    pub fn shake(&mut self, magnitude: f32, duration: Duration) {
+        if self.shake_strength() > magnitude {
+            return;
+        }
+        self.shake_magnitude = magnitude;
+        self.shake_duration = duration;
+        self.shake_remaining = duration;
+    }
+    pub fn is_shaking(&self) -> bool {
+        !self.shake_remaining.is_zero()
+    }
+    /// Advance the shake and keep to `bounds`, call once per frame
+    pub fn update(&mut self, elapsed: Duration) {
+        self.shake_remaining = self.shake_remaining.saturating_sub(elapsed);
+        let strength = self.shake_strength();
+        self.shake_offset = if strength > 0.0 {
+            let unit = || rand::random::<f32>() * 2.0 - 1.0;
+            Vec2F::new(unit(), unit()) * strength
+        } else {
+            Vec2F::new(0.0, 0.0)
+        };
+        self.clamp_to_bounds();
+    }
+    fn shake_strength(&self) -> f32 {
+        if self.shake_remaining.is_zero() {
+            return 0.0;
+        }
+        self.shake_magnitude * self.shake_remaining.as_secs_f32()
+            / self.shake_duration.as_secs_f32()
+    }
+    /// Half the size of the world area in view, grown to fit around it when rotated
+    fn half_extents(&self) -> Vec2F {
+        let half = self.viewport / (2.0 * self.zoom);
+        let (sin, cos) = self.rotation.sin_cos();
+        Vec2F::new(
+            (half.x * cos).abs() + (half.y * sin).abs(),
+            (half.x * sin).abs() + (half.y * cos).abs(),
+        )
+    }
+    fn clamp_to_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let extents = self.half_extents();
+        let clamp = |value: f32, min: i32, max: i32, extent: f32| {
+            let (min, max) = (min as f32 + extent, max as f32 - extent);
+            if min > max {
+                (min + max) / 2.0
+            } else {
+                value.clamp(min, max)
+            }
+        };
+        self.position = Vec2F::new(
+            clamp(self.position.x, bounds.left(), bounds.right(), extents.x),
+            clamp(self.position.y, bounds.top(), bounds.bottom(), extents.y),
+        );
+    }
+    /// Top left and bottom right corners of the world area in view
+    pub fn visible_area(&self) -> (Vec2F, Vec2F) {
+        let extents = self.half_extents();
+        let center = self.center();
+        (center - extents, center + extents)
+    }
+    pub fn world_to_screen(&self, point: Vec2F) -> Vec2F {
+        let offset = point - self.center();
+        let (sin, cos) = self.rotation.sin_cos();
+        let rotated = Vec2F::new(
+            offset.x * cos + offset.y * sin,
+            -offset.x * sin + offset.y * cos,
+        );
+        rotated * self.zoom + self.viewport / 2.0
+    }
+    pub fn screen_to_world(&self, point: Vec2F) -> Vec2F {
+        let offset = (point - self.viewport / 2.0) / self.zoom;
+        let (sin, cos) = self.rotation.sin_cos();
+        Vec2F::new(
+            offset.x * cos - offset.y * sin,
+            offset.x * sin + offset.y * cos,
+        ) + self.center()
+    }
+    /// Where a mouse position on the frame buffer, as `Engine::mouse_position` gives it, points
+    /// at in the world. `None` when it is outside of the viewport.
+    pub fn mouse_position(&self, mouse: Vec2F) -> Option<Vec2F> {
+        let inside = mouse.x >= 0.0
+            && mouse.y >= 0.0
+            && mouse.x < self.viewport.x
+            && mouse.y < self.viewport.y;
+        inside.then(|| self.screen_to_world(mouse))
+    }
+    /// Blit `src_rect` with its `transform.pivot` placed at `position` in the world, the
+    /// camera's zoom and rotation are added on top of the transform's own
+    pub fn draw_image(
+        &self,
+        src: &impl ImageResource,
+        src_rect: Rect,
+        dst: &mut impl ImageResource,
+        position: Vec2F,
+        transform: &Transform,
+    ) {
+        let screen = self.world_to_screen(position);
+        let transform = Transform {
+            rotation: transform.rotation - self.rotation,
+            scale: transform.scale * self.zoom,
+            ..*transform
+        };
+        let position = Vec2::new(screen.x.round() as i32, screen.y.round() as i32);
+        blit_transformed(src, src_rect, dst, position, &transform);
+    }
+    /// Draw a sprite sheet frame with its pivot at `position` in the world
+    pub fn draw_frame(
+        &self,
+        image: &impl ImageResource,
+        frame: &Frame,
+        dst: &mut impl ImageResource,
+        position: Vec2F,
+        transform: &Transform,
+    ) {
+        let transform = Transform {
+            pivot: frame.pivot,
+            ..*transform
+        };
+        self.draw_image(image, frame.rect, dst, position, &transform);
+    }
+    pub fn draw_line(&self, start: Vec2F, end: Vec2F, dst: &mut impl ImageResource, color: Color) {
+        let to_screen = |point: Vec2F| {
+            let point = self.world_to_screen(point);
+            Vec2::new(point.x.round() as i32, point.y.round() as i32)
+        };
+        draw_line(to_screen(start), to_screen(end), dst, color);
+    }
+    /// Draw every visible layer of `map`. Without zoom or rotation the tiles are drawn straight
+    /// to `dst`, otherwise the part of the map in view is drawn offscreen first and then
+    /// transformed. The offscreen image is kept for the next frame.
+    pub fn draw_tilemap(
+        &mut self,
+        map: &Tilemap,
+        resource_manager: &ResourceManager,
+        dst: &mut impl ImageResource,
+        time: Duration,
+    ) {
+        let (min, max) = self.visible_area();
+        if self.zoom == 1.0 && self.rotation == 0.0 {
+            let top_left = Vec2::new(min.x.round() as i32, min.y.round() as i32);
+            map.draw(resource_manager, dst, top_left, time);
+            return;
+        }
+        // only the map itself needs drawing, however far out the camera is zoomed
+        let (map_width, map_height) = map.pixel_size();
+        let overhang = map_overhang(map);
+        let map_area = Rect::new(
+            Vec2::new(-overhang, -overhang),
+            map_width + overhang as u32 * 2,
+            map_height + overhang as u32 * 2,
+        );
+        let top_left = Vec2::new(min.x.floor() as i32, min.y.floor() as i32);
+        let view = Rect::new(
+            top_left,
+            (max.x.ceil() as i32 - top_left.x).max(0) as u32,
+            (max.y.ceil() as i32 - top_left.y).max(0) as u32,
+        );
+        let area = match view.intersection(&map_area) {
+            Some(area) => area,
+            None => return,
+        };
+        let mut image = match self.view.take() {
+            Some(mut image) if image.width() >= area.width && image.height() >= area.height => {
+                image.get_buf_u32_mut().fill(0);
+                image
+            }
+            _ => Image::blank(area.width, area.height),
+        };
+        map.draw(resource_manager, &mut image, area.top_left, time);
+        self.draw_image(
+            &image,
+            Rect::new(Vec2::new(0, 0), area.width, area.height),
+            dst,
+            Vec2F::from(area.top_left),
+            &Transform::default(),
+        );
+        self.view = Some(image);
+    }
+}
+
+/// How far tiles can reach past the map's own area, through tiles larger than the grid, tile
+/// offsets and layer offsets
+fn map_overhang(map: &Tilemap) -> i32 {
+    let tiles = map
+        .tilesets
+        .iter()
+        .map(|tileset| {
+            let size = tileset.tile_width.max(tileset.tile_height) as i32;
+            size + tileset.tile_offset.x.abs().max(tileset.tile_offset.y.abs())
+        })
+        .max()
+        .unwrap_or(0);
+    let layers = map
+        .layers
+        .iter()
+        .map(|layer| layer.offset.x.abs().max(layer.offset.y.abs()).ceil() as i32)
+        .max()
+        .unwrap_or(0);
+    tiles + layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: Vec2F, b: Vec2F) {
+        assert!(
+            (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_world_screen_conversion() {
+        let mut camera = Camera2D::new(100, 50);
+        camera.position = Vec2F::new(200.0, 100.0);
+        assert_close(
+            camera.world_to_screen(Vec2F::new(200.0, 100.0)),
+            Vec2F::new(50.0, 25.0),
+        );
+        assert_close(
+            camera.world_to_screen(Vec2F::new(210.0, 100.0)),
+            Vec2F::new(60.0, 25.0),
+        );
+
+        camera.zoom = 2.0;
+        assert_close(
+            camera.world_to_screen(Vec2F::new(210.0, 100.0)),
+            Vec2F::new(70.0, 25.0),
+        );
+        // turning the camera clockwise turns the world the other way
+        camera.rotation = FRAC_PI_2;
+        assert_close(
+            camera.world_to_screen(Vec2F::new(210.0, 100.0)),
+            Vec2F::new(50.0, 5.0),
+        );
+
+        camera.rotation = 0.7;
+        camera.zoom = 1.5;
+        let point = Vec2F::new(-31.0, 412.5);
+        assert_close(camera.screen_to_world(camera.world_to_screen(point)), point);
+
+        let mouse = Vec2F::new(10.0, 40.0);
+        assert_close(
+            camera.mouse_position(mouse).unwrap(),
+            camera.screen_to_world(mouse),
+        );
+        assert!(camera.mouse_position(Vec2F::new(100.0, 10.0)).is_none());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut camera = Camera2D::new(100, 50);
+        camera.bounds = Some(Rect::new(Vec2::new(0, 0), 400, 30));
+        camera.position = Vec2F::new(-100.0, 0.0);
+        camera.update(Duration::ZERO);
+        // too short to fit the view, so it is centred vertically
+        assert_close(camera.position, Vec2F::new(50.0, 15.0));
+
+        camera.zoom = 2.0;
+        camera.position = Vec2F::new(1000.0, 0.0);
+        camera.update(Duration::ZERO);
+        assert_close(camera.position, Vec2F::new(375.0, 12.5));
+        let (min, max) = camera.visible_area();
+        assert_close(min, Vec2F::new(350.0, 0.0));
+        assert_close(max, Vec2F::new(400.0, 25.0));
+    }
+
+    #[test]
+    fn test_follow() {
+        let mut camera = Camera2D::new(100, 100);
+        camera.position = Vec2F::new(0.0, 0.0);
+        camera.deadzone = Vec2F::new(20.0, 10.0);
+        camera.follow(Vec2F::new(8.0, -4.0), Duration::from_millis(16));
+        assert_close(camera.position, Vec2F::new(0.0, 0.0));
+        camera.follow(Vec2F::new(30.0, -4.0), Duration::from_millis(16));
+        assert_close(camera.position, Vec2F::new(20.0, 0.0));
+
+        camera.follow_speed = 10.0;
+        camera.follow(Vec2F::new(30.0, 25.0), Duration::from_millis(100));
+        let expected = 20.0 * (1.0 - (-1.0f32).exp());
+        assert_close(camera.position, Vec2F::new(20.0, expected));
+        for _ in 0..100 {
+            camera.follow(Vec2F::new(30.0, 25.0), Duration::from_millis(100));
+        }
+        assert_close(camera.position, Vec2F::new(20.0, 20.0));
+    }
+
+    #[test]
+    fn test_shake() {
+        let mut camera = Camera2D::new(100, 100);
+        camera.shake(4.0, Duration::from_millis(200));
+        camera.shake(1.0, Duration::from_secs(5));
+        for _ in 0..10 {
+            camera.update(Duration::from_millis(10));
+            let offset = camera.center() - camera.position;
+            assert!(offset.x.abs() <= 4.0 && offset.y.abs() <= 4.0);
+        }
+        camera.update(Duration::from_millis(200));
+        assert!(!camera.is_shaking());
+        assert_eq!(camera.center(), camera.position);
+    }
+
+    #[test]
+    fn test_draw_image() {
+        let white = u32::from(Color::new(255, 255, 255, 255));
+        let src = Image::new(2, 2, vec![255; 16]);
+        let mut dst = Image::blank(16, 16);
+        let mut camera = Camera2D::new(16, 16);
+        camera.position = Vec2F::new(100.0, 100.0);
+        camera.zoom = 2.0;
+        camera.draw_image(
+            &src,
+            Rect::new(Vec2::new(0, 0), 2, 2),
+            &mut dst,
+            Vec2F::new(101.0, 100.0),
+            &Transform::default(),
+        );
+        let buf = dst.get_buf_u32();
+        let filled: Vec<usize> = (0..buf.len()).filter(|&i| buf[i] == white).collect();
+        let expected: Vec<usize> = (8..12)
+            .flat_map(|y| (10..14).map(move |x| x + y * 16))
+            .collect();
+        assert_eq!(filled, expected);
+    }
+
+    #[test]
+    fn test_draw_tilemap_far_out() {
+        let mut resource_manager = ResourceManager::new();
+        let map = Tilemap::open(
+            std::path::Path::new("resources/maps/orthogonal.tmx"),
+            &mut resource_manager,
+        )
+        .unwrap();
+        let (map_width, map_height) = map.pixel_size();
+        let overhang = map_overhang(&map) as u32 * 2;
+        let mut camera = Camera2D::new(640, 480);
+        // the view covers millions of world pixels, only the map's own are drawn
+        camera.zoom = 0.001;
+        let mut dst = Image::blank(640, 480);
+        camera.draw_tilemap(&map, &resource_manager, &mut dst, Duration::ZERO);
+        let view = camera.view.as_ref().unwrap();
+        assert!(view.width() <= map_width + overhang);
+        assert!(view.height() <= map_height + overhang);
+
+        // the kept image is cleared before it is drawn into again
+        camera.zoom = 0.5;
+        camera.rotation = 0.3;
+        let mut first = Image::blank(640, 480);
+        camera.draw_tilemap(&map, &resource_manager, &mut first, Duration::ZERO);
+        let mut second = Image::blank(640, 480);
+        camera.draw_tilemap(&map, &resource_manager, &mut second, Duration::ZERO);
+        assert!(first.get_buf_u32().iter().any(|pixel| *pixel != 0));
+        assert_eq!(first.get_buf(), second.get_buf());
+    }
+}
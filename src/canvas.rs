@@ -0,0 +1,251 @@
+use crate::resource::ImageResource;
+use crate::types::{Rect, Vec2};
+
+/// Wraps any image, the `Screen` included, with a stack of clip rects that every blit,
+/// primitive and text drawn into it keeps to. Each pushed rect is narrowed down to the one
+/// below it, so nested panels can't draw outside their parents.
+///
+/// The `_unchecked` drawing functions skip clipping along with bounds checking.
+pub struct Canvas<'a, T: ImageResource> {
+    target: &'a mut T,
+    clips: Vec<Rect>,
+}
+
+impl<'a, T: ImageResource> Canvas<'a, T> {
+    /// Starts out clipped to whatever `target` is clipped to, usually all of it
+    pub fn new(target: &'a mut T) -> Self {
+        Self {
+            target,
+            clips: Vec::new(),
+        }
+    }
+    /// Limit drawing to `rect` within the current clip until the matching `pop_clip`
+    pub fn push_clip(&mut self, rect: Rect) {
+        let clip = rect
+            .intersection(&self.clip_rect())
+            .unwrap_or(Rect::new(Vec2::new(0, 0), 0, 0));
+        self.clips.push(clip);
+    }
+    /// Go back to the clip from before the last `push_clip`, returning the clip that was
+    /// removed or `None` if nothing was pushed
+    pub fn pop_clip(&mut self) -> Option<Rect> {
+        self.clips.pop()
+    }
+    /// How many clips are pushed
+    pub fn clip_depth(&self) -> usize {
+        self.clips.len()
+    }
+    /// Run `draw` with `rect` pushed, popping it again afterwards
+    pub fn with_clip<R>(&mut self, rect: Rect, draw: impl FnOnce(&mut Self) -> R) -> R {
+        self.push_clip(rect);
+        let result = draw(self);
+        self.pop_clip();
+        result
+    }
+    pub fn target(&self) -> &T {
+        self.target
+    }
+}
+
+impl<'a, T: ImageResource> ImageResource for Canvas<'a, T> {
+    fn width(&self) -> u32 {
+        self.target.width()
+    }
+    fn height(&self) -> u32 {
+        self.target.height()
+    }
+    fn get_buf(&self) -> &[u8] {
+        self.target.get_buf()
+    }
+    fn get_buf_mut(&mut self) -> &mut [u8] {
+        self.target.get_buf_mut()
+    }
+    fn get_buf_u32(&self) -> &[u32] {
+        self.target.get_buf_u32()
+    }
+    fn get_buf_u32_mut(&mut self) -> &mut [u32] {
+        self.target.get_buf_u32_mut()
+    }
+    fn clip_rect(&self) -> Rect {
+        match self.clips.last() {
+            Some(clip) => *clip,
+            None => self.target.clip_rect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+    use std::path::Path;
+
+    use crate::drawing::*;
+    use crate::resource::{FontHelper, Image, ResourceManager};
+    use crate::text::{TextBox, TextOutline, TextStyle};
+    use crate::types::{Color, FontSettings, Vec2F};
+    use crate::Screen;
+
+    const SIZE: u32 = 24;
+
+    /// Everything the drawing module can put on screen, each drawn across the whole image
+    fn draw_everything(dst: &mut Canvas<Image>, text: &mut impl FnMut(&mut Canvas<Image>)) {
+        let red = Color::new(255, 0, 0, 255);
+        let translucent = Color::new(0, 255, 0, 128);
+        let src = Image::new(SIZE, SIZE, vec![200; (SIZE * SIZE * 4) as usize]);
+        blit(&src, dst, Vec2::new(-12, 0));
+        blit_with_alpha(&src, dst, Vec2::new(12, 0));
+        let transform = Transform {
+            rotation: PI / 5.0,
+            scale: Vec2F::new(0.5, 0.5),
+            ..Transform::default()
+        };
+        blit_transformed(
+            &src,
+            Rect::new(Vec2::new(0, 0), SIZE, SIZE),
+            dst,
+            Vec2::new(12, 2),
+            &transform,
+        );
+        draw_line(Vec2::new(0, 23), Vec2::new(23, 0), dst, red);
+        draw_line_aa(
+            Vec2F::new(0.0, 3.0),
+            Vec2F::new(23.0, 20.0),
+            dst,
+            translucent,
+        );
+        draw_polyline(
+            &[
+                Vec2F::new(1.0, 1.0),
+                Vec2F::new(22.0, 12.0),
+                Vec2F::new(1.0, 22.0),
+            ],
+            &LineStyle {
+                width: 3.0,
+                ..LineStyle::default()
+            },
+            dst,
+            translucent,
+        );
+        draw_rectangle(Rect::new(Vec2::new(1, 1), 21, 21), dst, red);
+        fill_circle(Vec2::new(12, 12), 7, dst, translucent);
+        draw_circle(Vec2::new(12, 12), 10, dst, red);
+        fill_ellipse(Vec2::new(12, 12), 11, 4, dst, red);
+        fill_arc(Vec2::new(12, 12), 9, 0.0, PI, dst, translucent);
+        fill_polygon(
+            &[Vec2::new(0, 0), Vec2::new(24, 6), Vec2::new(6, 24)],
+            dst,
+            translucent,
+        );
+        text(dst);
+    }
+
+    /// Draws with and without `clip`, everything inside of it has to match and nothing
+    /// outside of it may be touched
+    fn check_clip(clip: Rect) {
+        let mut resource_manager = ResourceManager::new();
+        let font = resource_manager
+            .try_load_font(
+                Path::new("resources/fonts/JetbrainsMonoRegular.ttf"),
+                FontSettings::default(),
+            )
+            .unwrap();
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle {
+            outline: Some(TextOutline {
+                thickness: 1,
+                color: Color::new(0, 0, 255, 255),
+            }),
+            ..TextStyle::new(14.0, Color::new(255, 255, 255, 255))
+        };
+        let mut text = |dst: &mut Canvas<Image>| {
+            font_helper.draw_text(
+                &resource_manager,
+                font,
+                "Wg",
+                &style,
+                &TextBox::at(Vec2::new(2, 2)),
+                dst,
+            );
+        };
+
+        let mut expected = Image::blank(SIZE, SIZE);
+        draw_everything(&mut Canvas::new(&mut expected), &mut text);
+        let mut result = Image::blank(SIZE, SIZE);
+        let mut canvas = Canvas::new(&mut result);
+        canvas.push_clip(clip);
+        draw_everything(&mut canvas, &mut text);
+
+        let (expected, result) = (expected.get_buf_u32(), result.get_buf_u32());
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let index = (x + y * SIZE as i32) as usize;
+                let inside =
+                    x >= clip.left() && y >= clip.top() && x < clip.right() && y < clip.bottom();
+                if inside {
+                    assert_eq!(result[index], expected[index], "at {}, {}", x, y);
+                } else {
+                    assert_eq!(result[index], 0, "at {}, {}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_clip_stack() {
+        let mut image = Image::blank(16, 16);
+        let mut canvas = Canvas::new(&mut image);
+        let full = Rect::new(Vec2::new(0, 0), 16, 16);
+        assert_eq!(canvas.clip_rect(), full);
+        canvas.push_clip(Rect::new(Vec2::new(-4, 2), 10, 30));
+        assert_eq!(canvas.clip_rect(), Rect::new(Vec2::new(0, 2), 6, 14));
+        canvas.with_clip(Rect::new(Vec2::new(4, 0), 8, 8), |canvas| {
+            assert_eq!(canvas.clip_depth(), 2);
+            assert_eq!(canvas.clip_rect(), Rect::new(Vec2::new(4, 2), 2, 6));
+        });
+        canvas.push_clip(Rect::new(Vec2::new(10, 10), 2, 2));
+        assert_eq!(canvas.clip_rect().area(), 0);
+        canvas.pop_clip();
+        assert_eq!(canvas.pop_clip(), Some(Rect::new(Vec2::new(0, 2), 6, 14)));
+        assert_eq!(canvas.pop_clip(), None);
+        assert_eq!(canvas.clip_rect(), full);
+
+        // a canvas over a canvas starts out with the outer one's clip
+        let mut canvas = Canvas::new(&mut image);
+        canvas.push_clip(Rect::new(Vec2::new(4, 4), 4, 4));
+        let inner = Canvas::new(&mut canvas);
+        assert_eq!(inner.clip_rect(), Rect::new(Vec2::new(4, 4), 4, 4));
+    }
+
+    #[test]
+    fn test_partially_clipped() {
+        check_clip(Rect::new(Vec2::new(5, 3), 9, 13));
+        check_clip(Rect::new(Vec2::new(-10, 12), 20, 40));
+        check_clip(Rect::new(Vec2::new(0, 0), 1, 1));
+    }
+
+    #[test]
+    fn test_fully_clipped() {
+        check_clip(Rect::new(Vec2::new(30, 30), 10, 10));
+        check_clip(Rect::new(Vec2::new(8, 8), 0, 4));
+    }
+
+    #[test]
+    fn test_screen_canvas() {
+        let mut screen = Screen::headless(8, 8);
+        let mut canvas = Canvas::new(&mut screen);
+        canvas.push_clip(Rect::new(Vec2::new(2, 2), 4, 4));
+        fill_rectangle(
+            Rect::new(Vec2::new(0, 0), 8, 8),
+            &mut canvas,
+            Color::new(255, 255, 255, 255),
+        );
+        let filled = screen
+            .frame()
+            .get_buf_u32()
+            .iter()
+            .filter(|pixel| **pixel != 0)
+            .count();
+        assert_eq!(filled, 16);
+    }
+}
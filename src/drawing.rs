@@ -5,83 +5,154 @@ use fontdue::Font;
 
 use crate::constants::PIXEL_SIZE;
 use crate::resource::{Image, ImageResource};
-use crate::types::{Color, Rect, Vec2};
+use crate::types::{Color, Rect, Vec2, Vec2F};
 
-pub fn blit(src: &impl ImageResource, dst: &mut impl ImageResource, position: Vec2) {
-    // this function taken in part from blit crate
-    let src_width = src.width();
-    let src_height = src.height();
-    let dst_width = dst.width();
-
-    let src_buf = src.get_buf_u32();
-    let dst_buf = dst.get_buf_u32_mut();
-
-    let dst_size = (
-        dst_width as i32,
-        (dst_buf.len() / dst_width as usize) as i32,
-    );
-
-    let min_x = cmp::max(-position.x, 0);
-    let min_y = cmp::max(-position.y, 0);
-    let max_x = cmp::min(dst_size.0 - position.x, src_width as i32);
-    let max_y = cmp::min(dst_size.1 - position.y, src_height as i32);
+/// How source pixels are combined with the destination pixels they land on.
+/// Apart from `Replace` and `PremultipliedAlpha`, the blended colour is faded in by the source
+/// alpha, so e.g. a half transparent `Additive` source adds half as much light.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Replace,
+    #[default]
+    Alpha,
+    /// Source colour channels are already multiplied by its alpha
+    PremultipliedAlpha,
+    Additive,
+    Multiply,
+    Screen,
+    Subtract,
+    Min,
+    Max,
+}
 
-    for y in min_y..max_y {
-        for x in min_x..max_x {
-            let index = // TODO rethink these casts?
-                ((x + position.x) + ((y + position.y) * dst_width as i32)) as usize;
-            let src_index = (x + y * src_width as i32) as usize;
-            dst_buf[index] = src_buf[src_index];
+impl BlendMode {
+    #[inline]
+    pub fn blend(self, src: u32, dst: u32) -> u32 {
+        match self {
+            Self::Replace => src,
+            Self::Alpha => blend_alpha(src, dst),
+            Self::PremultipliedAlpha => blend_premultiplied_alpha(src, dst),
+            Self::Additive => blend_additive(src, dst),
+            Self::Multiply => blend_multiply(src, dst),
+            Self::Screen => blend_screen(src, dst),
+            Self::Subtract => blend_subtract(src, dst),
+            Self::Min => blend_min(src, dst),
+            Self::Max => blend_max(src, dst),
         }
     }
 }
 
+pub fn blit(src: &impl ImageResource, dst: &mut impl ImageResource, position: Vec2) {
+    blit_blended(src, dst, position, BlendMode::Replace);
+}
+
 pub fn blit_with_alpha(src: &impl ImageResource, dst: &mut impl ImageResource, position: Vec2) {
-    // how to blend with alpha https://stackoverflow.com/a/64655571/9057528
-    let src_width = src.width();
-    let src_height = src.height();
-    let dst_width = dst.width();
+    blit_blended(src, dst, position, BlendMode::Alpha);
+}
 
-    let src_buf = src.get_buf_u32();
-    let dst_buf = dst.get_buf_u32_mut();
+pub fn blit_blended(
+    src: &impl ImageResource,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+    blend_mode: BlendMode,
+) {
+    let src_rect = Rect::new(Vec2::new(0, 0), src.width(), src.height());
+    blit_rect_blended(src, src_rect, dst, position, blend_mode);
+}
 
-    let dst_size = (
-        dst_width as i32,
-        (dst_buf.len() / dst_width as usize) as i32,
-    );
+pub fn blit_rect(
+    src: &impl ImageResource,
+    src_rect: Rect,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+) {
+    blit_rect_blended(src, src_rect, dst, position, BlendMode::Replace);
+}
 
-    let min_x = cmp::max(-position.x, 0);
-    let min_y = cmp::max(-position.y, 0);
-    let max_x = cmp::min(dst_size.0 - position.x, src_width as i32);
-    let max_y = cmp::min(dst_size.1 - position.y, src_height as i32);
+pub fn blit_rect_with_alpha(
+    src: &impl ImageResource,
+    src_rect: Rect,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+) {
+    blit_rect_blended(src, src_rect, dst, position, BlendMode::Alpha);
+}
 
-    for y in min_y..max_y {
-        for x in min_x..max_x {
-            let dst_index = (x + position.x + ((y + position.y) * dst_width as i32)) as usize;
-            let src_index = (x + y * src_width as i32) as usize;
-            let out = blend_alpha(src_buf[src_index], dst_buf[dst_index]);
-            dst_buf[dst_index] = out;
+pub fn blit_rect_blended(
+    src: &impl ImageResource,
+    src_rect: Rect,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+    blend_mode: BlendMode,
+) {
+    // dispatch once up front so each mode gets its own inner loop
+    match blend_mode {
+        BlendMode::Replace => blit_rect_with(src, src_rect, dst, position, |src, _| src),
+        BlendMode::Alpha => blit_rect_with(src, src_rect, dst, position, blend_alpha),
+        BlendMode::PremultipliedAlpha => {
+            blit_rect_with(src, src_rect, dst, position, blend_premultiplied_alpha)
         }
+        BlendMode::Additive => blit_rect_with(src, src_rect, dst, position, blend_additive),
+        BlendMode::Multiply => blit_rect_with(src, src_rect, dst, position, blend_multiply),
+        BlendMode::Screen => blit_rect_with(src, src_rect, dst, position, blend_screen),
+        BlendMode::Subtract => blit_rect_with(src, src_rect, dst, position, blend_subtract),
+        BlendMode::Min => blit_rect_with(src, src_rect, dst, position, blend_min),
+        BlendMode::Max => blit_rect_with(src, src_rect, dst, position, blend_max),
     }
 }
 
-pub fn blit_rect(
+pub fn blit_tinted(
+    src: &impl ImageResource,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+    tint: Color,
+    opacity: f32,
+    blend_mode: BlendMode,
+) {
+    let src_rect = Rect::new(Vec2::new(0, 0), src.width(), src.height());
+    blit_rect_tinted(src, src_rect, dst, position, tint, opacity, blend_mode);
+}
+
+/// Like `blit_rect_blended`, with every source channel multiplied by `tint` and the alpha
+/// additionally multiplied by `opacity`, which is clamped to 0..=1
+pub fn blit_rect_tinted(
+    src: &impl ImageResource,
+    src_rect: Rect,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+    tint: Color,
+    opacity: f32,
+    blend_mode: BlendMode,
+) {
+    let opacity = opacity_to_u8(opacity);
+    blit_rect_with(src, src_rect, dst, position, |src, dst| {
+        blend_mode.blend(modulate(src, tint, opacity), dst)
+    });
+}
+
+#[inline]
+fn blit_rect_with(
     src: &impl ImageResource,
     src_rect: Rect,
     dst: &mut impl ImageResource,
     position: Vec2,
+    blend: impl Fn(u32, u32) -> u32,
 ) {
     // stolen shamelessly from OneLoneCoder's PixelGameEngine with bounds checking that ended up
     // looking like blit crate's
     let src_width = src.width() as i32;
     let src_height = src.height() as i32;
     let dst_width = dst.width() as i32;
-    let dst_height = dst.height() as i32;
-    let min_x = cmp::max(-position.x, 0);
-    let min_y = cmp::max(-position.y, 0);
-    let max_x = cmp::min(dst_width - position.x, src_rect.width as i32);
-    let max_y = cmp::min(dst_height - position.y, src_rect.height as i32);
-    if src_rect.right() > src_width || src_rect.bottom() > src_height {
+    let clip = dst.clip_rect();
+    let min_x = cmp::max(clip.left() - position.x, 0);
+    let min_y = cmp::max(clip.top() - position.y, 0);
+    let max_x = cmp::min(clip.right() - position.x, src_rect.width as i32);
+    let max_y = cmp::min(clip.bottom() - position.y, src_rect.height as i32);
+    if src_rect.left() < 0
+        || src_rect.top() < 0
+        || src_rect.right() > src_width
+        || src_rect.bottom() > src_height
+    {
         return;
     }
     let src_buf = src.get_buf_u32();
@@ -92,44 +163,192 @@ pub fn blit_rect(
             let dst_index = (position.x + x + (y + position.y) * dst_width) as usize;
             let src_index =
                 (x + src_rect.top_left.x + (y + src_rect.top_left.y) * src_width) as usize;
-            dst_buf[dst_index] = src_buf[src_index];
+            dst_buf[dst_index] = blend(src_buf[src_index], dst_buf[dst_index]);
         }
     }
 }
 
-pub fn blit_rect_with_alpha(
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Flip {
+    pub horizontal: bool,
+    pub vertical: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sampling {
+    NearestNeighbour,
+    Bilinear,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    /// Radians, clockwise on screen
+    pub rotation: f32,
+    pub scale: Vec2F,
+    /// Point of the (flipped) source rect that rotation and scaling happen around and that is
+    /// placed at the destination position
+    pub pivot: Vec2F,
+    pub flip: Flip,
+    pub sampling: Sampling,
+    pub blend_mode: BlendMode,
+    /// Multiplied into every sampled pixel, white leaves the source as is
+    pub tint: Color,
+    /// From 0 to 1, multiplied into the sampled alpha
+    pub opacity: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            scale: Vec2F::new(1.0, 1.0),
+            pivot: Vec2F::new(0.0, 0.0),
+            flip: Flip::default(),
+            sampling: Sampling::NearestNeighbour,
+            blend_mode: BlendMode::Alpha,
+            tint: Color::new(255, 255, 255, 255),
+            opacity: 1.0,
+        }
+    }
+}
+
+pub fn blit_transformed(
     src: &impl ImageResource,
     src_rect: Rect,
     dst: &mut impl ImageResource,
     position: Vec2,
+    transform: &Transform,
 ) {
-    // stolen shamelessly from OneLoneCoder's PixelGameEngine with bounds checking that ended up
-    // looking like blit crate's
     let src_width = src.width() as i32;
     let src_height = src.height() as i32;
     let dst_width = dst.width() as i32;
-    let dst_height = dst.height() as i32;
-    let min_x = cmp::max(-position.x, 0);
-    let min_y = cmp::max(-position.y, 0);
-    let max_x = cmp::min(dst_width - position.x, src_rect.width as i32);
-    let max_y = cmp::min(dst_height - position.y, src_rect.height as i32);
-    if src_rect.right() > src_width || src_rect.bottom() > src_height {
+    let clip = dst.clip_rect();
+    if src_rect.left() < 0
+        || src_rect.top() < 0
+        || src_rect.right() > src_width
+        || src_rect.bottom() > src_height
+        || src_rect.area() == 0
+        || transform.scale.x == 0.0
+        || transform.scale.y == 0.0
+    {
         return;
     }
+    let rect_width = src_rect.width as f32;
+    let rect_height = src_rect.height as f32;
+    let (sin, cos) = transform.rotation.sin_cos();
+    let origin = Vec2F::from(position);
+
+    // bounding box of the transformed rect, clipped to the destination
+    let corners = [
+        Vec2F::new(0.0, 0.0),
+        Vec2F::new(rect_width, 0.0),
+        Vec2F::new(0.0, rect_height),
+        Vec2F::new(rect_width, rect_height),
+    ];
+    let (mut min, mut max) = (
+        Vec2F::new(f32::MAX, f32::MAX),
+        Vec2F::new(f32::MIN, f32::MIN),
+    );
+    for corner in corners {
+        let scaled = Vec2F::new(
+            (corner.x - transform.pivot.x) * transform.scale.x,
+            (corner.y - transform.pivot.y) * transform.scale.y,
+        );
+        let rotated = Vec2F::new(
+            scaled.x * cos - scaled.y * sin,
+            scaled.x * sin + scaled.y * cos,
+        ) + origin;
+        min = Vec2F::new(min.x.min(rotated.x), min.y.min(rotated.y));
+        max = Vec2F::new(max.x.max(rotated.x), max.y.max(rotated.y));
+    }
+    let min_x = cmp::max(min.x.floor() as i32, clip.left());
+    let min_y = cmp::max(min.y.floor() as i32, clip.top());
+    let max_x = cmp::min(max.x.ceil() as i32, clip.right());
+    let max_y = cmp::min(max.y.ceil() as i32, clip.bottom());
+
+    let opacity = opacity_to_u8(transform.opacity);
     let src_buf = src.get_buf_u32();
     let dst_buf = dst.get_buf_u32_mut();
+    let sample_at = |x: i32, y: i32| {
+        let x = x.clamp(0, src_rect.width as i32 - 1) + src_rect.left();
+        let y = y.clamp(0, src_rect.height as i32 - 1) + src_rect.top();
+        src_buf[(x + y * src_width) as usize]
+    };
 
     for y in min_y..max_y {
         for x in min_x..max_x {
-            let dst_index = (position.x + x + (y + position.y) * dst_width) as usize;
-            let src_index =
-                (x + src_rect.top_left.x + (y + src_rect.top_left.y) * src_width) as usize;
-            let out = blend_alpha(src_buf[src_index], dst_buf[dst_index]);
-            dst_buf[dst_index] = out;
+            // map the destination pixel centre back into the source rect
+            let offset = Vec2F::new(x as f32 + 0.5, y as f32 + 0.5) - origin;
+            let mut u = (offset.x * cos + offset.y * sin) / transform.scale.x + transform.pivot.x;
+            let mut v = (-offset.x * sin + offset.y * cos) / transform.scale.y + transform.pivot.y;
+            if u < 0.0 || v < 0.0 || u >= rect_width || v >= rect_height {
+                continue;
+            }
+            if transform.flip.horizontal {
+                u = rect_width - u;
+            }
+            if transform.flip.vertical {
+                v = rect_height - v;
+            }
+            let color = match transform.sampling {
+                Sampling::NearestNeighbour => sample_at(u as i32, v as i32),
+                Sampling::Bilinear => {
+                    let (u, v) = (u - 0.5, v - 0.5);
+                    let (x0, y0) = (u.floor() as i32, v.floor() as i32);
+                    let (fx, fy) = (u - x0 as f32, v - y0 as f32);
+                    bilinear(
+                        sample_at(x0, y0),
+                        sample_at(x0 + 1, y0),
+                        sample_at(x0, y0 + 1),
+                        sample_at(x0 + 1, y0 + 1),
+                        fx,
+                        fy,
+                    )
+                }
+            };
+            let color = modulate(color, transform.tint, opacity);
+            let dst_index = (x + y * dst_width) as usize;
+            dst_buf[dst_index] = transform.blend_mode.blend(color, dst_buf[dst_index]);
         }
     }
 }
 
+/// Interpolates in premultiplied space so transparent texels don't bleed their colour
+fn bilinear(
+    top_left: u32,
+    top_right: u32,
+    bottom_left: u32,
+    bottom_right: u32,
+    fx: f32,
+    fy: f32,
+) -> u32 {
+    let weights = [
+        (1.0 - fx) * (1.0 - fy),
+        fx * (1.0 - fy),
+        (1.0 - fx) * fy,
+        fx * fy,
+    ];
+    let mut channels = [0.0_f32; 4];
+    for (texel, weight) in [top_left, top_right, bottom_left, bottom_right]
+        .iter()
+        .zip(weights)
+    {
+        let alpha = (texel >> 24) as f32 * weight;
+        channels[0] += (texel & 0xFF) as f32 * alpha;
+        channels[1] += ((texel >> 8) & 0xFF) as f32 * alpha;
+        channels[2] += ((texel >> 16) & 0xFF) as f32 * alpha;
+        channels[3] += alpha;
+    }
+    if channels[3] <= 0.0 {
+        return 0;
+    }
+    let r = (channels[0] / channels[3]).round() as u32;
+    let g = (channels[1] / channels[3]).round() as u32;
+    let b = (channels[2] / channels[3]).round() as u32;
+    let a = channels[3].round().min(255.0) as u32;
+    r | (g << 8) | (b << 16) | (a << 24)
+}
+
 #[inline]
 fn plot_unchecked(x: u32, y: u32, dst: &mut impl ImageResource, color: Color) {
     let dst_width = dst.width();
@@ -144,8 +363,7 @@ fn plot_unchecked(x: u32, y: u32, dst: &mut impl ImageResource, color: Color) {
 #[inline]
 fn plot(x: i32, y: i32, dst: &mut impl ImageResource, color: Color) {
     let dst_width = dst.width();
-    let dst_height = dst.height();
-    if x < 0 || y < 0 || x >= dst_width as i32 || y >= dst_height as i32 {
+    if !in_clip(x, y, dst) {
         return;
     }
     let index = ((x + y * dst_width as i32) * PIXEL_SIZE as i32) as usize;
@@ -156,6 +374,13 @@ fn plot(x: i32, y: i32, dst: &mut impl ImageResource, color: Color) {
     dst_buf[index + 3] = color.a;
 }
 
+/// Whether the pixel at (`x`, `y`) lies inside the clip rect of `dst`
+#[inline]
+fn in_clip(x: i32, y: i32, dst: &impl ImageResource) -> bool {
+    let clip = dst.clip_rect();
+    x >= clip.left() && y >= clip.top() && x < clip.right() && y < clip.bottom()
+}
+
 pub fn draw_line(start: Vec2, end: Vec2, dst: &mut impl ImageResource, color: Color) {
     // Bresenham's algorithm shamelessly stolen from wikipedia's pseudocode
     let distance_x = (end.x - start.x).abs();
@@ -188,6 +413,354 @@ pub fn draw_line(start: Vec2, end: Vec2, dst: &mut impl ImageResource, color: Co
     }
 }
 
+/// Blend `color` into the pixel at (`x`, `y`) with its alpha scaled by `coverage`
+#[inline]
+fn plot_blended(x: i32, y: i32, dst: &mut impl ImageResource, color: Color, coverage: f32) {
+    let dst_width = dst.width() as i32;
+    if !in_clip(x, y, dst) || coverage <= 0.0 {
+        return;
+    }
+    let alpha = (color.a as f32 * coverage.min(1.0)).round() as u8;
+    let src = u32::from(Color::new(color.r, color.g, color.b, alpha));
+    let index = (x + y * dst_width) as usize;
+    let dst_buf = dst.get_buf_u32_mut();
+    dst_buf[index] = blend_alpha(src, dst_buf[index]);
+}
+
+/// Anti-aliased single pixel line using Xiaolin Wu's algorithm.
+/// Like `draw_line`, integer coordinates are pixel centres. Endpoints are covered in proportion
+/// to how much of their pixel the line reaches into, so one on a pixel centre covers it by half.
+pub fn draw_line_aa(start: Vec2F, end: Vec2F, dst: &mut impl ImageResource, color: Color) {
+    let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+    let (mut start, mut end) = (start, end);
+    if steep {
+        start = Vec2F::new(start.y, start.x);
+        end = Vec2F::new(end.y, end.x);
+    }
+    if start.x > end.x {
+        std::mem::swap(&mut start, &mut end);
+    }
+    let mut plot = |x: i32, y: i32, coverage: f32| {
+        if steep {
+            plot_blended(y, x, dst, color, coverage);
+        } else {
+            plot_blended(x, y, dst, color, coverage);
+        }
+    };
+    let delta = end - start;
+    let gradient = if delta.x == 0.0 {
+        1.0
+    } else {
+        delta.y / delta.x
+    };
+
+    let x_start = start.x.round();
+    let y_start = start.y + gradient * (x_start - start.x);
+    let gap = 1.0 - fract_floor(start.x + 0.5);
+    plot(
+        x_start as i32,
+        y_start.floor() as i32,
+        (1.0 - fract_floor(y_start)) * gap,
+    );
+    plot(
+        x_start as i32,
+        y_start.floor() as i32 + 1,
+        fract_floor(y_start) * gap,
+    );
+
+    let x_end = end.x.round();
+    if x_end > x_start {
+        let y_end = end.y + gradient * (x_end - end.x);
+        let gap = fract_floor(end.x + 0.5);
+        plot(
+            x_end as i32,
+            y_end.floor() as i32,
+            (1.0 - fract_floor(y_end)) * gap,
+        );
+        plot(
+            x_end as i32,
+            y_end.floor() as i32 + 1,
+            fract_floor(y_end) * gap,
+        );
+    }
+
+    let mut y = y_start + gradient;
+    for x in x_start as i32 + 1..x_end as i32 {
+        plot(x, y.floor() as i32, 1.0 - fract_floor(y));
+        plot(x, y.floor() as i32 + 1, fract_floor(y));
+        y += gradient;
+    }
+}
+
+/// Unlike `f32::fract`, never negative
+#[inline]
+fn fract_floor(value: f32) -> f32 {
+    value - value.floor()
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LineCap {
+    /// The line stops exactly at its endpoints
+    #[default]
+    Butt,
+    Round,
+    /// The line extends past its endpoints by half its width
+    Square,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Bevel,
+    Round,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LineStyle {
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    /// Miter joins whose length, from the inner corner to the tip, is more than this many times
+    /// the line width fall back to bevels, the same ratio as SVG's `stroke-miterlimit`
+    pub miter_limit: f32,
+    pub anti_aliased: bool,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 4.0,
+            anti_aliased: true,
+        }
+    }
+}
+
+/// The pieces a stroke is built from, each able to tell how far a point is from its edge
+enum StrokeShape {
+    /// Convex, in either winding order
+    Polygon(Vec<Vec2F>),
+    Circle(Vec2F, f32),
+}
+
+impl StrokeShape {
+    fn bounds(&self) -> (Vec2F, Vec2F) {
+        match self {
+            Self::Polygon(points) => points.iter().fold(
+                (
+                    Vec2F::new(f32::MAX, f32::MAX),
+                    Vec2F::new(f32::MIN, f32::MIN),
+                ),
+                |(min, max), point| {
+                    (
+                        Vec2F::new(min.x.min(point.x), min.y.min(point.y)),
+                        Vec2F::new(max.x.max(point.x), max.y.max(point.y)),
+                    )
+                },
+            ),
+            Self::Circle(center, radius) => (
+                *center - Vec2F::new(*radius, *radius),
+                *center + Vec2F::new(*radius, *radius),
+            ),
+        }
+    }
+    /// Negative inside. Exact for circles, for polygons it is the furthest distance outside
+    /// of any edge which is close enough for anti-aliasing.
+    fn distance(&self, point: Vec2F) -> f32 {
+        match self {
+            Self::Polygon(points) => {
+                let area: f32 = points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a)| a.cross(points[(i + 1) % points.len()]))
+                    .sum();
+                let winding = if area < 0.0 { 1.0 } else { -1.0 };
+                points
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, a)| {
+                        let edge = points[(i + 1) % points.len()] - *a;
+                        let normal = edge.perpendicular().normalize() * winding;
+                        if normal.x == 0.0 && normal.y == 0.0 {
+                            return None;
+                        }
+                        Some((point - *a).dot(normal))
+                    })
+                    .fold(f32::MIN, f32::max)
+            }
+            Self::Circle(center, radius) => (point - *center).magnitude() - radius,
+        }
+    }
+}
+
+pub fn draw_thick_line(
+    start: Vec2F,
+    end: Vec2F,
+    style: &LineStyle,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    draw_polyline(&[start, end], style, dst, color);
+}
+
+/// Stroke the path through `points` with the given width, caps and joins.
+/// Like `fill_polygon`, points lie on pixel corners and coverage is sampled at pixel centres.
+/// The stroke's coverage is worked out as a whole before blending, so overlapping segments and
+/// joins of translucent lines don't darken.
+pub fn draw_polyline(
+    points: &[Vec2F],
+    style: &LineStyle,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    let mut points = points.to_vec();
+    points.dedup();
+    let half_width = style.width / 2.0;
+    if points.is_empty() || half_width <= 0.0 {
+        return;
+    }
+    let mut shapes = Vec::new();
+    let cap = |shapes: &mut Vec<StrokeShape>, point: Vec2F, direction: Vec2F| match style.cap {
+        LineCap::Butt => {}
+        LineCap::Round => shapes.push(StrokeShape::Circle(point, half_width)),
+        LineCap::Square => {
+            let along = direction * half_width;
+            let across = direction.perpendicular() * half_width;
+            shapes.push(StrokeShape::Polygon(vec![
+                point - across,
+                point - across + along,
+                point + across + along,
+                point + across,
+            ]));
+        }
+    };
+    if points.len() == 1 {
+        cap(&mut shapes, points[0], Vec2F::new(1.0, 0.0));
+        cap(&mut shapes, points[0], Vec2F::new(-1.0, 0.0));
+    }
+    for (i, segment) in points.windows(2).enumerate() {
+        let (a, b) = (segment[0], segment[1]);
+        let direction = (b - a).normalize();
+        let across = direction.perpendicular() * half_width;
+        shapes.push(StrokeShape::Polygon(vec![
+            a - across,
+            b - across,
+            b + across,
+            a + across,
+        ]));
+        if i == 0 {
+            cap(&mut shapes, a, direction * -1.0);
+        }
+        if i == points.len() - 2 {
+            cap(&mut shapes, b, direction);
+        }
+    }
+    for corner in points.windows(3) {
+        let (a, vertex, b) = (corner[0], corner[1], corner[2]);
+        let incoming = (vertex - a).normalize();
+        let outgoing = (b - vertex).normalize();
+        let turn = incoming.cross(outgoing);
+        if turn.abs() < f32::EPSILON && incoming.dot(outgoing) > 0.0 {
+            continue;
+        }
+        // the gap to fill is on the outside of the turn
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let outer_in = vertex + incoming.perpendicular() * half_width * side;
+        let outer_out = vertex + outgoing.perpendicular() * half_width * side;
+        let bevel = StrokeShape::Polygon(vec![vertex, outer_in, outer_out]);
+        match style.join {
+            LineJoin::Round => shapes.push(StrokeShape::Circle(vertex, half_width)),
+            LineJoin::Bevel => shapes.push(bevel),
+            LineJoin::Miter => {
+                let miter = (incoming.perpendicular() + outgoing.perpendicular()) * side;
+                let miter = miter.normalize();
+                let cos = miter.dot(incoming.perpendicular() * side);
+                let length = if cos > f32::EPSILON {
+                    half_width / cos
+                } else {
+                    f32::MAX
+                };
+                // `length` only reaches from the vertex to the tip, half of the miter
+                if 2.0 * length > style.miter_limit * style.width {
+                    shapes.push(bevel);
+                } else {
+                    shapes.push(StrokeShape::Polygon(vec![
+                        vertex,
+                        outer_in,
+                        vertex + miter * length,
+                        outer_out,
+                    ]));
+                }
+            }
+        }
+    }
+    fill_stroke_shapes(&shapes, style.anti_aliased, dst, color);
+}
+
+fn fill_stroke_shapes(
+    shapes: &[StrokeShape],
+    anti_aliased: bool,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    let clip = dst.clip_rect();
+    let clipped_bounds = |shape: &StrokeShape| {
+        let (min, max) = shape.bounds();
+        (
+            cmp::max(min.x.floor() as i32 - 1, clip.left()),
+            cmp::max(min.y.floor() as i32 - 1, clip.top()),
+            cmp::min(max.x.ceil() as i32 + 2, clip.right()),
+            cmp::min(max.y.ceil() as i32 + 2, clip.bottom()),
+        )
+    };
+    let (min_x, min_y, max_x, max_y) = shapes.iter().map(clipped_bounds).fold(
+        (i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+        |acc, bounds| {
+            (
+                cmp::min(acc.0, bounds.0),
+                cmp::min(acc.1, bounds.1),
+                cmp::max(acc.2, bounds.2),
+                cmp::max(acc.3, bounds.3),
+            )
+        },
+    );
+    if min_x >= max_x || min_y >= max_y {
+        return;
+    }
+    let width = (max_x - min_x) as usize;
+    let mut coverage = vec![0.0_f32; width * (max_y - min_y) as usize];
+    for shape in shapes {
+        let (start_x, start_y, end_x, end_y) = clipped_bounds(shape);
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let centre = Vec2F::new(x as f32 + 0.5, y as f32 + 0.5);
+                let value = if anti_aliased {
+                    (0.5 - shape.distance(centre)).clamp(0.0, 1.0)
+                } else {
+                    // nudging the sample point makes pixel centres exactly on an edge only
+                    // count for top and left edges
+                    let nudged = centre + Vec2F::new(1e-3, 1e-3);
+                    if shape.distance(nudged) <= 0.0 {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                let index = (x - min_x) as usize + (y - min_y) as usize * width;
+                coverage[index] = coverage[index].max(value);
+            }
+        }
+    }
+    for (i, value) in coverage.into_iter().enumerate() {
+        let x = min_x + (i % width) as i32;
+        let y = min_y + (i / width) as i32;
+        plot_blended(x, y, dst, color, value);
+    }
+}
+
 pub fn draw_triangle(p1: Vec2, p2: Vec2, p3: Vec2, dst: &mut impl ImageResource, color: Color) {
     draw_line(p1, p2, dst, color);
     draw_line(p2, p3, dst, color);
@@ -256,15 +829,274 @@ pub fn draw_rectangle(rect: Rect, dst: &mut impl ImageResource, color: Color) {
 }
 
 pub fn fill_rectangle_unchecked(rect: Rect, dst: &mut impl ImageResource, color: Color) {
-    for y in rect.top()..rect.height as i32 {
+    for y in rect.top()..rect.bottom() {
         draw_horizontal_unchecked(Vec2::new(rect.left(), y), rect.width, dst, color);
     }
 }
 
 pub fn fill_rectangle(rect: Rect, dst: &mut impl ImageResource, color: Color) {
-    for y in rect.top()..rect.height as i32 {
-        draw_horizontal(Vec2::new(rect.left(), y), rect.width, dst, color);
+    fill_polygon(
+        &[
+            rect.top_left,
+            rect.top_right(),
+            rect.bottom_right(),
+            rect.bottom_left(),
+        ],
+        dst,
+        color,
+    );
+}
+
+/// Fill the pixels from `x_start` up to but not including `x_end` on row `y`
+#[inline]
+fn fill_span(x_start: i32, x_end: i32, y: i32, dst: &mut impl ImageResource, color: Color) {
+    let dst_width = dst.width() as i32;
+    let clip = dst.clip_rect();
+    let x_start = cmp::max(x_start, clip.left());
+    let x_end = cmp::min(x_end, clip.right());
+    if y < clip.top() || y >= clip.bottom() || x_start >= x_end {
+        return;
+    }
+    let row = (y * dst_width) as usize;
+    dst.get_buf_u32_mut()[row + x_start as usize..row + x_end as usize].fill(color.into());
+}
+
+pub fn draw_polygon(points: &[Vec2], dst: &mut impl ImageResource, color: Color) {
+    for (i, point) in points.iter().enumerate() {
+        draw_line(*point, points[(i + 1) % points.len()], dst, color);
+    }
+}
+
+/// Scanline fill of any simple or self intersecting polygon using the even-odd rule.
+/// Vertices lie on pixel corners and a pixel is filled when its centre is inside, with centres
+/// exactly on a left or top edge counting as inside and those on a right or bottom edge as
+/// outside, so polygons sharing an edge never overlap or leave a gap.
+pub fn fill_polygon(points: &[Vec2], dst: &mut impl ImageResource, color: Color) {
+    if points.len() < 3 {
+        return;
     }
+    let min_y = points.iter().map(|point| point.y).min().unwrap();
+    let max_y = points.iter().map(|point| point.y).max().unwrap();
+    let clip = dst.clip_rect();
+    let min_y = cmp::max(min_y, clip.top());
+    let max_y = cmp::min(max_y, clip.bottom());
+    let mut crossings = Vec::new();
+    for y in min_y..max_y {
+        let centre_y = y as f32 + 0.5;
+        crossings.clear();
+        for (i, start) in points.iter().enumerate() {
+            let end = points[(i + 1) % points.len()];
+            let (top, bottom) = if start.y < end.y {
+                (*start, end)
+            } else {
+                (end, *start)
+            };
+            // half open so a vertex shared by two edges is only counted once
+            if centre_y < top.y as f32 || centre_y >= bottom.y as f32 {
+                continue;
+            }
+            let t = (centre_y - top.y as f32) / (bottom.y - top.y) as f32;
+            crossings.push(top.x as f32 + t * (bottom.x - top.x) as f32);
+        }
+        crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for pair in crossings.chunks_exact(2) {
+            let x_start = (pair[0] - 0.5).ceil() as i32;
+            let x_end = (pair[1] - 0.5).ceil() as i32;
+            fill_span(x_start, x_end, y, dst, color);
+        }
+    }
+}
+
+pub fn fill_triangle(p1: Vec2, p2: Vec2, p3: Vec2, dst: &mut impl ImageResource, color: Color) {
+    fill_polygon(&[p1, p2, p3], dst, color);
+}
+
+/// Columns `start..end` of row `y` whose pixel centres lie inside an ellipse around `center`.
+/// Like polygon vertices, `center` is a pixel corner and the row is cut where `fill_polygon`
+/// would cut an edge, so an ellipse covers exactly the rectangle bounding it.
+fn ellipse_row(center: Vec2, radius_x: u32, radius_y: u32, y: i32) -> Option<(i32, i32)> {
+    if radius_y == 0 {
+        return None;
+    }
+    let v = (y as f32 + 0.5 - center.y as f32) / radius_y as f32;
+    if v.abs() >= 1.0 {
+        return None;
+    }
+    let half_width = radius_x as f32 * (1.0 - v * v).sqrt();
+    let start = (center.x as f32 - half_width - 0.5).ceil() as i32;
+    let end = (center.x as f32 + half_width - 0.5).ceil() as i32;
+    (start < end).then_some((start, end))
+}
+
+/// The parts of `row` with a pixel next to them that is outside the shape, given the rows
+/// above and below it
+fn outline_spans(
+    (start, end): (i32, i32),
+    above: Option<(i32, i32)>,
+    below: Option<(i32, i32)>,
+) -> [(i32, i32); 2] {
+    let (inner_start, inner_end) = match (above, below) {
+        (Some(above), Some(below)) => (
+            (start + 1).max(above.0).max(below.0),
+            (end - 1).min(above.1).min(below.1),
+        ),
+        _ => (end, end),
+    };
+    if inner_start >= inner_end {
+        [(start, end), (end, end)]
+    } else {
+        [(start, inner_start), (inner_end, end)]
+    }
+}
+
+/// Columns of row `y` whose pixel centres lie in the clockwise sweep from `start_angle` to
+/// `end_angle` around the pixel corner `center`, as at most two spans that don't overlap
+fn sector_spans(center: Vec2, start_angle: f32, end_angle: f32, y: i32) -> Vec<(i32, i32)> {
+    use std::f32::consts::{PI, TAU};
+    const ALL: (i32, i32) = (i32::MIN, i32::MAX);
+    let sweep = end_angle - start_angle;
+    if sweep >= TAU {
+        return vec![ALL];
+    }
+    let v = y as f32 + 0.5 - center.y as f32;
+    // the columns clockwise of the ray at `angle`, or anticlockwise of it, those where
+    // `sin * u <= cos * v` for `u` the pixel centre relative to `center`
+    let side = |angle: f32, clockwise: bool| {
+        let (sin, cos) = angle.sin_cos();
+        let (sin, cos) = if clockwise { (sin, cos) } else { (-sin, -cos) };
+        if sin == 0.0 {
+            return (cos * v >= 0.0).then_some(ALL);
+        }
+        let bound = cos * v / sin + center.x as f32 - 0.5;
+        Some(if sin > 0.0 {
+            (i32::MIN, (bound.floor() as i32).saturating_add(1))
+        } else {
+            (bound.ceil() as i32, i32::MAX)
+        })
+    };
+    let from = side(start_angle, true);
+    let to = side(end_angle, false);
+    let mut spans = if sweep.rem_euclid(TAU) <= PI {
+        // a convex wedge is where both sides agree
+        match (from, to) {
+            (Some(from), Some(to)) => vec![(from.0.max(to.0), from.1.min(to.1))],
+            _ => Vec::new(),
+        }
+    } else {
+        let mut spans = from.into_iter().chain(to).collect::<Vec<_>>();
+        spans.sort_unstable();
+        if let [first, second] = spans[..] {
+            if second.0 <= first.1 {
+                spans = vec![(first.0, first.1.max(second.1))];
+            }
+        }
+        spans
+    };
+    spans.retain(|(start, end)| start < end);
+    spans
+}
+
+/// Fill the rows of an ellipse, only its outline when `outline` is set, only the part inside
+/// `sweep` when given
+fn ellipse_spans(
+    center: Vec2,
+    radius_x: u32,
+    radius_y: u32,
+    outline: bool,
+    sweep: Option<(f32, f32)>,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    let clip = dst.clip_rect();
+    let top = cmp::max(center.y - radius_y as i32, clip.top());
+    let bottom = cmp::min(center.y + radius_y as i32, clip.bottom());
+    for y in top..bottom {
+        let row = match ellipse_row(center, radius_x, radius_y, y) {
+            Some(row) => row,
+            None => continue,
+        };
+        let spans = if outline {
+            let above = ellipse_row(center, radius_x, radius_y, y - 1);
+            let below = ellipse_row(center, radius_x, radius_y, y + 1);
+            outline_spans(row, above, below)
+        } else {
+            [row, (row.1, row.1)]
+        };
+        let sectors = match sweep {
+            Some((start_angle, end_angle)) => sector_spans(center, start_angle, end_angle, y),
+            None => vec![(i32::MIN, i32::MAX)],
+        };
+        for (start, end) in spans {
+            for (sector_start, sector_end) in &sectors {
+                fill_span(
+                    start.max(*sector_start),
+                    end.min(*sector_end),
+                    y,
+                    dst,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// Circles follow the same rule as `fill_polygon`: `center` is a pixel corner and a circle
+/// covers the pixels whose centres are inside it, so it fits the `2 * radius` square around
+/// `center`. The outline is made of the pixels of `fill_circle` that border the outside.
+pub fn draw_circle(center: Vec2, radius: u32, dst: &mut impl ImageResource, color: Color) {
+    ellipse_spans(center, radius, radius, true, None, dst, color);
+}
+
+pub fn fill_circle(center: Vec2, radius: u32, dst: &mut impl ImageResource, color: Color) {
+    ellipse_spans(center, radius, radius, false, None, dst, color);
+}
+
+/// Like circles, ellipses are centred on the pixel corner at `center`
+pub fn draw_ellipse(
+    center: Vec2,
+    radius_x: u32,
+    radius_y: u32,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    ellipse_spans(center, radius_x, radius_y, true, None, dst, color);
+}
+
+pub fn fill_ellipse(
+    center: Vec2,
+    radius_x: u32,
+    radius_y: u32,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    ellipse_spans(center, radius_x, radius_y, false, None, dst, color);
+}
+
+/// The part of `draw_circle` running clockwise from `start_angle` to `end_angle`, in radians
+pub fn draw_arc(
+    center: Vec2,
+    radius: u32,
+    start_angle: f32,
+    end_angle: f32,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    let sweep = Some((start_angle, end_angle));
+    ellipse_spans(center, radius, radius, true, sweep, dst, color);
+}
+
+/// The pie slice of `fill_circle` running clockwise from `start_angle` to `end_angle`, in
+/// radians. Pixels are in the slice when their centres are.
+pub fn fill_arc(
+    center: Vec2,
+    radius: u32,
+    start_angle: f32,
+    end_angle: f32,
+    dst: &mut impl ImageResource,
+    color: Color,
+) {
+    let sweep = Some((start_angle, end_angle));
+    ellipse_spans(center, radius, radius, false, sweep, dst, color);
 }
 
 pub fn draw_text(
@@ -276,7 +1108,6 @@ pub fn draw_text(
     screen: &mut impl ImageResource,
     offset: Vec2,
 ) {
-    // Note that the alpha channel in color is currently ignored
     layout.reset(&LayoutSettings {
         ..LayoutSettings::default()
     });
@@ -285,17 +1116,20 @@ pub fn draw_text(
         let (metrics, coverage) = font.rasterize(glyph.parent, size);
         let glyph_image_buf_32 = coverage
             .iter()
-            .map(|mask| mask_to_u32(color.r, color.g, color.b, *mask))
+            .map(|mask| mask_to_u32(255, 255, 255, *mask))
             .collect::<Vec<u32>>();
         let glyph_image_buf = unsafe { glyph_image_buf_32.align_to::<u8>().1.to_vec() };
         let glyph_image = Image::new(metrics.width as u32, metrics.height as u32, glyph_image_buf);
-        blit_with_alpha(
+        blit_tinted(
             &glyph_image,
             screen,
             Vec2 {
                 x: glyph.x as i32 + offset.x,
                 y: glyph.y as i32 + offset.y,
             },
+            color,
+            1.0,
+            BlendMode::Alpha,
         );
     }
 }
@@ -307,16 +1141,17 @@ pub fn draw_text_to_image(
     size: f32,
     color: Color,
 ) -> Image {
-    // Note that the alpha channel in color is currently ignored
     layout.reset(&LayoutSettings {
         ..LayoutSettings::default()
     });
     layout.append(&[font], &TextStyle::new(text, size, 0));
     let glyphs = layout.glyphs();
-    let last_glyph = glyphs.last().unwrap();
-    let width = last_glyph.x as usize + last_glyph.width;
-    let line = layout.lines().unwrap()[0];
-    let height = (line.max_ascent - line.min_descent) as usize;
+    let width = glyphs
+        .iter()
+        .map(|glyph| glyph.x as usize + glyph.width)
+        .max()
+        .unwrap_or(0);
+    let height = layout.height().ceil() as usize;
     let mut result_image = Image::new(
         width as u32,
         height as u32,
@@ -326,24 +1161,42 @@ pub fn draw_text_to_image(
         let (metrics, coverage) = font.rasterize(glyph.parent, size);
         let glyph_image_buf_32 = coverage
             .iter()
-            .map(|mask| mask_to_u32(color.r, color.g, color.b, *mask))
+            .map(|mask| mask_to_u32(255, 255, 255, *mask))
             .collect::<Vec<u32>>();
         let glyph_image_buf = unsafe { glyph_image_buf_32.align_to::<u8>().1.to_vec() };
         let glyph_image = Image::new(metrics.width as u32, metrics.height as u32, glyph_image_buf);
-        blit_with_alpha(
+        blit_tinted(
             &glyph_image,
             &mut result_image,
             Vec2 {
                 x: glyph.x as i32,
                 y: glyph.y as i32,
             },
+            color,
+            1.0,
+            BlendMode::Alpha,
         );
     }
     result_image
 }
 
+#[inline]
+fn opacity_to_u8(opacity: f32) -> u8 {
+    (opacity.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[inline]
+fn modulate(pixel: u32, tint: Color, opacity: u8) -> u32 {
+    let r = (pixel & 0xFF) * tint.r as u32 / 255;
+    let g = ((pixel >> 8) & 0xFF) * tint.g as u32 / 255;
+    let b = ((pixel >> 16) & 0xFF) * tint.b as u32 / 255;
+    let a = (pixel >> 24) * tint.a as u32 / 255 * opacity as u32 / 255;
+    r | (g << 8) | (b << 16) | (a << 24)
+}
+
 #[inline]
 fn blend_alpha(src: u32, dst: u32) -> u32 {
+    // how to blend with alpha https://stackoverflow.com/a/64655571/9057528
     let src_r = src & 0xFF;
     let src_g = (src & 0xFF00) >> 8;
     let src_b = (src & 0xFF0000) >> 16;
@@ -362,6 +1215,64 @@ fn blend_alpha(src: u32, dst: u32) -> u32 {
     r_out | (g_out << 8) | (b_out << 16) | (a_out << 24)
 }
 
+#[inline]
+fn blend_premultiplied_alpha(src: u32, dst: u32) -> u32 {
+    let src_a = src >> 24;
+    let mut out = 0;
+    for shift in [0, 8, 16, 24] {
+        let src_c = (src >> shift) & 0xFF;
+        let dst_c = (dst >> shift) & 0xFF;
+        out |= cmp::min(src_c + dst_c * (255 - src_a) / 255, 255) << shift;
+    }
+    out
+}
+
+/// Fade from the destination colour towards `blend(src, dst)` of each colour channel by the
+/// source alpha, compositing alpha the same way as `blend_alpha`
+#[inline]
+fn blend_channels(src: u32, dst: u32, blend: impl Fn(u32, u32) -> u32) -> u32 {
+    let src_a = src >> 24;
+    let dst_a = dst >> 24;
+    let mut out = (src_a + (dst_a * (255 - src_a) / 255)) << 24;
+    for shift in [0, 8, 16] {
+        let src_c = (src >> shift) & 0xFF;
+        let dst_c = (dst >> shift) & 0xFF;
+        let blended = blend(src_c, dst_c);
+        out |= ((blended * src_a + dst_c * (255 - src_a)) / 255) << shift;
+    }
+    out
+}
+
+#[inline]
+fn blend_additive(src: u32, dst: u32) -> u32 {
+    blend_channels(src, dst, |s, d| cmp::min(s + d, 255))
+}
+
+#[inline]
+fn blend_multiply(src: u32, dst: u32) -> u32 {
+    blend_channels(src, dst, |s, d| s * d / 255)
+}
+
+#[inline]
+fn blend_screen(src: u32, dst: u32) -> u32 {
+    blend_channels(src, dst, |s, d| 255 - (255 - s) * (255 - d) / 255)
+}
+
+#[inline]
+fn blend_subtract(src: u32, dst: u32) -> u32 {
+    blend_channels(src, dst, |s, d| d.saturating_sub(s))
+}
+
+#[inline]
+fn blend_min(src: u32, dst: u32) -> u32 {
+    blend_channels(src, dst, cmp::min)
+}
+
+#[inline]
+fn blend_max(src: u32, dst: u32) -> u32 {
+    blend_channels(src, dst, cmp::max)
+}
+
 #[inline]
 pub fn mask_to_u32(r: u8, g: u8, b: u8, mask: u8) -> u32 {
     ((mask as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | b as u32
@@ -503,4 +1414,625 @@ mod tests {
         screen
     }
     */
+
+    const A: u32 = 0xFF0000FF;
+    const B: u32 = 0xFF00FF00;
+    const BLACK: u32 = 0xFF000000;
+
+    fn image_from_u32(width: u32, height: u32, pixels: &[u32]) -> Image {
+        Image::new(width, height, unsafe { pixels.align_to::<u8>().1.to_vec() })
+    }
+
+    fn test_blit_transformed(position: Vec2, transform: &Transform) -> Image {
+        let mut screen = image_from_u32(4, 4, &[BLACK; 16]);
+        let image = image_from_u32(2, 1, &[A, B]);
+        blit_transformed(
+            &image,
+            Rect::new(Vec2::new(0, 0), 2, 1),
+            &mut screen,
+            position,
+            transform,
+        );
+        screen
+    }
+
+    #[test]
+    fn test_blit_transformed_identity_matches_blit() {
+        let (mut expected, image) = get_images();
+        blit_with_alpha(&image, &mut expected, Vec2::new(2, 3));
+        let (mut result, image) = get_images();
+        blit_transformed(
+            &image,
+            Rect::new(Vec2::new(0, 0), IMAGE_WIDTH, IMAGE_HEIGHT),
+            &mut result,
+            Vec2::new(2, 3),
+            &Transform::default(),
+        );
+        assert_eq!(result.get_buf(), expected.get_buf());
+    }
+
+    #[test]
+    fn test_blit_transformed_rotate() {
+        let transform = Transform {
+            rotation: std::f32::consts::FRAC_PI_2,
+            ..Transform::default()
+        };
+        let result = test_blit_transformed(Vec2::new(2, 1), &transform);
+        #[rustfmt::skip]
+        let expected = [
+            BLACK, BLACK, BLACK, BLACK,
+            BLACK, A, BLACK, BLACK,
+            BLACK, B, BLACK, BLACK,
+            BLACK, BLACK, BLACK, BLACK,
+        ];
+        assert_eq!(result.get_buf_u32(), expected);
+    }
+
+    #[test]
+    fn test_blit_transformed_scale_and_flip() {
+        let transform = Transform {
+            scale: Vec2F::new(2.0, 2.0),
+            flip: Flip {
+                horizontal: true,
+                vertical: false,
+            },
+            ..Transform::default()
+        };
+        let result = test_blit_transformed(Vec2::new(0, 1), &transform);
+        #[rustfmt::skip]
+        let expected = [
+            BLACK, BLACK, BLACK, BLACK,
+            B, B, A, A,
+            B, B, A, A,
+            BLACK, BLACK, BLACK, BLACK,
+        ];
+        assert_eq!(result.get_buf_u32(), expected);
+    }
+
+    #[test]
+    fn test_blit_transformed_clipped() {
+        let transform = Transform {
+            scale: Vec2F::new(3.0, 3.0),
+            pivot: Vec2F::new(1.0, 0.5),
+            ..Transform::default()
+        };
+        let result = test_blit_transformed(Vec2::new(0, 4), &transform);
+        #[rustfmt::skip]
+        let expected = [
+            BLACK, BLACK, BLACK, BLACK,
+            BLACK, BLACK, BLACK, BLACK,
+            B, B, B, BLACK,
+            B, B, B, BLACK,
+        ];
+        assert_eq!(result.get_buf_u32(), expected);
+    }
+
+    #[test]
+    fn test_blit_transformed_bilinear_solid() {
+        let (mut result, image) = get_images();
+        let transform = Transform {
+            rotation: 0.3,
+            scale: Vec2F::new(1.5, 1.5),
+            pivot: Vec2F::new(2.0, 2.0),
+            sampling: Sampling::Bilinear,
+            ..Transform::default()
+        };
+        blit_transformed(
+            &image,
+            Rect::new(Vec2::new(0, 0), IMAGE_WIDTH, IMAGE_HEIGHT),
+            &mut result,
+            Vec2::new(4, 4),
+            &transform,
+        );
+        assert_eq!(
+            result.get_buf_u32()[(4 + 4 * SCREEN_WIDTH) as usize],
+            0xFFCCBBAA
+        );
+        assert_eq!(result.get_buf_u32()[0], 0xFF000000);
+    }
+
+    fn rgba(r: u32, g: u32, b: u32, a: u32) -> u32 {
+        r | (g << 8) | (b << 16) | (a << 24)
+    }
+
+    #[test]
+    fn test_blend_modes_opaque() {
+        let src = rgba(200, 100, 50, 255);
+        let dst = rgba(100, 100, 100, 255);
+        let cases = [
+            (BlendMode::Replace, rgba(200, 100, 50, 255)),
+            (BlendMode::Alpha, rgba(200, 100, 50, 255)),
+            (BlendMode::Additive, rgba(255, 200, 150, 255)),
+            (BlendMode::Multiply, rgba(78, 39, 19, 255)),
+            (BlendMode::Screen, rgba(222, 161, 131, 255)),
+            (BlendMode::Subtract, rgba(0, 0, 50, 255)),
+            (BlendMode::Min, rgba(100, 100, 50, 255)),
+            (BlendMode::Max, rgba(200, 100, 100, 255)),
+        ];
+        for (mode, expected) in cases {
+            assert_eq!(mode.blend(src, dst), expected, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_blend_modes_translucent() {
+        let dst = rgba(100, 100, 100, 255);
+        assert_eq!(
+            BlendMode::Additive.blend(rgba(200, 100, 50, 128), dst),
+            rgba(177, 150, 125, 255)
+        );
+        assert_eq!(
+            BlendMode::PremultipliedAlpha.blend(rgba(100, 50, 25, 128), dst),
+            rgba(149, 99, 74, 255)
+        );
+    }
+
+    #[test]
+    fn test_blit_rect_blended() {
+        let mut screen = image_from_u32(2, 2, &[rgba(100, 100, 100, 255); 4]);
+        let image = image_from_u32(2, 1, &[rgba(10, 20, 30, 255), rgba(200, 200, 200, 255)]);
+        blit_rect_blended(
+            &image,
+            Rect::new(Vec2::new(1, 0), 1, 1),
+            &mut screen,
+            Vec2::new(1, 1),
+            BlendMode::Additive,
+        );
+        #[rustfmt::skip]
+        let expected = [
+            rgba(100, 100, 100, 255), rgba(100, 100, 100, 255),
+            rgba(100, 100, 100, 255), rgba(255, 255, 255, 255),
+        ];
+        assert_eq!(screen.get_buf_u32(), expected);
+    }
+
+    #[test]
+    fn test_blit_tinted() {
+        let mut screen = image_from_u32(2, 1, &[rgba(0, 0, 0, 255); 2]);
+        let image = image_from_u32(1, 1, &[rgba(255, 200, 100, 255)]);
+        blit_tinted(
+            &image,
+            &mut screen,
+            Vec2::new(0, 0),
+            Color::new(255, 0, 255, 255),
+            1.0,
+            BlendMode::Alpha,
+        );
+        blit_tinted(
+            &image,
+            &mut screen,
+            Vec2::new(1, 0),
+            Color::new(255, 255, 255, 255),
+            0.5,
+            BlendMode::Alpha,
+        );
+        assert_eq!(
+            screen.get_buf_u32(),
+            [rgba(255, 0, 100, 255), rgba(128, 100, 50, 255)]
+        );
+    }
+
+    #[test]
+    fn test_blit_transformed_tinted() {
+        let transform = Transform {
+            tint: Color::new(0, 255, 0, 255),
+            opacity: 0.0,
+            ..Transform::default()
+        };
+        let result = test_blit_transformed(Vec2::new(0, 0), &transform);
+        assert_eq!(result.get_buf_u32(), [BLACK; 16]);
+        let transform = Transform {
+            tint: Color::new(0, 255, 0, 255),
+            ..Transform::default()
+        };
+        let result = test_blit_transformed(Vec2::new(0, 0), &transform);
+        assert_eq!(result.get_buf_u32()[..2], [BLACK, B]);
+    }
+
+    /// Renders `draw` onto a blank image and turns it into rows of '#' for set pixels and '.'
+    /// for untouched ones
+    fn bitmap(width: u32, height: u32, draw: impl Fn(&mut Image)) -> Vec<String> {
+        let mut image = image_from_u32(width, height, &vec![0; (width * height) as usize]);
+        draw(&mut image);
+        image
+            .get_buf_u32()
+            .chunks(width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|pixel| if *pixel == 0 { '.' } else { '#' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    const WHITE: Color = Color::new(255, 255, 255, 255);
+
+    #[test]
+    fn test_fill_rectangle() {
+        let result = bitmap(6, 6, |image| {
+            fill_rectangle(Rect::new(Vec2::new(1, 2), 3, 2), image, WHITE)
+        });
+        assert_eq!(
+            result,
+            ["......", "......", ".###..", ".###..", "......", "......"]
+        );
+        let unchecked = bitmap(6, 6, |image| {
+            fill_rectangle_unchecked(Rect::new(Vec2::new(1, 2), 3, 2), image, WHITE)
+        });
+        assert_eq!(result, unchecked);
+    }
+
+    #[test]
+    fn test_fill_triangles_sharing_an_edge() {
+        let first = bitmap(6, 6, |image| {
+            fill_triangle(
+                Vec2::new(0, 0),
+                Vec2::new(6, 0),
+                Vec2::new(0, 6),
+                image,
+                WHITE,
+            )
+        });
+        let second = bitmap(6, 6, |image| {
+            fill_triangle(
+                Vec2::new(6, 0),
+                Vec2::new(6, 6),
+                Vec2::new(0, 6),
+                image,
+                WHITE,
+            )
+        });
+        assert_eq!(
+            first,
+            ["#####.", "####..", "###...", "##....", "#.....", "......"]
+        );
+        assert_eq!(
+            second,
+            [".....#", "....##", "...###", "..####", ".#####", "######"]
+        );
+    }
+
+    #[test]
+    fn test_fill_polygon_concave() {
+        let points = [
+            Vec2::new(0, 0),
+            Vec2::new(2, 0),
+            Vec2::new(2, 4),
+            Vec2::new(4, 4),
+            Vec2::new(4, 0),
+            Vec2::new(6, 0),
+            Vec2::new(6, 6),
+            Vec2::new(0, 6),
+        ];
+        let result = bitmap(6, 6, |image| fill_polygon(&points, image, WHITE));
+        assert_eq!(
+            result,
+            ["##..##", "##..##", "##..##", "##..##", "######", "######"]
+        );
+    }
+
+    #[test]
+    fn test_circle() {
+        let filled = bitmap(9, 9, |image| fill_circle(Vec2::new(4, 4), 4, image, WHITE));
+        let outline = bitmap(9, 9, |image| draw_circle(Vec2::new(4, 4), 4, image, WHITE));
+        #[rustfmt::skip]
+        let expected_filled = [
+            "..####...",
+            ".######..",
+            "########.",
+            "########.",
+            "########.",
+            "########.",
+            ".######..",
+            "..####...",
+            ".........",
+        ];
+        #[rustfmt::skip]
+        let expected_outline = [
+            "..####...",
+            ".#....#..",
+            "#......#.",
+            "#......#.",
+            "#......#.",
+            "#......#.",
+            ".#....#..",
+            "..####...",
+            ".........",
+        ];
+        assert_eq!(filled, expected_filled);
+        assert_eq!(outline, expected_outline);
+        // the same fill rule as polygons, so it touches every side of the square around it
+        // and nothing outside of it
+        let square = bitmap(9, 9, |image| {
+            let corners = [(0, 0), (8, 0), (8, 8), (0, 8)].map(|(x, y)| Vec2::new(x, y));
+            fill_polygon(&corners, image, WHITE)
+        });
+        let bounds = |rows: &[String]| {
+            let filled = |x: usize, y: usize| rows[y].as_bytes()[x] == b'#';
+            let columns = (0..9).filter(|x| (0..9).any(|y| filled(*x, y)));
+            let rows = (0..9).filter(|y| (0..9).any(|x| filled(x, *y)));
+            (columns.collect::<Vec<_>>(), rows.collect::<Vec<_>>())
+        };
+        assert_eq!(bounds(&filled), bounds(&square));
+    }
+
+    #[test]
+    fn test_ellipse() {
+        let filled = bitmap(9, 5, |image| {
+            fill_ellipse(Vec2::new(4, 2), 4, 2, image, WHITE)
+        });
+        let outline = bitmap(9, 5, |image| {
+            draw_ellipse(Vec2::new(4, 2), 4, 2, image, WHITE)
+        });
+        assert_eq!(
+            filled,
+            [
+                ".######..",
+                "########.",
+                "########.",
+                ".######..",
+                "........."
+            ]
+        );
+        assert_eq!(
+            outline,
+            [
+                ".######..",
+                "#......#.",
+                "#......#.",
+                ".######..",
+                "........."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arc() {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        let filled = bitmap(9, 9, |image| {
+            fill_arc(Vec2::new(4, 4), 4, 0.0, FRAC_PI_2, image, WHITE)
+        });
+        let outline = bitmap(9, 9, |image| {
+            draw_arc(Vec2::new(4, 4), 4, 0.0, PI, image, WHITE)
+        });
+        #[rustfmt::skip]
+        let expected_filled = [
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+            "....####.",
+            "....####.",
+            "....###..",
+            "....##...",
+            ".........",
+        ];
+        #[rustfmt::skip]
+        let expected_outline = [
+            ".........",
+            ".........",
+            ".........",
+            ".........",
+            "#......#.",
+            "#......#.",
+            ".#....#..",
+            "..####...",
+            ".........",
+        ];
+        assert_eq!(filled, expected_filled);
+        assert_eq!(outline, expected_outline);
+
+        // past half a turn the slice is no longer convex, and still blended once per pixel
+        let reflex = gray_bitmap(8, 8, |image| {
+            let color = Color::new(255, 255, 255, 100);
+            fill_arc(Vec2::new(4, 4), 4, FRAC_PI_2, 2.0 * PI, image, color)
+        });
+        let full = gray_bitmap(8, 8, |image| {
+            fill_circle(Vec2::new(4, 4), 4, image, Color::new(255, 255, 255, 100))
+        });
+        for y in 0..8 {
+            for x in 0..8 {
+                // all but the quarter from the positive x axis down to the positive y axis
+                let expected = if x >= 4 && y >= 4 { 0 } else { full[y][x] };
+                assert_eq!(reflex[y][x], expected, "at {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shapes_clipped() {
+        let circle = bitmap(5, 5, |image| fill_circle(Vec2::new(0, 4), 3, image, WHITE));
+        assert_eq!(circle, [".....", "##...", "###..", "###..", "###.."]);
+        let triangle = bitmap(4, 4, |image| {
+            fill_triangle(
+                Vec2::new(-4, 0),
+                Vec2::new(4, 0),
+                Vec2::new(-4, 8),
+                image,
+                WHITE,
+            );
+            draw_ellipse(Vec2::new(-10, -10), 3, 2, image, WHITE);
+        });
+        assert_eq!(triangle, ["###.", "##..", "#...", "...."]);
+    }
+
+    fn gray_bitmap(width: u32, height: u32, draw: impl Fn(&mut Image)) -> Vec<Vec<u8>> {
+        let mut image = image_from_u32(width, height, &vec![BLACK; (width * height) as usize]);
+        draw(&mut image);
+        image
+            .get_buf()
+            .chunks(width as usize * 4)
+            .map(|row| row.chunks(4).map(|pixel| pixel[0]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_line_aa() {
+        let horizontal = gray_bitmap(6, 3, |image| {
+            draw_line_aa(Vec2F::new(1.0, 1.0), Vec2F::new(4.0, 1.0), image, WHITE)
+        });
+        assert_eq!(
+            horizontal,
+            [
+                [0, 0, 0, 0, 0, 0],
+                [0, 128, 255, 255, 128, 0],
+                [0, 0, 0, 0, 0, 0]
+            ]
+        );
+        // 2.3 reaches a fifth of the way into its pixel from the right, 6.3 four fifths of the
+        // way into its own from the left
+        let fractional = gray_bitmap(8, 1, |image| {
+            draw_line_aa(Vec2F::new(2.3, 0.0), Vec2F::new(6.3, 0.0), image, WHITE)
+        });
+        assert_eq!(fractional, [[0, 0, 51, 255, 255, 255, 204, 0]]);
+        let shallow = gray_bitmap(6, 3, |image| {
+            draw_line_aa(Vec2F::new(0.0, 0.0), Vec2F::new(5.0, 2.0), image, WHITE)
+        });
+        assert_eq!(
+            shallow,
+            [
+                [128, 153, 51, 0, 0, 0],
+                [0, 102, 204, 204, 102, 0],
+                [0, 0, 0, 51, 153, 128]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_thick_line_caps() {
+        let draw = |cap| {
+            let style = LineStyle {
+                width: 2.0,
+                cap,
+                anti_aliased: false,
+                ..LineStyle::default()
+            };
+            bitmap(7, 4, move |image| {
+                draw_thick_line(
+                    Vec2F::new(1.0, 2.0),
+                    Vec2F::new(5.0, 2.0),
+                    &style,
+                    image,
+                    WHITE,
+                )
+            })
+        };
+        assert_eq!(
+            draw(LineCap::Butt),
+            [".......", ".####..", ".####..", "......."]
+        );
+        assert_eq!(
+            draw(LineCap::Square),
+            [".......", "######.", "######.", "......."]
+        );
+        // the same outline as a polygon covers the same pixels
+        let outline = [
+            Vec2::new(1, 1),
+            Vec2::new(5, 1),
+            Vec2::new(5, 3),
+            Vec2::new(1, 3),
+        ];
+        assert_eq!(
+            bitmap(7, 4, |image| fill_polygon(&outline, image, WHITE)),
+            draw(LineCap::Butt)
+        );
+        let style = LineStyle {
+            width: 2.0,
+            ..LineStyle::default()
+        };
+        let anti_aliased = gray_bitmap(7, 4, |image| {
+            draw_thick_line(
+                Vec2F::new(1.0, 2.0),
+                Vec2F::new(5.0, 2.0),
+                &style,
+                image,
+                WHITE,
+            )
+        });
+        assert_eq!(
+            anti_aliased,
+            [
+                [0, 0, 0, 0, 0, 0, 0],
+                [0, 255, 255, 255, 255, 0, 0],
+                [0, 255, 255, 255, 255, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_polyline_joins() {
+        let draw_limited = |join, miter_limit| {
+            let style = LineStyle {
+                width: 4.0,
+                join,
+                miter_limit,
+                anti_aliased: false,
+                ..LineStyle::default()
+            };
+            let points = [
+                Vec2F::new(2.0, 3.0),
+                Vec2F::new(8.0, 3.0),
+                Vec2F::new(8.0, 10.0),
+            ];
+            bitmap(11, 11, move |image| {
+                draw_polyline(&points, &style, image, WHITE)
+            })
+        };
+        let draw = |join| draw_limited(join, 4.0);
+        #[rustfmt::skip]
+        let miter = [
+            "...........",
+            "..########.",
+            "..########.",
+            "..########.",
+            "..########.",
+            "......####.",
+            "......####.",
+            "......####.",
+            "......####.",
+            "......####.",
+            "...........",
+        ];
+        #[rustfmt::skip]
+        let bevel = [
+            "...........",
+            "..######...",
+            "..#######..",
+            "..########.",
+            "..########.",
+            "......####.",
+            "......####.",
+            "......####.",
+            "......####.",
+            "......####.",
+            "...........",
+        ];
+        assert_eq!(draw(LineJoin::Miter), miter);
+        assert_eq!(draw(LineJoin::Bevel), bevel);
+        // a right angle miter is sqrt(2) times the width long
+        assert_eq!(draw_limited(LineJoin::Miter, 1.5), miter);
+        assert_eq!(draw_limited(LineJoin::Miter, 1.3), bevel);
+    }
+
+    #[test]
+    fn test_translucent_polyline_blends_once() {
+        let style = LineStyle {
+            width: 3.0,
+            join: LineJoin::Round,
+            cap: LineCap::Round,
+            ..LineStyle::default()
+        };
+        let points = [
+            Vec2F::new(1.0, 1.0),
+            Vec2F::new(6.0, 1.0),
+            Vec2F::new(1.0, 6.0),
+            Vec2F::new(6.0, 6.0),
+        ];
+        let result = gray_bitmap(8, 8, |image| {
+            draw_polyline(&points, &style, image, Color::new(255, 255, 255, 100))
+        });
+        let brightest = result.iter().flatten().max().unwrap();
+        assert_eq!(*brightest, 100);
+    }
 }
@@ -0,0 +1,438 @@
+use std::time::Duration;
+
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{
+    DeviceId, ElementState, Ime, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    TouchPhase, VirtualKeyCode, WindowEvent,
+};
+
+use crate::{update, Engine, GameState, Screen};
+
+/// Input that can be fed to a headless run on a given frame
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScriptedInput {
+    KeyPressed(VirtualKeyCode),
+    KeyReleased(VirtualKeyCode),
+    /// In window pixels, the window being as large as the initial screen until `Resized`
+    MouseMoved(f32, f32),
+    MousePressed(MouseButton),
+    MouseReleased(MouseButton),
+    MouseWheel(f32),
+    Character(char),
+    /// The IME is composing this text, with the cursor over the given byte range
+    ImePreedit(&'static str, Option<(usize, usize)>),
+    /// The IME finished composing this text
+    ImeCommit(&'static str),
+    /// The window was resized to this many physical pixels
+    Resized(u32, u32),
+    Quit,
+}
+
+impl ScriptedInput {
+    #[allow(deprecated)]
+    fn to_window_event(self) -> WindowEvent<'static> {
+        // winit only hands out real device ids from an event loop
+        let device_id = unsafe { DeviceId::dummy() };
+        let keyboard_input = |state, keycode| WindowEvent::KeyboardInput {
+            device_id,
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(keycode),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        };
+        let mouse_input = |state, button| WindowEvent::MouseInput {
+            device_id,
+            state,
+            button,
+            modifiers: ModifiersState::empty(),
+        };
+        match self {
+            Self::KeyPressed(keycode) => keyboard_input(ElementState::Pressed, keycode),
+            Self::KeyReleased(keycode) => keyboard_input(ElementState::Released, keycode),
+            Self::MouseMoved(x, y) => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(x as f64, y as f64),
+                modifiers: ModifiersState::empty(),
+            },
+            Self::MousePressed(button) => mouse_input(ElementState::Pressed, button),
+            Self::MouseReleased(button) => mouse_input(ElementState::Released, button),
+            Self::MouseWheel(lines) => WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::LineDelta(0.0, lines),
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::empty(),
+            },
+            Self::Character(c) => WindowEvent::ReceivedCharacter(c),
+            Self::ImePreedit(text, cursor) => {
+                WindowEvent::Ime(Ime::Preedit(text.to_string(), cursor))
+            }
+            Self::ImeCommit(text) => WindowEvent::Ime(Ime::Commit(text.to_string())),
+            Self::Resized(width, height) => WindowEvent::Resized(PhysicalSize::new(width, height)),
+            Self::Quit => WindowEvent::CloseRequested,
+        }
+    }
+}
+
+/// Describes how many frames a headless run lasts, what the clock reports for each of them and
+/// which input arrives when
+#[derive(Clone, Debug)]
+pub struct Script {
+    pub frames: u64,
+    frame_times: Vec<Duration>,
+    inputs: Vec<(u64, ScriptedInput)>,
+}
+
+impl Script {
+    pub fn new(frames: u64, frame_time: Duration) -> Self {
+        Self {
+            frames,
+            frame_times: vec![frame_time],
+            inputs: Vec::new(),
+        }
+    }
+    /// The elapsed time reported on each frame, repeating once exhausted
+    pub fn with_frame_times(mut self, frame_times: Vec<Duration>) -> Self {
+        assert!(
+            !frame_times.is_empty(),
+            "Script needs at least one frame time"
+        );
+        self.frame_times = frame_times;
+        self
+    }
+    /// Deliver `input` right before `on_update` is called for `frame`
+    pub fn with_input(mut self, frame: u64, input: ScriptedInput) -> Self {
+        self.inputs.push((frame, input));
+        self
+    }
+    pub fn frame_time(&self, frame: u64) -> Duration {
+        self.frame_times[frame as usize % self.frame_times.len()]
+    }
+    fn events_for_frame(&self, frame: u64) -> Vec<WindowEvent<'static>> {
+        self.inputs
+            .iter()
+            .filter(|(input_frame, _)| *input_frame == frame)
+            .map(|(_, input)| input.to_window_event())
+            .collect()
+    }
+}
+
+/// Drive `game_state` through its lifecycle without a window or GPU.
+/// The returned engine holds the final framebuffer in `screen`.
+pub fn run_headless<T: GameState>(game_state: &mut T, script: &Script) -> Engine {
+    let ctx = game_state.context();
+    let screen = Screen::headless(ctx.screen_width, ctx.screen_height);
+    let mut engine = Engine::new(screen, None);
+    if !game_state.on_create(&mut engine) {
+        game_state.on_exit();
+        return engine;
+    }
+    for frame in 0..script.frames {
+        let events = script.events_for_frame(frame);
+        for event in &events {
+            engine.text_input.handle_event(event);
+        }
+        engine.input.step_with_window_events(&events);
+        if engine.input.quit() {
+            break;
+        }
+        if let Some(size) = engine.input.window_resized() {
+            engine.resize_surface(size.width, size.height);
+        }
+        if !update(game_state, &mut engine, script.frame_time(frame)) {
+            break;
+        }
+        engine.render();
+    }
+    game_state.on_exit();
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{ImageResource, ResourceError};
+    use crate::types::{Color, Vec2F};
+    use crate::Context;
+
+    struct Recorder {
+        ctx: Context,
+        elapsed: Vec<Duration>,
+        exited: bool,
+    }
+
+    impl Recorder {
+        fn new() -> Self {
+            Self {
+                ctx: Context {
+                    screen_width: 4,
+                    screen_height: 2,
+                    vsync_enabled: false,
+                    fixed_timestep: None,
+                },
+                elapsed: Vec::new(),
+                exited: false,
+            }
+        }
+    }
+
+    impl GameState for Recorder {
+        fn on_update(&mut self, elapsed_time: Duration, engine: &mut Engine) -> bool {
+            self.elapsed.push(elapsed_time);
+            if engine.input.key_held(VirtualKeyCode::Space) {
+                engine.screen.clear(Color::new(255, 0, 0, 255));
+            } else {
+                engine.screen.clear(Color::new(0, 0, 255, 255));
+            }
+            true
+        }
+        fn on_exit(&mut self) {
+            self.exited = true;
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_headless_clock_and_input() {
+        let mut game = Recorder::new();
+        let script = Script::new(4, Duration::from_millis(16))
+            .with_frame_times(vec![Duration::from_millis(16), Duration::from_millis(33)])
+            .with_input(1, ScriptedInput::KeyPressed(VirtualKeyCode::Space));
+        let engine = run_headless(&mut game, &script);
+        assert!(game.exited);
+        assert_eq!(
+            game.elapsed,
+            [16, 33, 16, 33].map(Duration::from_millis).to_vec()
+        );
+        assert_eq!(engine.screen.get_buf()[..4], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_headless_quit() {
+        let mut game = Recorder::new();
+        let script = Script::new(10, Duration::from_millis(16)).with_input(2, ScriptedInput::Quit);
+        let engine = run_headless(&mut game, &script);
+        assert!(game.exited);
+        assert_eq!(game.elapsed.len(), 2);
+        assert_eq!(engine.screen.get_buf()[..4], [0, 0, 255, 255]);
+    }
+
+    struct FixedCounter {
+        ctx: Context,
+        fixed_steps: Vec<u32>,
+        interpolation: Vec<f32>,
+    }
+
+    impl GameState for FixedCounter {
+        fn on_fixed_update(&mut self, _step: Duration, _engine: &mut Engine) -> bool {
+            *self.fixed_steps.last_mut().unwrap() += 1;
+            true
+        }
+        fn on_update(&mut self, _elapsed_time: Duration, engine: &mut Engine) -> bool {
+            self.interpolation.push(engine.interpolation());
+            self.fixed_steps.push(0);
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_headless_fixed_timestep() {
+        let mut game = FixedCounter {
+            ctx: Context {
+                screen_width: 1,
+                screen_height: 1,
+                vsync_enabled: false,
+                fixed_timestep: Some(crate::FixedTimestep::new(Duration::from_millis(10), 3)),
+            },
+            fixed_steps: vec![0],
+            interpolation: Vec::new(),
+        };
+        let script = Script::new(3, Duration::from_millis(25)).with_frame_times(vec![
+            Duration::from_millis(25),
+            Duration::from_millis(25),
+            Duration::from_millis(100),
+        ]);
+        run_headless(&mut game, &script);
+        assert_eq!(game.fixed_steps, [2, 3, 3, 0]);
+        assert_eq!(game.interpolation, [0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be zero")]
+    fn test_zero_fixed_rate() {
+        crate::FixedTimestep::from_hz(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be zero")]
+    fn test_zero_fixed_step() {
+        crate::FixedTimestep::new(Duration::ZERO, 3);
+    }
+
+    struct Screenshotter {
+        ctx: Context,
+        dir: std::path::PathBuf,
+    }
+
+    impl GameState for Screenshotter {
+        fn on_create(&mut self, engine: &mut Engine) -> bool {
+            engine.screenshot_key = Some(VirtualKeyCode::F12);
+            engine.screenshot_dir = self.dir.clone();
+            true
+        }
+        fn on_update(&mut self, _elapsed_time: Duration, engine: &mut Engine) -> bool {
+            engine.screen.clear(Color::new(10, 20, 30, 255));
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_headless_screenshot_key() {
+        let dir = std::env::temp_dir().join("engine_test_headless_screenshot_key");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut game = Screenshotter {
+            ctx: Context {
+                screen_width: 2,
+                screen_height: 2,
+                vsync_enabled: false,
+                fixed_timestep: None,
+            },
+            dir: dir.clone(),
+        };
+        let script = Script::new(2, Duration::from_millis(16))
+            .with_input(1, ScriptedInput::KeyPressed(VirtualKeyCode::F12));
+        let engine = run_headless(&mut game, &script);
+        let screenshots = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(screenshots.len(), 1);
+        assert_eq!(
+            engine.last_screenshot().unwrap().as_ref().unwrap(),
+            &screenshots[0]
+        );
+        let mut resource_manager = crate::resource::ResourceManager::new();
+        let handle = resource_manager.try_load_image(&screenshots[0]).unwrap();
+        let image = resource_manager.get_image(handle).unwrap();
+        assert_eq!(image.get_buf(), engine.screenshot().get_buf());
+        assert_eq!(image.get_buf()[..4], [10, 20, 30, 255]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the directory is gone now, so saving fails
+        let engine = run_headless(&mut game, &script);
+        assert!(matches!(
+            engine.last_screenshot(),
+            Some(Err(ResourceError::Encode { .. }))
+        ));
+    }
+
+    struct MouseRecorder {
+        ctx: Context,
+        positions: Vec<Option<Vec2F>>,
+        deltas: Vec<Vec2F>,
+        wheel: Vec<f32>,
+    }
+
+    impl GameState for MouseRecorder {
+        fn on_create(&mut self, engine: &mut Engine) -> bool {
+            engine.resize_buffer(4, 2);
+            true
+        }
+        fn on_update(&mut self, _elapsed_time: Duration, engine: &mut Engine) -> bool {
+            self.positions.push(engine.mouse_position());
+            self.deltas.push(engine.mouse_delta());
+            self.wheel.push(engine.mouse_wheel());
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_headless_mouse_in_buffer_pixels() {
+        let mut game = MouseRecorder {
+            ctx: Context {
+                screen_width: 10,
+                screen_height: 6,
+                vsync_enabled: false,
+                fixed_timestep: None,
+            },
+            positions: Vec::new(),
+            deltas: Vec::new(),
+            wheel: Vec::new(),
+        };
+        // the 4x2 buffer is scaled by 2 and centered, leaving a pixel of border all around
+        let script = Script::new(5, Duration::from_millis(16))
+            .with_input(0, ScriptedInput::MouseMoved(2.0, 2.0))
+            .with_input(1, ScriptedInput::MouseMoved(8.0, 4.0))
+            .with_input(1, ScriptedInput::MouseWheel(-2.0))
+            .with_input(2, ScriptedInput::MouseMoved(9.0, 4.0))
+            // now the buffer is scaled by 3, 1 pixel off the left and 2 off the top
+            .with_input(3, ScriptedInput::Resized(14, 10))
+            .with_input(4, ScriptedInput::MouseMoved(10.5, 5.5));
+        let engine = run_headless(&mut game, &script);
+        assert_eq!(
+            game.positions,
+            [
+                Some(Vec2F::new(0.0, 0.0)),
+                Some(Vec2F::new(3.0, 1.0)),
+                None,
+                Some(Vec2F::new(2.0, 0.0)),
+                Some(Vec2F::new(3.0, 1.0)),
+            ]
+        );
+        assert_eq!(engine.screen.surface_size(), (14, 10));
+        assert_eq!(engine.screen.buffer_scale(), 3.0);
+        assert_eq!(
+            game.deltas,
+            [
+                Vec2F::new(0.0, 0.0),
+                Vec2F::new(3.0, 1.0),
+                Vec2F::new(0.5, 0.0),
+                Vec2F::new(0.0, 0.0),
+                Vec2F::new(0.5, 0.5),
+            ]
+        );
+        assert_eq!(game.wheel, [0.0, -2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(engine.window_to_buffer((0.5, 5.0)), None);
+        assert_eq!(
+            engine.window_to_buffer((1.5, 2.5)),
+            Some(Vec2F::new(0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_mapping_matches_pixels_on_odd_surfaces() {
+        // with an 11 pixel wide window the 8 pixel wide scaled buffer is centred on a half
+        // pixel, `Pixels::window_pos_to_pixel` puts buffer pixel 0 from 1.625 to 3.625
+        let mut engine = Engine::new(Screen::headless(11, 4), None);
+        engine.resize_buffer(4, 2);
+        let column = |x: f32| engine.window_to_buffer((x, 2.0)).map(|point| point.x);
+        assert_eq!(column(1.4), None);
+        assert_eq!(column(1.6), None);
+        assert_eq!(column(1.7), Some(0.0));
+        assert_eq!(column(3.2), Some(0.0));
+        assert_eq!(column(3.55), Some(0.0));
+        assert_eq!(column(3.7), Some(1.0));
+        assert_eq!(column(9.45), Some(3.0));
+        assert_eq!(column(9.7), None);
+        // and back again
+        for x in [0.25, 1.5, 3.75] {
+            let (window_x, window_y) = engine.buffer_to_window(Vec2F::new(x, 1.5));
+            let back = engine.window_to_buffer((window_x, window_y)).unwrap();
+            assert_eq!(back, Vec2F::new(x.floor(), 1.0));
+        }
+    }
+}
@@ -0,0 +1,791 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+use winit::event::{MouseButton, VirtualKeyCode};
+use winit_input_helper::WinitInputHelper;
+
+use crate::resource::ResourceError;
+
+/// Something on the keyboard or mouse an action can be bound to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    /// Active on frames the wheel scrolls up
+    WheelUp,
+    /// Active on frames the wheel scrolls down
+    WheelDown,
+}
+
+impl Binding {
+    /// The first binding pressed this frame, for remapping controls to whatever the player
+    /// presses next
+    pub fn capture(input: &WinitInputHelper) -> Option<Self> {
+        let key = ALL_KEYS
+            .iter()
+            .find(|key| input.key_pressed(**key))
+            .map(|key| Self::Key(*key));
+        let mouse = || {
+            [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                .into_iter()
+                .chain((3..MAX_MOUSE_BUTTON).map(MouseButton::Other))
+                .find(|button| input.mouse_pressed(mouse_index(*button)))
+                .map(Self::Mouse)
+        };
+        let wheel = || match input.scroll_diff() {
+            diff if diff > 0.0 => Some(Self::WheelUp),
+            diff if diff < 0.0 => Some(Self::WheelDown),
+            _ => None,
+        };
+        key.or_else(mouse).or_else(wheel)
+    }
+    /// Whether `WinitInputHelper` can track this binding. `MouseButton::Other` numbers below 3
+    /// would alias left, right and middle, and ones from 255 up are out of its range.
+    pub fn is_valid(self) -> bool {
+        match self {
+            Self::Mouse(MouseButton::Other(button)) => (3..MOUSE_BUTTON_LIMIT).contains(&button),
+            _ => true,
+        }
+    }
+    /// Held down, or pressed at some point this frame so quick taps aren't missed
+    fn is_active(self, input: &WinitInputHelper) -> bool {
+        match self {
+            Self::Key(key) => input.key_held(key) || input.key_pressed(key),
+            Self::Mouse(button) => {
+                let index = mouse_index(button);
+                input.mouse_held(index) || input.mouse_pressed(index)
+            }
+            Self::WheelUp => input.scroll_diff() > 0.0,
+            Self::WheelDown => input.scroll_diff() < 0.0,
+        }
+    }
+    /// Pressed this frame, counting the OS key repeat
+    fn is_repeated(self, input: &WinitInputHelper) -> bool {
+        match self {
+            Self::Key(key) => input.key_pressed_os(key),
+            Self::Mouse(button) => input.mouse_pressed(mouse_index(button)),
+            Self::WheelUp | Self::WheelDown => self.is_active(input),
+        }
+    }
+}
+
+/// Written like `Key:Space`, `Mouse:Left`, `Mouse:4` or `Wheel:Up` in binding files
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "Key:{:?}", key),
+            Self::Mouse(MouseButton::Other(button)) => write!(f, "Mouse:{}", button),
+            Self::Mouse(button) => write!(f, "Mouse:{:?}", button),
+            Self::WheelUp => write!(f, "Wheel:Up"),
+            Self::WheelDown => write!(f, "Wheel:Down"),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("unknown binding {:?}", s);
+        let (kind, name) = s.split_once(':').ok_or_else(error)?;
+        match kind {
+            "Key" => ALL_KEYS
+                .iter()
+                .find(|key| format!("{:?}", key) == name)
+                .map(|key| Self::Key(*key))
+                .ok_or_else(error),
+            "Mouse" => match name {
+                "Left" => Ok(Self::Mouse(MouseButton::Left)),
+                "Right" => Ok(Self::Mouse(MouseButton::Right)),
+                "Middle" => Ok(Self::Mouse(MouseButton::Middle)),
+                _ => name
+                    .parse()
+                    .ok()
+                    .map(|button| Self::Mouse(MouseButton::Other(button)))
+                    .filter(|binding| binding.is_valid())
+                    .ok_or_else(error),
+            },
+            "Wheel" => match name {
+                "Up" => Ok(Self::WheelUp),
+                "Down" => Ok(Self::WheelDown),
+                _ => Err(error()),
+            },
+            _ => Err(error()),
+        }
+    }
+}
+
+/// What makes up the value of an axis
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AxisBinding {
+    /// -1 while `negative` is active, 1 while `positive` is, 0 for both or neither
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    /// Lines scrolled this frame, up being positive
+    Wheel,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ActionState {
+    /// Went down this frame
+    pub pressed: bool,
+    pub held: bool,
+    /// Went up this frame
+    pub released: bool,
+    /// Pressed this frame or repeated by the OS while held, for menus
+    pub repeated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Action {
+    bindings: Vec<Binding>,
+    state: ActionState,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Axis {
+    bindings: Vec<AxisBinding>,
+    value: f32,
+}
+
+/// Named actions and axes on top of the raw input, updated by the engine before every
+/// `GameState::on_update`. An action is held while any of its bindings is.
+#[derive(Debug, Clone, Default)]
+pub struct InputMap {
+    actions: BTreeMap<String, Action>,
+    axes: BTreeMap<String, Axis>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add `binding` to `action`, creating the action if needed. Panics if the binding isn't
+    /// valid, see `Binding::is_valid`.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        assert_valid(binding);
+        let bindings = &mut self.actions.entry(action.to_string()).or_default().bindings;
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+    /// Returns whether `action` had the binding
+    pub fn unbind(&mut self, action: &str, binding: Binding) -> bool {
+        match self.actions.get_mut(action) {
+            Some(action) => {
+                let before = action.bindings.len();
+                action.bindings.retain(|b| *b != binding);
+                action.bindings.len() != before
+            }
+            None => false,
+        }
+    }
+    /// Replace every binding of `action`. Panics if any of them isn't valid, see
+    /// `Binding::is_valid`.
+    pub fn rebind(&mut self, action: &str, bindings: &[Binding]) {
+        bindings.iter().for_each(|binding| assert_valid(*binding));
+        self.actions.entry(action.to_string()).or_default().bindings = bindings.to_vec();
+    }
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions
+            .get(action)
+            .map_or(&[], |action| &action.bindings)
+    }
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+    /// Add `binding` to `axis`, creating the axis if needed. Panics if its buttons aren't valid,
+    /// see `Binding::is_valid`.
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        assert_axis_valid(binding);
+        let bindings = &mut self.axes.entry(axis.to_string()).or_default().bindings;
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+    /// Returns whether `axis` had the binding
+    pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) -> bool {
+        match self.axes.get_mut(axis) {
+            Some(axis) => {
+                let before = axis.bindings.len();
+                axis.bindings.retain(|b| *b != binding);
+                axis.bindings.len() != before
+            }
+            None => false,
+        }
+    }
+    /// Replace every binding of `axis`. Panics if any of their buttons isn't valid, see
+    /// `Binding::is_valid`.
+    pub fn rebind_axis(&mut self, axis: &str, bindings: &[AxisBinding]) {
+        bindings
+            .iter()
+            .for_each(|binding| assert_axis_valid(*binding));
+        self.axes.entry(axis.to_string()).or_default().bindings = bindings.to_vec();
+    }
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], |axis| &axis.bindings)
+    }
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+    /// Work out this frame's action states and axis values
+    pub fn update(&mut self, input: &WinitInputHelper) {
+        for action in self.actions.values_mut() {
+            let was_held = action.state.held;
+            let held = action.bindings.iter().any(|b| b.is_active(input));
+            let pressed = held && !was_held;
+            action.state = ActionState {
+                pressed,
+                held,
+                released: was_held && !held,
+                repeated: pressed || action.bindings.iter().any(|b| b.is_repeated(input)),
+            };
+        }
+        for axis in self.axes.values_mut() {
+            let mut buttons = 0.0;
+            let mut wheel = 0.0;
+            for binding in &axis.bindings {
+                match binding {
+                    AxisBinding::Buttons { negative, positive } => {
+                        if negative.is_active(input) {
+                            buttons -= 1.0;
+                        }
+                        if positive.is_active(input) {
+                            buttons += 1.0;
+                        }
+                    }
+                    AxisBinding::Wheel => wheel += input.scroll_diff(),
+                }
+            }
+            axis.value = f32::clamp(buttons, -1.0, 1.0) + wheel;
+        }
+    }
+    /// All false for unknown actions
+    pub fn state(&self, action: &str) -> ActionState {
+        self.actions
+            .get(action)
+            .map_or(ActionState::default(), |action| action.state)
+    }
+    pub fn pressed(&self, action: &str) -> bool {
+        self.state(action).pressed
+    }
+    pub fn held(&self, action: &str) -> bool {
+        self.state(action).held
+    }
+    pub fn released(&self, action: &str) -> bool {
+        self.state(action).released
+    }
+    pub fn repeated(&self, action: &str) -> bool {
+        self.state(action).repeated
+    }
+    /// Button pairs add up to between -1 and 1, with the wheel adding the lines scrolled on
+    /// top. 0 for unknown axes.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).map_or(0.0, |axis| axis.value)
+    }
+    /// The bindings as `{"actions": {"jump": ["Key:Space"]}, "axes": {"move_x": [{"negative":
+    /// "Key:Left", "positive": "Key:Right"}, "Wheel"]}}`
+    pub fn to_json(&self) -> Value {
+        let actions: Map<_, _> = self
+            .actions
+            .iter()
+            .map(|(name, action)| {
+                let bindings: Vec<_> = action.bindings.iter().map(Binding::to_string).collect();
+                (name.clone(), json!(bindings))
+            })
+            .collect();
+        let axes: Map<_, _> = self
+            .axes
+            .iter()
+            .map(|(name, axis)| {
+                let bindings: Vec<_> = axis
+                    .bindings
+                    .iter()
+                    .map(|binding| match binding {
+                        AxisBinding::Buttons { negative, positive } => json!({
+                            "negative": negative.to_string(),
+                            "positive": positive.to_string(),
+                        }),
+                        AxisBinding::Wheel => json!("Wheel"),
+                    })
+                    .collect();
+                (name.clone(), json!(bindings))
+            })
+            .collect();
+        json!({ "actions": actions, "axes": axes })
+    }
+    /// Bindings in `json` replace those of the same actions and axes, the rest keep theirs so
+    /// actions added after the file was saved still get their defaults. Nothing changes on error.
+    pub fn load_json(&mut self, json: &Value) -> Result<(), String> {
+        let parse = |value: &Value| -> Result<Binding, String> {
+            value.as_str().ok_or("binding is not a string")?.parse()
+        };
+        let mut loaded = self.clone();
+        if let Some(actions) = json.get("actions") {
+            for (name, bindings) in actions.as_object().ok_or("actions is not an object")? {
+                let bindings = bindings
+                    .as_array()
+                    .ok_or("action bindings are not an array")?
+                    .iter()
+                    .map(parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                loaded.rebind(name, &bindings);
+            }
+        }
+        if let Some(axes) = json.get("axes") {
+            for (name, bindings) in axes.as_object().ok_or("axes is not an object")? {
+                let bindings = bindings
+                    .as_array()
+                    .ok_or("axis bindings are not an array")?
+                    .iter()
+                    .map(|value| match value.as_str() {
+                        Some("Wheel") => Ok(AxisBinding::Wheel),
+                        _ => {
+                            let side = |key| {
+                                value
+                                    .get(key)
+                                    .ok_or_else(|| format!("axis binding has no {}", key))
+                                    .and_then(parse)
+                            };
+                            Ok(AxisBinding::Buttons {
+                                negative: side("negative")?,
+                                positive: side("positive")?,
+                            })
+                        }
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                loaded.rebind_axis(name, &bindings);
+            }
+        }
+        *self = loaded;
+        Ok(())
+    }
+    pub fn save(&self, path: &Path) -> Result<(), ResourceError> {
+        write(path, self.to_json().to_string()).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+    /// Load bindings saved by `save`, see `load_json`
+    pub fn load(&mut self, path: &Path) -> Result<(), ResourceError> {
+        let text = read_to_string(path).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |reason: String| ResourceError::Parse {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let json =
+            serde_json::from_str::<Value>(&text).map_err(|why| parse_error(why.to_string()))?;
+        self.load_json(&json).map_err(parse_error)
+    }
+}
+
+/// `Binding::capture` looks at buttons below this
+const MAX_MOUSE_BUTTON: u16 = 16;
+
+/// `WinitInputHelper` tracks buttons below this
+const MOUSE_BUTTON_LIMIT: u16 = 255;
+
+fn assert_valid(binding: Binding) {
+    assert!(binding.is_valid(), "{:?} can't be bound", binding);
+}
+
+fn assert_axis_valid(binding: AxisBinding) {
+    if let AxisBinding::Buttons { negative, positive } = binding {
+        assert_valid(negative);
+        assert_valid(positive);
+    }
+}
+
+/// Button numbering used by `WinitInputHelper`
+fn mouse_index(button: MouseButton) -> usize {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Other(button) => button as usize,
+    }
+}
+
+/// Every key `Binding::capture` checks and binding files can name
+const ALL_KEYS: &[VirtualKeyCode] = &[
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::F13,
+    VirtualKeyCode::F14,
+    VirtualKeyCode::F15,
+    VirtualKeyCode::F16,
+    VirtualKeyCode::F17,
+    VirtualKeyCode::F18,
+    VirtualKeyCode::F19,
+    VirtualKeyCode::F20,
+    VirtualKeyCode::F21,
+    VirtualKeyCode::F22,
+    VirtualKeyCode::F23,
+    VirtualKeyCode::F24,
+    VirtualKeyCode::Snapshot,
+    VirtualKeyCode::Scroll,
+    VirtualKeyCode::Pause,
+    VirtualKeyCode::Insert,
+    VirtualKeyCode::Home,
+    VirtualKeyCode::Delete,
+    VirtualKeyCode::End,
+    VirtualKeyCode::PageDown,
+    VirtualKeyCode::PageUp,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Back,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Compose,
+    VirtualKeyCode::Caret,
+    VirtualKeyCode::Numlock,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::NumpadAdd,
+    VirtualKeyCode::NumpadDivide,
+    VirtualKeyCode::NumpadDecimal,
+    VirtualKeyCode::NumpadComma,
+    VirtualKeyCode::NumpadEnter,
+    VirtualKeyCode::NumpadEquals,
+    VirtualKeyCode::NumpadMultiply,
+    VirtualKeyCode::NumpadSubtract,
+    VirtualKeyCode::AbntC1,
+    VirtualKeyCode::AbntC2,
+    VirtualKeyCode::Apostrophe,
+    VirtualKeyCode::Apps,
+    VirtualKeyCode::Asterisk,
+    VirtualKeyCode::At,
+    VirtualKeyCode::Ax,
+    VirtualKeyCode::Backslash,
+    VirtualKeyCode::Calculator,
+    VirtualKeyCode::Capital,
+    VirtualKeyCode::Colon,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Convert,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::Grave,
+    VirtualKeyCode::Kana,
+    VirtualKeyCode::Kanji,
+    VirtualKeyCode::LAlt,
+    VirtualKeyCode::LBracket,
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::LWin,
+    VirtualKeyCode::Mail,
+    VirtualKeyCode::MediaSelect,
+    VirtualKeyCode::MediaStop,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Mute,
+    VirtualKeyCode::MyComputer,
+    VirtualKeyCode::NavigateForward,
+    VirtualKeyCode::NavigateBackward,
+    VirtualKeyCode::NextTrack,
+    VirtualKeyCode::NoConvert,
+    VirtualKeyCode::OEM102,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::PlayPause,
+    VirtualKeyCode::Plus,
+    VirtualKeyCode::Power,
+    VirtualKeyCode::PrevTrack,
+    VirtualKeyCode::RAlt,
+    VirtualKeyCode::RBracket,
+    VirtualKeyCode::RControl,
+    VirtualKeyCode::RShift,
+    VirtualKeyCode::RWin,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Sleep,
+    VirtualKeyCode::Stop,
+    VirtualKeyCode::Sysrq,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Underline,
+    VirtualKeyCode::Unlabeled,
+    VirtualKeyCode::VolumeDown,
+    VirtualKeyCode::VolumeUp,
+    VirtualKeyCode::Wake,
+    VirtualKeyCode::WebBack,
+    VirtualKeyCode::WebFavorites,
+    VirtualKeyCode::WebForward,
+    VirtualKeyCode::WebHome,
+    VirtualKeyCode::WebRefresh,
+    VirtualKeyCode::WebSearch,
+    VirtualKeyCode::WebStop,
+    VirtualKeyCode::Yen,
+    VirtualKeyCode::Copy,
+    VirtualKeyCode::Paste,
+    VirtualKeyCode::Cut,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::headless::{run_headless, Script, ScriptedInput};
+    use crate::{Context, Engine, GameState};
+
+    /// Records the state of "jump" and the value of "move_x" every frame
+    struct Recorder {
+        ctx: Context,
+        jump: Vec<ActionState>,
+        move_x: Vec<f32>,
+        captured: Vec<Option<Binding>>,
+    }
+
+    impl Recorder {
+        fn new() -> Self {
+            Self {
+                ctx: Context {
+                    screen_width: 2,
+                    screen_height: 2,
+                    vsync_enabled: false,
+                    fixed_timestep: None,
+                },
+                jump: Vec::new(),
+                move_x: Vec::new(),
+                captured: Vec::new(),
+            }
+        }
+    }
+
+    impl GameState for Recorder {
+        fn on_create(&mut self, engine: &mut Engine) -> bool {
+            let actions = &mut engine.actions;
+            actions.bind("jump", Binding::Key(VirtualKeyCode::Space));
+            actions.bind("jump", Binding::Mouse(MouseButton::Left));
+            actions.bind_axis(
+                "move_x",
+                AxisBinding::Buttons {
+                    negative: Binding::Key(VirtualKeyCode::Left),
+                    positive: Binding::Key(VirtualKeyCode::Right),
+                },
+            );
+            actions.bind_axis(
+                "move_x",
+                AxisBinding::Buttons {
+                    negative: Binding::Key(VirtualKeyCode::A),
+                    positive: Binding::Key(VirtualKeyCode::D),
+                },
+            );
+            actions.bind_axis("move_x", AxisBinding::Wheel);
+            true
+        }
+        fn on_update(&mut self, _elapsed_time: Duration, engine: &mut Engine) -> bool {
+            self.jump.push(engine.actions.state("jump"));
+            self.move_x.push(engine.actions.axis("move_x"));
+            self.captured.push(Binding::capture(&engine.input));
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    fn state(pressed: bool, held: bool, released: bool, repeated: bool) -> ActionState {
+        ActionState {
+            pressed,
+            held,
+            released,
+            repeated,
+        }
+    }
+
+    #[test]
+    fn test_action_states() {
+        let mut game = Recorder::new();
+        let script = Script::new(8, Duration::from_millis(16))
+            .with_input(1, ScriptedInput::KeyPressed(VirtualKeyCode::Space))
+            // the OS repeating the key while it is held
+            .with_input(2, ScriptedInput::KeyPressed(VirtualKeyCode::Space))
+            // a second binding going down and the first coming up keep the action held
+            .with_input(3, ScriptedInput::MousePressed(MouseButton::Left))
+            .with_input(4, ScriptedInput::KeyReleased(VirtualKeyCode::Space))
+            .with_input(5, ScriptedInput::MouseReleased(MouseButton::Left))
+            // a tap within a single frame still counts
+            .with_input(6, ScriptedInput::KeyPressed(VirtualKeyCode::Space))
+            .with_input(6, ScriptedInput::KeyReleased(VirtualKeyCode::Space));
+        run_headless(&mut game, &script);
+        assert_eq!(
+            game.jump,
+            [
+                state(false, false, false, false),
+                state(true, true, false, true),
+                state(false, true, false, true),
+                state(false, true, false, true),
+                state(false, true, false, false),
+                state(false, false, true, false),
+                state(true, true, false, true),
+                state(false, false, true, false),
+            ]
+        );
+        assert_eq!(game.captured[1], Some(Binding::Key(VirtualKeyCode::Space)));
+        assert_eq!(game.captured[2], None);
+        assert_eq!(game.captured[3], Some(Binding::Mouse(MouseButton::Left)));
+    }
+
+    #[test]
+    fn test_axes() {
+        let mut game = Recorder::new();
+        let script = Script::new(5, Duration::from_millis(16))
+            .with_input(1, ScriptedInput::KeyPressed(VirtualKeyCode::Right))
+            .with_input(2, ScriptedInput::KeyPressed(VirtualKeyCode::D))
+            .with_input(3, ScriptedInput::KeyPressed(VirtualKeyCode::Left))
+            .with_input(4, ScriptedInput::KeyReleased(VirtualKeyCode::Right))
+            .with_input(4, ScriptedInput::KeyReleased(VirtualKeyCode::D))
+            .with_input(4, ScriptedInput::KeyReleased(VirtualKeyCode::Left))
+            .with_input(4, ScriptedInput::MouseWheel(-2.0));
+        run_headless(&mut game, &script);
+        // two bindings for the same direction don't go past 1, opposite ones cancel out
+        assert_eq!(game.move_x, [0.0, 1.0, 1.0, 1.0, -2.0]);
+        assert_eq!(game.captured[4], Some(Binding::WheelDown));
+    }
+
+    #[test]
+    fn test_rebinding_and_config() {
+        let mut map = InputMap::new();
+        map.bind("jump", Binding::Key(VirtualKeyCode::Space));
+        map.bind("jump", Binding::Key(VirtualKeyCode::Space));
+        map.bind("fire", Binding::Mouse(MouseButton::Other(4)));
+        map.bind("zoom", Binding::WheelUp);
+        map.bind_axis(
+            "move_x",
+            AxisBinding::Buttons {
+                negative: Binding::Key(VirtualKeyCode::Left),
+                positive: Binding::Key(VirtualKeyCode::Right),
+            },
+        );
+        map.bind_axis("move_x", AxisBinding::Wheel);
+        assert_eq!(map.bindings("jump"), [Binding::Key(VirtualKeyCode::Space)]);
+        assert!(map.unbind("fire", Binding::Mouse(MouseButton::Other(4))));
+        assert!(!map.unbind("fire", Binding::Mouse(MouseButton::Other(4))));
+        map.rebind(
+            "fire",
+            &[
+                Binding::Mouse(MouseButton::Right),
+                Binding::Key(VirtualKeyCode::LControl),
+            ],
+        );
+
+        let path = std::env::temp_dir().join("engine_test_input_bindings.json");
+        map.save(&path).unwrap();
+        let mut loaded = InputMap::new();
+        // actions the file doesn't mention keep their defaults
+        loaded.bind("pause", Binding::Key(VirtualKeyCode::Escape));
+        loaded.bind("jump", Binding::Key(VirtualKeyCode::W));
+        loaded.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.to_json(), {
+            let mut expected = map.clone();
+            expected.bind("pause", Binding::Key(VirtualKeyCode::Escape));
+            expected.to_json()
+        });
+        assert_eq!(
+            loaded.bindings("fire"),
+            [
+                Binding::Mouse(MouseButton::Right),
+                Binding::Key(VirtualKeyCode::LControl)
+            ]
+        );
+        assert_eq!(loaded.axis_bindings("move_x").len(), 2);
+
+        for text in [
+            r#"{"actions": {"jump": ["Key:Nope"]}}"#,
+            r#"{"actions": {"jump": "Key:Space"}}"#,
+            r#"{"axes": {"move_x": [{"negative": "Key:Left"}]}}"#,
+            r#"{"actions": {"jump": ["Mouse:Left", "Wheel:Sideways"]}}"#,
+            r#"{"actions": {"jump": ["Mouse:300"]}}"#,
+            r#"{"actions": {"jump": ["Mouse:255"]}}"#,
+            r#"{"actions": {"jump": ["Mouse:0"]}}"#,
+            r#"{"actions": {"jump": ["Mouse:2"]}}"#,
+            r#"{"actions": {"jump": ["Mouse:-1"]}}"#,
+            r#"{"axes": {"move_x": [{"negative": "Mouse:1", "positive": "Key:Right"}]}}"#,
+        ] {
+            let json = serde_json::from_str::<Value>(text).unwrap();
+            assert!(loaded.load_json(&json).is_err(), "{}", text);
+        }
+        assert_eq!(
+            loaded.bindings("jump"),
+            [Binding::Key(VirtualKeyCode::Space)]
+        );
+        assert_eq!(
+            "Mouse:254".parse(),
+            Ok(Binding::Mouse(MouseButton::Other(254)))
+        );
+        assert!(!Binding::Mouse(MouseButton::Other(1)).is_valid());
+        assert!(!Binding::Mouse(MouseButton::Other(255)).is_valid());
+        let bind = std::panic::catch_unwind(|| {
+            InputMap::new().bind("fire", Binding::Mouse(MouseButton::Other(300)))
+        });
+        assert!(bind.is_err());
+        let rebind = std::panic::catch_unwind(|| {
+            InputMap::new().rebind("fire", &[Binding::Mouse(MouseButton::Other(0))])
+        });
+        assert!(rebind.is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use crate::drawing::{blit_tinted, blit_with_alpha, BlendMode};
+use crate::resource::{Handle, Image, ImageResource, ResourceError, SlotMap};
+use crate::types::{Color, Vec2};
+
+pub type LayerHandle = Handle<Layer>;
+
+/// An offscreen surface the size of the frame buffer that is composited with the screen whenever
+/// a frame is presented or a screenshot taken. Neither the screen nor the surfaces are changed by
+/// compositing, so a layer only needs redrawing when its content changes.
+pub struct Layer {
+    pub surface: Image,
+    pub blend_mode: BlendMode,
+    /// From 0 to 1
+    pub opacity: f32,
+    /// Layers are composited from low to high, ties in the order they were added. Negative
+    /// layers go under whatever was drawn straight to the screen and show through wherever that
+    /// is transparent, the rest go over it.
+    pub z_order: i32,
+    pub visible: bool,
+    sequence: u64,
+}
+
+impl Layer {
+    pub fn clear(&mut self, color: Color) {
+        self.surface.get_buf_u32_mut().fill(color.into());
+    }
+}
+
+/// Owns the layers composited by the engine, see `Engine::layers`
+pub struct LayerManager {
+    layers: SlotMap<Layer>,
+    width: u32,
+    height: u32,
+    next_sequence: u64,
+    /// What `present` composites into, kept between frames
+    output: Option<Image>,
+}
+
+impl LayerManager {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            layers: SlotMap::new(),
+            width,
+            height,
+            next_sequence: 0,
+            output: None,
+        }
+    }
+    /// Add a transparent layer, drawn with alpha blending at full opacity
+    pub fn add(&mut self, z_order: i32) -> LayerHandle {
+        let layer = Layer {
+            surface: Image::blank(self.width, self.height),
+            blend_mode: BlendMode::Alpha,
+            opacity: 1.0,
+            z_order,
+            visible: true,
+            sequence: self.next_sequence,
+        };
+        self.next_sequence += 1;
+        self.layers.insert(layer)
+    }
+    pub fn get(&self, handle: LayerHandle) -> Option<&Layer> {
+        self.layers.get(handle)
+    }
+    pub fn get_mut(&mut self, handle: LayerHandle) -> Option<&mut Layer> {
+        self.layers.get_mut(handle)
+    }
+    pub fn try_get(&self, handle: LayerHandle) -> Result<&Layer, ResourceError> {
+        self.get(handle).ok_or(ResourceError::InvalidHandle)
+    }
+    pub fn try_get_mut(&mut self, handle: LayerHandle) -> Result<&mut Layer, ResourceError> {
+        self.get_mut(handle).ok_or(ResourceError::InvalidHandle)
+    }
+    pub fn remove(&mut self, handle: LayerHandle) -> Option<Layer> {
+        self.layers.remove(handle)
+    }
+    pub fn len(&self) -> usize {
+        self.layers.iter().count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.layers.iter().next().is_none()
+    }
+    /// Every layer in the order they are composited in
+    pub fn ordered(&self) -> Vec<LayerHandle> {
+        let mut layers = self.layers.iter().collect::<Vec<_>>();
+        layers.sort_by_key(|(_, layer)| (layer.z_order, layer.sequence));
+        layers.into_iter().map(|(handle, _)| handle).collect()
+    }
+    /// Replace every surface with a transparent one of the new size, called by
+    /// `Engine::resize_buffer`
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        for layer in self.layers.iter_mut() {
+            layer.surface = Image::blank(width, height);
+        }
+    }
+    /// Draw `screen` into `dst` along with the visible layers, from the lowest z order up, with
+    /// `screen` placed between the negative layers and the rest. `dst` has to be the size of
+    /// `screen` and is overwritten.
+    pub fn composite(&self, screen: &impl ImageResource, dst: &mut impl ImageResource) {
+        let mut layers = self
+            .layers
+            .iter()
+            .map(|(_, layer)| layer)
+            .filter(|layer| layer.visible && layer.opacity > 0.0)
+            .collect::<Vec<_>>();
+        layers.sort_by_key(|layer| (layer.z_order, layer.sequence));
+        let split = layers
+            .iter()
+            .position(|layer| layer.z_order >= 0)
+            .unwrap_or(layers.len());
+        let (below, above) = layers.split_at(split);
+        if below.is_empty() {
+            dst.get_buf_mut().copy_from_slice(screen.get_buf());
+        } else {
+            dst.get_buf_u32_mut().fill(0);
+            for layer in below {
+                draw_layer(layer, dst);
+            }
+            blit_with_alpha(screen, dst, Vec2::new(0, 0));
+        }
+        for layer in above {
+            draw_layer(layer, dst);
+        }
+    }
+    /// The frame to show for `screen`, which is `screen` itself when no layer is visible
+    pub(crate) fn present<'a>(&'a mut self, screen: &'a Image) -> &'a Image {
+        if !self
+            .layers
+            .iter()
+            .any(|(_, layer)| layer.visible && layer.opacity > 0.0)
+        {
+            return screen;
+        }
+        let mut output = match self.output.take() {
+            Some(output)
+                if output.width() == screen.width() && output.height() == screen.height() =>
+            {
+                output
+            }
+            _ => Image::blank(screen.width(), screen.height()),
+        };
+        self.composite(screen, &mut output);
+        self.output.insert(output)
+    }
+}
+
+fn draw_layer(layer: &Layer, dst: &mut impl ImageResource) {
+    blit_tinted(
+        &layer.surface,
+        dst,
+        Vec2::new(0, 0),
+        Color::new(255, 255, 255, 255),
+        layer.opacity,
+        layer.blend_mode,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::drawing::fill_rectangle;
+    use crate::headless::{run_headless, Script};
+    use crate::types::Rect;
+    use crate::{Context, Engine, GameState};
+
+    fn pixel(image: &impl ImageResource, x: u32) -> u32 {
+        image.get_buf_u32()[x as usize]
+    }
+
+    #[test]
+    fn test_composite_order_and_blending() {
+        let red = Color::new(255, 0, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+        let mut manager = LayerManager::new(4, 1);
+        let top = manager.add(2);
+        let bottom = manager.add(1);
+        let tied = manager.add(1);
+        manager.get_mut(bottom).unwrap().clear(red);
+        let layer = manager.get_mut(tied).unwrap();
+        fill_rectangle(Rect::new(Vec2::new(1, 0), 1, 1), &mut layer.surface, blue);
+        let layer = manager.get_mut(top).unwrap();
+        fill_rectangle(Rect::new(Vec2::new(2, 0), 2, 1), &mut layer.surface, blue);
+        layer.blend_mode = BlendMode::Additive;
+        layer.opacity = 0.5;
+        assert_eq!(manager.ordered(), vec![bottom, tied, top]);
+
+        let screen = Image::blank(4, 1);
+        let mut dst = Image::blank(4, 1);
+        manager.composite(&screen, &mut dst);
+        assert_eq!(pixel(&dst, 0), u32::from(red));
+        assert_eq!(pixel(&dst, 1), u32::from(blue));
+        assert_eq!(pixel(&dst, 2), u32::from(Color::new(255, 0, 128, 255)));
+
+        manager.get_mut(top).unwrap().visible = false;
+        manager.get_mut(tied).unwrap().z_order = 0;
+        manager.composite(&screen, &mut dst);
+        assert_eq!(pixel(&dst, 1), u32::from(red));
+        assert_eq!(pixel(&dst, 2), u32::from(red));
+    }
+
+    #[test]
+    fn test_negative_layers_go_under_the_screen() {
+        let green = Color::new(0, 255, 0, 255);
+        let white = Color::new(255, 255, 255, 255);
+        let mut manager = LayerManager::new(2, 1);
+        let background = manager.add(-1);
+        manager.get_mut(background).unwrap().clear(green);
+        // the screen has only drawn its left pixel, the background shows through the other
+        let mut screen = Image::blank(2, 1);
+        fill_rectangle(Rect::new(Vec2::new(0, 0), 1, 1), &mut screen, white);
+        let mut dst = Image::blank(2, 1);
+        manager.composite(&screen, &mut dst);
+        assert_eq!(pixel(&dst, 0), u32::from(white));
+        assert_eq!(pixel(&dst, 1), u32::from(green));
+        // the screen itself is left alone
+        assert_eq!(pixel(&screen, 1), 0);
+    }
+
+    #[test]
+    fn test_resize_and_remove() {
+        let mut manager = LayerManager::new(2, 2);
+        let first = manager.add(0);
+        let second = manager.add(0);
+        manager.resize(3, 5);
+        let surface = &manager.get(second).unwrap().surface;
+        assert_eq!((surface.width(), surface.height()), (3, 5));
+        assert!(manager.remove(first).is_some());
+        assert!(manager.get(first).is_none());
+        assert!(manager.try_get(first).is_err());
+        assert_eq!(manager.len(), 1);
+        // a reused slot doesn't revive the old handle
+        let third = manager.add(0);
+        assert!(manager.get(first).is_none());
+        assert_eq!(manager.ordered(), vec![second, third]);
+    }
+
+    struct LayeredGame {
+        ctx: Context,
+        ui: Option<LayerHandle>,
+    }
+
+    impl GameState for LayeredGame {
+        fn on_create(&mut self, engine: &mut Engine) -> bool {
+            let ui = engine.layers.add(1);
+            engine.resize_buffer(3, 1);
+            // resizing drops what was on the surfaces, so draw after it
+            let layer = engine.layers.get_mut(ui).unwrap();
+            fill_rectangle(
+                Rect::new(Vec2::new(2, 0), 1, 1),
+                &mut layer.surface,
+                Color::new(255, 255, 255, 255),
+            );
+            self.ui = Some(ui);
+            true
+        }
+        fn on_update(&mut self, _elapsed_time: Duration, engine: &mut Engine) -> bool {
+            engine.screen.clear(Color::new(0, 0, 255, 255));
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_engine_composites_every_frame() {
+        let mut game = LayeredGame {
+            ctx: Context {
+                screen_width: 2,
+                screen_height: 2,
+                vsync_enabled: false,
+                fixed_timestep: None,
+            },
+            ui: None,
+        };
+        let engine = run_headless(&mut game, &Script::new(3, Duration::from_millis(16)));
+        let surface = &engine.layers.get(game.ui.unwrap()).unwrap().surface;
+        assert_eq!((surface.width(), surface.height()), (3, 1));
+        let blue = u32::from(Color::new(0, 0, 255, 255));
+        let white = u32::from(Color::new(255, 255, 255, 255));
+        assert_eq!(engine.screenshot().get_buf_u32(), [blue, blue, white]);
+    }
+
+    /// Draws its screen once and never clears it
+    struct UnclearedGame {
+        ctx: Context,
+    }
+
+    impl GameState for UnclearedGame {
+        fn on_create(&mut self, engine: &mut Engine) -> bool {
+            engine.resize_buffer(2, 1);
+            let background = engine.layers.add(-1);
+            engine
+                .layers
+                .get_mut(background)
+                .unwrap()
+                .clear(Color::new(0, 255, 0, 255));
+            let glow = engine.layers.add(1);
+            let glow = engine.layers.get_mut(glow).unwrap();
+            glow.clear(Color::new(100, 0, 0, 255));
+            glow.blend_mode = BlendMode::Additive;
+            glow.opacity = 0.5;
+            fill_rectangle(
+                Rect::new(Vec2::new(0, 0), 1, 1),
+                &mut engine.screen,
+                Color::new(0, 0, 255, 255),
+            );
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_layers_dont_build_up_on_the_screen() {
+        let mut game = UnclearedGame {
+            ctx: Context {
+                screen_width: 2,
+                screen_height: 1,
+                vsync_enabled: false,
+                fixed_timestep: None,
+            },
+        };
+        let engine = run_headless(&mut game, &Script::new(5, Duration::from_millis(16)));
+        let blue = u32::from(Color::new(0, 0, 255, 255));
+        assert_eq!(engine.screen.get_buf_u32(), [blue, 0]);
+        assert_eq!(
+            engine.screenshot().get_buf_u32(),
+            [
+                u32::from(Color::new(50, 0, 255, 255)),
+                u32::from(Color::new(50, 255, 0, 255))
+            ]
+        );
+    }
+}
@@ -1,18 +1,37 @@
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use pixels::wgpu::{PowerPreference, RequestAdapterOptions};
 use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
 use winit::dpi::LogicalSize;
+use winit::dpi::PhysicalPosition;
+use winit::event::{Event, VirtualKeyCode};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::{Window, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
-use resource::{FontHelper, ImageResource, ResourceManager};
-use types::Color;
+use input::InputMap;
+use layer::LayerManager;
+use resource::{FontHelper, Image, ImageResource, ResourceError, ResourceManager};
+use text_input::TextInput;
+use types::{Color, Vec2F};
 
+pub mod animation;
+pub mod aseprite;
+pub mod bitmap_font;
+pub mod camera;
+pub mod canvas;
 pub mod constants;
 pub mod drawing;
+pub mod headless;
+pub mod input;
+pub mod layer;
 pub mod resource;
+pub mod rich_text;
+pub mod sprite;
+pub mod text;
+pub mod text_input;
+pub mod tilemap;
 pub mod timer;
 pub mod types;
 
@@ -21,61 +40,334 @@ pub struct Context {
     pub screen_width: u32,
     pub screen_height: u32,
     pub vsync_enabled: bool,
+    /// When set, `GameState::on_fixed_update` is called at a constant rate ahead of `on_update`
+    pub fixed_timestep: Option<FixedTimestep>,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// Panics if `step` is zero
+    pub fn new(step: Duration, max_steps: u32) -> Self {
+        assert!(!step.is_zero(), "fixed timestep step can't be zero");
+        Self { step, max_steps }
+    }
+    /// `hz` fixed updates per second, at most 5 per frame. Panics if `hz` is zero.
+    pub fn from_hz(hz: u32) -> Self {
+        assert!(hz > 0, "fixed timestep rate can't be zero");
+        Self::new(Duration::from_secs(1) / hz, 5)
+    }
+    /// Time between fixed updates, never zero
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+    /// Upper bound on fixed updates per frame, any time left over past it is dropped so a slow
+    /// frame can't snowball into ever slower frames
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+}
+
+/// The frame is drawn into an owned `Image` so it can be read back, and copied over to the
+/// window surface on `Engine::render`
 pub struct Screen {
-    pixels: Pixels,
-    screen_width: u32,
-    screen_height: u32,
+    frame: Image,
+    pixels: Option<Box<Pixels>>,
+    surface_size: (u32, u32),
 }
 
 impl Screen {
+    fn new(
+        pixels: Pixels,
+        screen_width: u32,
+        screen_height: u32,
+        surface_size: (u32, u32),
+    ) -> Self {
+        Self {
+            frame: Self::new_frame(screen_width, screen_height),
+            pixels: Some(Box::new(pixels)),
+            surface_size,
+        }
+    }
+    /// A screen that is never presented anywhere, for running without a window or GPU. It acts
+    /// as if shown in a window of its initial size, so positions are mapped the same way they
+    /// would be once the buffer is resized.
+    pub fn headless(screen_width: u32, screen_height: u32) -> Self {
+        Self {
+            frame: Self::new_frame(screen_width, screen_height),
+            pixels: None,
+            surface_size: (screen_width, screen_height),
+        }
+    }
+    fn new_frame(width: u32, height: u32) -> Image {
+        Image::blank(width, height)
+    }
+    pub fn is_headless(&self) -> bool {
+        self.pixels.is_none()
+    }
     #[inline]
     pub fn clear(&mut self, color: Color) {
         self.get_buf_u32_mut().fill(color.into());
     }
+    pub fn frame(&self) -> &Image {
+        &self.frame
+    }
+    /// Size of the window surface in physical pixels
+    pub fn surface_size(&self) -> (u32, u32) {
+        self.surface_size
+    }
+    /// How many window pixels one buffer pixel covers. Like `pixels`, this is the largest whole
+    /// number the buffer fits in the window with, but never less than 1.
+    pub fn buffer_scale(&self) -> f32 {
+        self.scaling().scale
+    }
+    /// Map a physical window position onto the frame buffer pixel under it, `None` when it
+    /// falls outside of the buffer, in the bars around a letterboxed buffer for example
+    pub fn window_to_buffer(&self, position: (f32, f32)) -> Option<Vec2F> {
+        match &self.pixels {
+            Some(pixels) => pixels
+                .window_pos_to_pixel(position)
+                .ok()
+                .map(|(x, y)| Vec2F::new(x as f32, y as f32)),
+            None => {
+                let (x, y) = self.scaling().window_to_buffer(position);
+                let (x, y) = (x.floor(), y.floor());
+                let inside =
+                    x >= 0.0 && y >= 0.0 && x < self.width() as f32 && y < self.height() as f32;
+                inside.then(|| Vec2F::new(x, y))
+            }
+        }
+    }
+    /// Map a frame buffer position onto the window, the inverse of `window_to_buffer`
+    pub fn buffer_to_window(&self, position: Vec2F) -> (f32, f32) {
+        self.scaling().buffer_to_window(position)
+    }
+    fn scaling(&self) -> BufferScaling {
+        BufferScaling::new(self.surface_size, (self.width(), self.height()))
+    }
+}
+
+/// How `pixels` fits the buffer into the window. `Pixels::window_pos_to_pixel` is followed step
+/// by step, so headless screens map positions the same way and `buffer_to_window` can undo it.
+struct BufferScaling {
+    scale: f32,
+    surface: (f32, f32),
+    buffer: (f32, f32),
+}
+
+impl BufferScaling {
+    fn new(surface: (u32, u32), buffer: (u32, u32)) -> Self {
+        let surface = (surface.0 as f32, surface.1 as f32);
+        let buffer = (buffer.0 as f32, buffer.1 as f32);
+        let scale = (surface.0 / buffer.0)
+            .min(surface.1 / buffer.1)
+            .max(1.0)
+            .floor();
+        Self {
+            scale,
+            surface,
+            buffer,
+        }
+    }
+    /// Buffer position before `pixels` floors it to a whole pixel
+    fn window_to_buffer(&self, position: (f32, f32)) -> (f32, f32) {
+        let axis = |position: f32, surface: f32, buffer: f32| {
+            let (ratio, translation) = self.transform(surface, buffer);
+            ((position / surface - 0.5) * buffer - translation) / ratio + buffer.min(surface) / 2.0
+        };
+        (
+            axis(position.0, self.surface.0, self.buffer.0),
+            axis(position.1, self.surface.1, self.buffer.1),
+        )
+    }
+    fn buffer_to_window(&self, position: Vec2F) -> (f32, f32) {
+        let axis = |position: f32, surface: f32, buffer: f32| {
+            let (ratio, translation) = self.transform(surface, buffer);
+            (((position - buffer.min(surface) / 2.0) * ratio + translation) / buffer + 0.5)
+                * surface
+        };
+        (
+            axis(position.x, self.surface.0, self.buffer.0),
+            axis(position.y, self.surface.1, self.buffer.1),
+        )
+    }
+    /// Scale and translation of `pixels`' scaling matrix along one axis
+    fn transform(&self, surface: f32, buffer: f32) -> (f32, f32) {
+        (
+            buffer * self.scale / surface,
+            (surface / 2.0).fract() / surface,
+        )
+    }
 }
 
 impl ImageResource for Screen {
     fn width(&self) -> u32 {
-        self.screen_width
+        self.frame.width()
     }
     fn height(&self) -> u32 {
-        self.screen_height
+        self.frame.height()
     }
     fn get_buf(&self) -> &[u8] {
-        unimplemented!("Pixels doesn't provide a read-only view")
+        self.frame.get_buf()
     }
     fn get_buf_mut(&mut self) -> &mut [u8] {
-        self.pixels.get_frame()
+        self.frame.get_buf_mut()
     }
     fn get_buf_u32(&self) -> &[u32] {
-        unimplemented!("Pixels doesn't provide a read-only view")
+        self.frame.get_buf_u32()
     }
     fn get_buf_u32_mut(&mut self) -> &mut [u32] {
-        unsafe { self.pixels.get_frame().align_to_mut::<u32>().1 }
+        self.frame.get_buf_u32_mut()
     }
 }
 
 pub struct Engine {
     pub screen: Screen,
-    pub window: Window,
+    /// `None` when running headless
+    pub window: Option<Window>,
     pub resource_manager: ResourceManager,
     pub font_helper: FontHelper,
     pub input: WinitInputHelper,
+    /// Named actions and axes, updated from `input` before every update
+    pub actions: InputMap,
+    /// Offscreen surfaces composited with `screen` when it is rendered or a screenshot is taken.
+    /// `screen` itself keeps only what the game drew on it.
+    pub layers: LayerManager,
+    /// Characters and editing keys typed this frame, for text fields
+    pub text_input: TextInput,
+    /// Pressing this saves a screenshot to `screenshot_dir`
+    pub screenshot_key: Option<VirtualKeyCode>,
+    pub screenshot_dir: PathBuf,
+    last_screenshot: Option<Result<PathBuf, ResourceError>>,
+    fixed_time_acc: Duration,
+    interpolation: f32,
 }
 
 impl Engine {
+    pub fn new(screen: Screen, window: Option<Window>) -> Self {
+        let layers = LayerManager::new(screen.width(), screen.height());
+        Self {
+            screen,
+            window,
+            resource_manager: ResourceManager::new(),
+            font_helper: FontHelper::new(),
+            input: WinitInputHelper::new(),
+            actions: InputMap::new(),
+            layers,
+            text_input: TextInput::new(),
+            screenshot_key: None,
+            screenshot_dir: PathBuf::from("."),
+            last_screenshot: None,
+            fixed_time_acc: Duration::ZERO,
+            interpolation: 0.0,
+        }
+    }
+    /// How far between the last and the next fixed update the current frame lies, from 0 to 1.
+    /// Always 0 when no fixed timestep is configured.
+    pub fn interpolation(&self) -> f32 {
+        self.interpolation
+    }
+    /// Map a physical window position, like the ones `input` reports, onto the frame buffer
+    /// pixel under it. `None` when it falls outside of the buffer.
+    pub fn window_to_buffer(&self, position: (f32, f32)) -> Option<Vec2F> {
+        self.screen.window_to_buffer(position)
+    }
+    /// Where a frame buffer position ends up in the window, in physical pixels
+    pub fn buffer_to_window(&self, position: Vec2F) -> (f32, f32) {
+        self.screen.buffer_to_window(position)
+    }
+    /// Mouse position on the frame buffer, `None` when the cursor is off it
+    pub fn mouse_position(&self) -> Option<Vec2F> {
+        self.input
+            .mouse()
+            .and_then(|position| self.window_to_buffer(position))
+    }
+    /// How far the mouse moved since the last frame, in buffer pixels
+    pub fn mouse_delta(&self) -> Vec2F {
+        let (x, y) = self.input.mouse_diff();
+        let scale = self.screen.buffer_scale();
+        Vec2F::new(x / scale, y / scale)
+    }
+    /// Lines scrolled since the last frame, positive is up. Touchpads scrolling by pixels are
+    /// converted to lines, so this doesn't depend on the buffer scale.
+    pub fn mouse_wheel(&self) -> f32 {
+        self.input.scroll_diff()
+    }
     pub fn resize_buffer(&mut self, width: u32, height: u32) {
-        self.screen.screen_width = width;
-        self.screen.screen_height = height;
-        self.screen.pixels.resize_buffer(width, height);
+        self.screen.frame = Screen::new_frame(width, height);
+        self.layers.resize(width, height);
+        if let Some(pixels) = &mut self.screen.pixels {
+            pixels.resize_buffer(width, height);
+        }
     }
     pub fn resize_surface(&mut self, width: u32, height: u32) {
-        self.screen.pixels.resize_surface(width, height);
+        self.screen.surface_size = (width, height);
+        if let Some(pixels) = &mut self.screen.pixels {
+            pixels.resize_surface(width, height);
+        }
     }
     pub fn render(&mut self) {
-        self.screen.pixels.render().unwrap();
+        if let Some(pixels) = &mut self.screen.pixels {
+            let frame = self.layers.present(&self.screen.frame);
+            pixels.get_frame().copy_from_slice(frame.get_buf());
+            pixels.render().unwrap();
+        }
+    }
+    /// What has been drawn to the screen so far this frame, with the layers composited in
+    pub fn screenshot(&self) -> Image {
+        let mut image = self.screen.frame.clone();
+        self.layers.composite(&self.screen.frame, &mut image);
+        image
+    }
+    pub fn save_screenshot(&self, path: &Path) -> Result<(), ResourceError> {
+        self.screenshot().save_png(path)
+    }
+    /// Where the last screenshot taken with `screenshot_key` was saved, or why it couldn't be.
+    /// `None` until the key is first pressed.
+    pub fn last_screenshot(&self) -> Option<&Result<PathBuf, ResourceError>> {
+        self.last_screenshot.as_ref()
+    }
+    /// Save a screenshot into `screenshot_dir` if `screenshot_key` was pressed this frame
+    fn handle_screenshot_key(&mut self) {
+        let pressed = match self.screenshot_key {
+            Some(key) => self.input.key_pressed(key),
+            None => false,
+        };
+        if !pressed {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self
+            .screenshot_dir
+            .join(format!("screenshot_{}.png", timestamp));
+        self.last_screenshot = Some(self.save_screenshot(&path).map(|_| path));
+    }
+    /// Turn the IME on or off as the text fields asked for this frame and drop the frame's text
+    /// input
+    fn end_text_input_frame(&mut self) {
+        let (toggled, moved) = self.text_input.end_frame();
+        if let Some(window) = &self.window {
+            if let Some(allowed) = toggled {
+                window.set_ime_allowed(allowed);
+            }
+            if let Some(position) = moved {
+                let (x, y) = self.screen.buffer_to_window(position.into());
+                window.set_ime_position(PhysicalPosition::new(x as f64, y as f64));
+            }
+        }
+    }
+    /// Sets the window title, does nothing when running headless
+    pub fn set_title(&self, title: &str) {
+        if let Some(window) = &self.window {
+            window.set_title(title);
+        }
     }
 }
 
@@ -86,13 +378,46 @@ pub trait GameState {
     fn on_update(&mut self, _elapsed_time: Duration, _engine: &mut Engine) -> bool {
         true
     }
+    fn on_fixed_update(&mut self, _step: Duration, _engine: &mut Engine) -> bool {
+        true
+    }
     fn on_exit(&mut self) {}
     fn context(&self) -> &Context;
 }
 
+/// Run the fixed updates owed for `elapsed_time`, if any, followed by `on_update`
+pub(crate) fn update<T: GameState>(
+    game_state: &mut T,
+    engine: &mut Engine,
+    elapsed_time: Duration,
+) -> bool {
+    engine.actions.update(&engine.input);
+    if let Some(fixed) = game_state.context().fixed_timestep {
+        engine.fixed_time_acc += elapsed_time;
+        let mut steps = 0;
+        while engine.fixed_time_acc >= fixed.step {
+            if steps == fixed.max_steps {
+                engine.fixed_time_acc = Duration::ZERO;
+                break;
+            }
+            if !game_state.on_fixed_update(fixed.step, engine) {
+                return false;
+            }
+            engine.fixed_time_acc -= fixed.step;
+            steps += 1;
+        }
+        engine.interpolation = engine.fixed_time_acc.as_secs_f32() / fixed.step.as_secs_f32();
+    }
+    if !game_state.on_update(elapsed_time, engine) {
+        return false;
+    }
+    engine.handle_screenshot_key();
+    engine.end_text_input_frame();
+    true
+}
+
 pub fn run<T: GameState + 'static>(mut game_state: T) {
     let event_loop = EventLoop::new();
-    let input = WinitInputHelper::new();
     let ctx = game_state.context();
     let window = {
         let size = LogicalSize::new(ctx.screen_width as f64, ctx.screen_height as f64);
@@ -102,8 +427,8 @@ pub fn run<T: GameState + 'static>(mut game_state: T) {
             .build(&event_loop)
             .expect("Error constructing window")
     };
+    let window_size = window.inner_size();
     let pixels = {
-        let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
         PixelsBuilder::new(ctx.screen_width, ctx.screen_height, surface_texture)
             .request_adapter_options(RequestAdapterOptions {
@@ -115,24 +440,22 @@ pub fn run<T: GameState + 'static>(mut game_state: T) {
             .build()
             .expect("Error constructing pixel buffer")
     };
-    let resource_manager = ResourceManager::new();
-    let mut engine = Engine {
-        screen: Screen {
-            pixels,
-            screen_width: ctx.screen_width,
-            screen_height: ctx.screen_height,
-        },
-        window,
-        resource_manager,
-        font_helper: FontHelper::new(),
-        input,
-    };
+    let screen = Screen::new(
+        pixels,
+        ctx.screen_width,
+        ctx.screen_height,
+        (window_size.width, window_size.height),
+    );
+    let mut engine = Engine::new(screen, Some(window));
     let mut t1 = Instant::now();
     if !game_state.on_create(&mut engine) {
         game_state.on_exit();
         return;
     }
     event_loop.run(move |event, _, control_flow| {
+        if let Event::WindowEvent { event, .. } = &event {
+            engine.text_input.handle_event(event);
+        }
         if engine.input.update(&event) {
             if engine.input.quit() {
                 *control_flow = ControlFlow::Exit;
@@ -144,13 +467,15 @@ pub fn run<T: GameState + 'static>(mut game_state: T) {
             }
             let elapsed_time = t1.elapsed();
             t1 = Instant::now();
-            if !game_state.on_update(elapsed_time, &mut engine) {
+            if !update(&mut game_state, &mut engine, elapsed_time) {
                 *control_flow = ControlFlow::Exit;
                 game_state.on_exit();
                 return;
             }
             engine.render();
-            engine.window.request_redraw();
+            if let Some(window) = &engine.window {
+                window.request_redraw();
+            }
         }
     });
 }
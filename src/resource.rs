@@ -1,19 +1,203 @@
-use std::collections::HashSet;
-use std::fs::read;
-use std::path::Path;
+use std::error::Error;
+use std::fmt;
+use std::fs::{read, read_to_string};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 
 use fontdue::layout::{CoordinateSystem, Layout};
 use fontdue::{Font, FontSettings};
 use image::io::Reader as ImageReader;
+use image::{ColorType, ImageError, ImageFormat};
+use serde_json::Value;
 
-#[derive(Copy, Clone, Debug)]
-pub struct Handle {
-    pub id: usize,
+use crate::aseprite::Aseprite;
+use crate::bitmap_font::BitmapFont;
+use crate::constants::PIXEL_SIZE;
+use crate::sprite::{Grid, SpriteSheet};
+pub use crate::text::FontHelper;
+use crate::types::{Rect, Vec2};
+
+/// Refers to a resource of type `T` owned by the `ResourceManager`, or a `Layer` owned by the
+/// `LayerManager`.
+/// The generation goes stale once the resource is deleted, even if its slot gets reused.
+pub struct Handle<T> {
     _index: usize,
+    _generation: u32,
+    _kind: PhantomData<fn() -> T>,
+}
+
+pub type ImageHandle = Handle<Image>;
+pub type FontHandle = Handle<Font>;
+pub type BitmapFontHandle = Handle<BitmapFont>;
+pub type SpriteSheetHandle = Handle<SpriteSheet>;
+
+// derives would needlessly require T to implement these as well
+impl<T> Copy for Handle<T> {}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self._index == other._index && self._generation == other._generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self._index.hash(state);
+        self._generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self._index)
+            .field("generation", &self._generation)
+            .finish()
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Storage for one kind of resource, handing out a generational `Handle` per entry
+pub(crate) struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    available_indexes: Vec<usize>,
+}
+
+impl<T> SlotMap<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            available_indexes: Vec::new(),
+        }
+    }
+    pub(crate) fn insert(&mut self, value: T) -> Handle<T> {
+        let index = match self.available_indexes.pop() {
+            Some(i) => {
+                self.slots[i].value = Some(value);
+                i
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                self.slots.len() - 1
+            }
+        };
+        Handle {
+            _index: index,
+            _generation: self.slots[index].generation,
+            _kind: PhantomData,
+        }
+    }
+    pub(crate) fn get(&self, handle: Handle<T>) -> Option<&T> {
+        match self.slots.get(handle._index) {
+            Some(slot) if slot.generation == handle._generation => slot.value.as_ref(),
+            _ => None,
+        }
+    }
+    pub(crate) fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        match self.slots.get_mut(handle._index) {
+            Some(slot) if slot.generation == handle._generation => slot.value.as_mut(),
+            _ => None,
+        }
+    }
+    pub(crate) fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        match self.slots.get_mut(handle._index) {
+            Some(slot) if slot.generation == handle._generation && slot.value.is_some() => {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.available_indexes.push(handle._index);
+                slot.value.take()
+            }
+            _ => None,
+        }
+    }
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = Handle {
+                _index: index,
+                _generation: slot.generation,
+                _kind: PhantomData,
+            };
+            slot.value.as_ref().map(|value| (handle, value))
+        })
+    }
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+#[derive(Debug)]
+pub enum ResourceError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Decode {
+        path: PathBuf,
+        source: ImageError,
+    },
+    Encode {
+        path: PathBuf,
+        source: ImageError,
+    },
+    FontParse {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    /// An asset file that was read but whose contents do not make sense
+    Parse {
+        path: PathBuf,
+        reason: String,
+    },
+    InvalidHandle,
 }
 
-pub type ImageHandle = Handle;
-pub type FontHandle = Handle;
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "Could not open {}: {}", path.display(), source),
+            Self::Decode { path, source } => {
+                write!(f, "Could not decode {}: {}", path.display(), source)
+            }
+            Self::Encode { path, source } => {
+                write!(f, "Could not save {}: {}", path.display(), source)
+            }
+            Self::FontParse { path, reason } => {
+                write!(f, "Could not instantiate {}: {}", path.display(), reason)
+            }
+            Self::Parse { path, reason } => {
+                write!(f, "Could not parse {}: {}", path.display(), reason)
+            }
+            Self::InvalidHandle => write!(f, "Handle does not refer to a loaded resource"),
+        }
+    }
+}
+
+impl Error for ResourceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Decode { source, .. } => Some(source),
+            Self::Encode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 pub trait ImageResource {
     fn width(&self) -> u32;
@@ -22,14 +206,29 @@ pub trait ImageResource {
     fn get_buf_mut(&mut self) -> &mut [u8];
     fn get_buf_u32(&self) -> &[u32];
     fn get_buf_u32_mut(&mut self) -> &mut [u32];
+    /// The area drawing functions may touch, always within the image. The whole image unless
+    /// narrowed down by a `Canvas`.
+    fn clip_rect(&self) -> Rect {
+        Rect::new(Vec2::new(0, 0), self.width(), self.height())
+    }
 }
 
+#[derive(Clone)]
 pub struct Image {
     pub buf: Vec<u8>,
     _width: u32,
     _height: u32,
 }
 
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("width", &self._width)
+            .field("height", &self._height)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Image {
     pub fn new(width: u32, height: u32, buf: Vec<u8>) -> Self {
         Self {
@@ -38,6 +237,58 @@ impl Image {
             buf,
         }
     }
+    /// A fully transparent image
+    pub fn blank(width: u32, height: u32) -> Self {
+        let size = width as usize * height as usize * PIXEL_SIZE as usize;
+        Self::new(width, height, vec![0; size])
+    }
+    /// Decode an image file into RGBA
+    pub fn open(path: &Path) -> Result<Self, ResourceError> {
+        let image_file = ImageReader::open(path).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let image = image_file
+            .decode()
+            .map_err(|source| ResourceError::Decode {
+                path: path.to_path_buf(),
+                source,
+            })?
+            .to_rgba8();
+        let width = image.width();
+        let height = image.height();
+        Ok(Self::new(width, height, image.into_vec()))
+    }
+    /// Magenta and black checkerboard used in place of images that failed to load
+    pub fn missing_texture() -> Self {
+        const SIZE: u32 = 16;
+        const CELL: u32 = 4;
+        let mut buf = Vec::with_capacity((SIZE * SIZE * PIXEL_SIZE) as usize);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                if (x / CELL + y / CELL) & 1 == 0 {
+                    buf.extend_from_slice(&[255, 0, 255, 255]);
+                } else {
+                    buf.extend_from_slice(&[0, 0, 0, 255]);
+                }
+            }
+        }
+        Self::new(SIZE, SIZE, buf)
+    }
+    pub fn save_png(&self, path: &Path) -> Result<(), ResourceError> {
+        image::save_buffer_with_format(
+            path,
+            &self.buf,
+            self._width,
+            self._height,
+            ColorType::Rgba8,
+            ImageFormat::Png,
+        )
+        .map_err(|source| ResourceError::Encode {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
 }
 
 impl ImageResource for Image {
@@ -67,31 +318,13 @@ impl ImageResource for Image {
     }
 }
 
-pub struct FontHelper {
-    pub default_layout: Layout,
-}
-
-impl Default for FontHelper {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl FontHelper {
-    pub fn new() -> Self {
-        Self {
-            default_layout: Layout::new(CoordinateSystem::PositiveYDown),
-        }
-    }
-}
-
 pub struct ResourceManager {
-    _handle_id: usize,
-    _handles: HashSet<usize>, // can probably be a vec and do a binary search since it will always be sorted..?
-    _images: Vec<Option<Image>>,
-    _fonts: Vec<Option<Font>>,
-    _available_image_indexes: Vec<usize>,
-    _available_font_indexes: Vec<usize>,
+    _images: SlotMap<Image>,
+    _fonts: SlotMap<Font>,
+    _bitmap_fonts: SlotMap<BitmapFont>,
+    _sprite_sheets: SlotMap<SpriteSheet>,
+    missing_image: Option<Image>,
+    load_errors: Vec<ResourceError>,
 }
 
 impl Default for ResourceManager {
@@ -103,106 +336,271 @@ impl Default for ResourceManager {
 impl ResourceManager {
     pub fn new() -> Self {
         Self {
-            _handle_id: 0,
-            _handles: HashSet::new(),
-            _images: Vec::new(),
-            _fonts: Vec::new(),
-            _available_image_indexes: Vec::new(),
-            _available_font_indexes: Vec::new(),
+            _images: SlotMap::new(),
+            _fonts: SlotMap::new(),
+            _bitmap_fonts: SlotMap::new(),
+            _sprite_sheets: SlotMap::new(),
+            missing_image: None,
+            load_errors: Vec::new(),
         }
     }
     pub fn new_layout() -> Layout {
         Layout::new(CoordinateSystem::PositiveYDown)
     }
-    fn create_image_handle(&mut self) -> ImageHandle {
-        let handle_id = self._handle_id;
-        self._handle_id += 1;
-        self._handles.insert(handle_id);
-        let index = match self._available_image_indexes.pop() {
-            Some(i) => i,
-            None => self._images.len(),
-        };
-        ImageHandle {
-            id: handle_id,
-            _index: index,
-        }
-    }
     ///load an image and create a new handle to store it with
+    pub fn try_load_image(&mut self, path: &Path) -> Result<ImageHandle, ResourceError> {
+        let image = Image::open(path)?;
+        Ok(self.add_image(image))
+    }
+    ///load an image, handing back a new copy of the missing image instead if that fails.
+    ///the copy belongs to the caller like any loaded image, deleting it affects no one else.
+    ///why it failed is kept until `take_load_errors`
     pub fn load_image(&mut self, path: &Path) -> ImageHandle {
-        let image_file = match ImageReader::open(path) {
-            Err(why) => panic!("Could not open {}: {}", path.display(), why),
-            Ok(file) => file,
-        };
-        let image = match image_file.decode() {
-            Err(why) => panic!("Could not decode {}: {}", path.display(), why),
-            Ok(result) => result.to_rgba8(),
-        };
-        let width = image.width();
-        let height = image.height();
-        let image = Image::new(width, height, image.into_vec());
+        match self.try_load_image(path) {
+            Ok(handle) => handle,
+            Err(why) => {
+                self.load_errors.push(why);
+                self.add_missing_image()
+            }
+        }
+    }
+    ///the errors of every `load_image` that fell back to the missing image since the last call
+    pub fn take_load_errors(&mut self) -> Vec<ResourceError> {
+        std::mem::take(&mut self.load_errors)
+    }
+    ///add a new copy of the image `load_image` uses for assets that could not be loaded,
+    ///`Image::missing_texture` unless `set_missing_image` was called
+    pub fn add_missing_image(&mut self) -> ImageHandle {
+        let image = self
+            .missing_image
+            .clone()
+            .unwrap_or_else(Image::missing_texture);
         self.add_image(image)
     }
+    ///replace the image copied by `add_missing_image`, images already handed out keep theirs
+    pub fn set_missing_image(&mut self, image: Image) {
+        self.missing_image = Some(image);
+    }
     pub fn add_image(&mut self, image: Image) -> ImageHandle {
-        let handle = self.create_image_handle();
-        if handle._index < self._images.len() {
-            self._images[handle._index] = Some(image);
-        } else {
-            self._images.push(Some(image));
-        }
-        handle
+        self._images.insert(image)
     }
-    pub fn get_image(&mut self, handle: ImageHandle) -> Option<&Image> {
-        if self._handles.contains(&handle.id) {
-            return Some(self._images[handle._index].as_ref().unwrap());
-        }
-        None
+    pub fn get_image(&self, handle: ImageHandle) -> Option<&Image> {
+        self._images.get(handle)
     }
-    pub fn delete_image(&mut self, handle: ImageHandle) {
-        if self._handles.remove(&handle.id) {
-            self._images[handle._index] = None;
-            self._available_image_indexes.push(handle._index);
-        }
+    pub fn get_image_mut(&mut self, handle: ImageHandle) -> Option<&mut Image> {
+        self._images.get_mut(handle)
     }
-    fn create_font_handle(&mut self) -> FontHandle {
-        let handle_id = self._handle_id;
-        self._handle_id += 1;
-        self._handles.insert(handle_id);
-        let index = match self._available_font_indexes.pop() {
-            Some(i) => i,
-            None => self._fonts.len(),
-        };
-        FontHandle {
-            id: handle_id,
-            _index: index,
-        }
+    pub fn try_get_image(&self, handle: ImageHandle) -> Result<&Image, ResourceError> {
+        self.get_image(handle).ok_or(ResourceError::InvalidHandle)
+    }
+    ///returns the removed image, or `None` if the handle was already stale
+    pub fn delete_image(&mut self, handle: ImageHandle) -> Option<Image> {
+        self._images.remove(handle)
     }
+    pub fn try_load_font(
+        &mut self,
+        path: &Path,
+        font_settings: FontSettings,
+    ) -> Result<FontHandle, ResourceError> {
+        let font_bytes = read(path).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let font = Font::from_bytes(font_bytes, font_settings).map_err(|reason| {
+            ResourceError::FontParse {
+                path: path.to_path_buf(),
+                reason,
+            }
+        })?;
+        Ok(self._fonts.insert(font))
+    }
+    ///there is no sensible stand-in for a font, so unlike `load_image` this panics on failure
     pub fn load_font(&mut self, path: &Path, font_settings: FontSettings) -> FontHandle {
-        let font_bytes = match read(path) {
-            Err(why) => panic!("Could not open {}: {}", path.display(), why),
-            Ok(file) => file,
-        };
-        let font = match Font::from_bytes(font_bytes, font_settings) {
-            Err(why) => panic!("Could not instantiate {}: {}", path.display(), why),
-            Ok(result) => result,
-        };
-        let handle = self.create_font_handle();
-        if handle._index < self._fonts.len() {
-            self._fonts[handle._index] = Some(font);
-        } else {
-            self._fonts.push(Some(font));
+        match self.try_load_font(path, font_settings) {
+            Err(why) => panic!("{}", why),
+            Ok(handle) => handle,
         }
-        handle
     }
     pub fn get_font(&self, handle: FontHandle) -> Option<&Font> {
-        if self._handles.contains(&handle.id) {
-            return Some(self._fonts[handle._index].as_ref().unwrap());
-        }
-        None
+        self._fonts.get(handle)
     }
-    pub fn delete_font(&mut self, handle: FontHandle) {
-        if self._handles.remove(&handle.id) {
-            self._fonts[handle._index] = None;
-            self._available_font_indexes.push(handle._index);
-        }
+    pub fn try_get_font(&self, handle: FontHandle) -> Result<&Font, ResourceError> {
+        self.get_font(handle).ok_or(ResourceError::InvalidHandle)
+    }
+    ///returns the removed font, or `None` if the handle was already stale
+    pub fn delete_font(&mut self, handle: FontHandle) -> Option<Font> {
+        self._fonts.remove(handle)
+    }
+    ///load an AngelCode BMFont `.fnt` file, text or binary, along with its page images
+    pub fn try_load_bitmap_font(&mut self, path: &Path) -> Result<BitmapFontHandle, ResourceError> {
+        let font = BitmapFont::open_fnt(path)?;
+        Ok(self.add_bitmap_font(font))
+    }
+    ///load a monospaced font from an image split into cells holding `chars` in reading order
+    pub fn try_load_grid_font(
+        &mut self,
+        path: &Path,
+        cell_width: u32,
+        cell_height: u32,
+        chars: &str,
+    ) -> Result<BitmapFontHandle, ResourceError> {
+        let image = Image::open(path)?;
+        let font = BitmapFont::from_grid(image, cell_width, cell_height, chars);
+        Ok(self.add_bitmap_font(font))
+    }
+    pub fn add_bitmap_font(&mut self, font: BitmapFont) -> BitmapFontHandle {
+        self._bitmap_fonts.insert(font)
+    }
+    pub fn get_bitmap_font(&self, handle: BitmapFontHandle) -> Option<&BitmapFont> {
+        self._bitmap_fonts.get(handle)
+    }
+    pub fn try_get_bitmap_font(
+        &self,
+        handle: BitmapFontHandle,
+    ) -> Result<&BitmapFont, ResourceError> {
+        self.get_bitmap_font(handle)
+            .ok_or(ResourceError::InvalidHandle)
+    }
+    ///returns the removed font, or `None` if the handle was already stale
+    pub fn delete_bitmap_font(&mut self, handle: BitmapFontHandle) -> Option<BitmapFont> {
+        self._bitmap_fonts.remove(handle)
+    }
+    ///load an image and cut it into a grid of frames
+    pub fn try_load_sprite_sheet_grid(
+        &mut self,
+        path: &Path,
+        grid: &Grid,
+    ) -> Result<SpriteSheetHandle, ResourceError> {
+        let image = Image::open(path)?;
+        let (width, height) = (image.width(), image.height());
+        let sheet = SpriteSheet::from_grid(self.add_image(image), width, height, grid);
+        Ok(self.add_sprite_sheet(sheet))
+    }
+    ///load a TexturePacker style JSON frame list along with the image named in its `meta`,
+    ///which is looked up next to the JSON file
+    pub fn try_load_sprite_sheet_json(
+        &mut self,
+        path: &Path,
+    ) -> Result<SpriteSheetHandle, ResourceError> {
+        let json = read_to_string(path).map_err(|source| ResourceError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |reason: String| ResourceError::Parse {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let image_file = serde_json::from_str::<Value>(&json)
+            .map_err(|error| parse_error(error.to_string()))?
+            .get("meta")
+            .and_then(|meta| meta.get("image"))
+            .and_then(Value::as_str)
+            .ok_or_else(|| parse_error("no meta.image".to_owned()))?
+            .to_owned();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let image = Image::open(&directory.join(image_file))?;
+        let (width, height) = (image.width(), image.height());
+        let image = self.add_image(image);
+        let sheet = match SpriteSheet::from_json(image, &json) {
+            Ok(sheet) if sheet.fits(width, height) => sheet,
+            result => {
+                self.delete_image(image);
+                let reason = result
+                    .err()
+                    .unwrap_or_else(|| "frame outside of the image".to_owned());
+                return Err(parse_error(reason));
+            }
+        };
+        Ok(self.add_sprite_sheet(sheet))
+    }
+    ///load an `.ase`/`.aseprite` file with its visible layers flattened, every frame side by side
+    ///in a new image, along with its frame durations and tags
+    pub fn try_load_aseprite(&mut self, path: &Path) -> Result<SpriteSheetHandle, ResourceError> {
+        let aseprite = Aseprite::open(path)?;
+        let image = self.add_image(aseprite.sheet_image());
+        Ok(self.add_sprite_sheet(aseprite.sprite_sheet(image)))
+    }
+    pub fn add_sprite_sheet(&mut self, sheet: SpriteSheet) -> SpriteSheetHandle {
+        self._sprite_sheets.insert(sheet)
+    }
+    pub fn get_sprite_sheet(&self, handle: SpriteSheetHandle) -> Option<&SpriteSheet> {
+        self._sprite_sheets.get(handle)
+    }
+    pub fn get_sprite_sheet_mut(&mut self, handle: SpriteSheetHandle) -> Option<&mut SpriteSheet> {
+        self._sprite_sheets.get_mut(handle)
+    }
+    pub fn try_get_sprite_sheet(
+        &self,
+        handle: SpriteSheetHandle,
+    ) -> Result<&SpriteSheet, ResourceError> {
+        self.get_sprite_sheet(handle)
+            .ok_or(ResourceError::InvalidHandle)
+    }
+    ///returns the removed sheet, its image stays loaded until deleted with `delete_image`
+    pub fn delete_sprite_sheet(&mut self, handle: SpriteSheetHandle) -> Option<SpriteSheet> {
+        self._sprite_sheets.remove(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_load_image_missing_file() {
+        let mut resource_manager = ResourceManager::new();
+        let result = resource_manager.try_load_image(Path::new("resources/images/nope.png"));
+        assert!(matches!(result, Err(ResourceError::Io { .. })));
+    }
+
+    #[test]
+    fn test_try_load_font_not_a_font() {
+        let mut resource_manager = ResourceManager::new();
+        let result = resource_manager.try_load_font(
+            Path::new("resources/images/test_pattern_1.bmp"),
+            FontSettings::default(),
+        );
+        assert!(matches!(result, Err(ResourceError::FontParse { .. })));
+    }
+
+    #[test]
+    fn test_load_image_falls_back_to_missing_image() {
+        let mut resource_manager = ResourceManager::new();
+        resource_manager.set_missing_image(Image::new(1, 1, vec![1, 2, 3, 4]));
+        let handle = resource_manager.load_image(Path::new("resources/images/nope.png"));
+        let image = resource_manager.get_image(handle).unwrap();
+        assert_eq!(image.get_buf(), [1, 2, 3, 4]);
+        let errors = resource_manager.take_load_errors();
+        assert!(matches!(errors[..], [ResourceError::Io { .. }]));
+        assert!(resource_manager.take_load_errors().is_empty());
+    }
+
+    #[test]
+    fn test_missing_images_are_not_shared() {
+        let mut resource_manager = ResourceManager::new();
+        let first = resource_manager.load_image(Path::new("resources/images/nope.png"));
+        let second = resource_manager.load_image(Path::new("resources/images/nope.png"));
+        assert_ne!(first, second);
+        // freeing one placeholder leaves the other in place
+        assert!(resource_manager.delete_image(first).is_some());
+        let image = resource_manager.try_get_image(second).unwrap();
+        assert_eq!(image.get_buf(), Image::missing_texture().get_buf());
+        assert_eq!(resource_manager.take_load_errors().len(), 2);
+    }
+
+    #[test]
+    fn test_stale_image_handle() {
+        let mut resource_manager = ResourceManager::new();
+        let stale = resource_manager.add_image(Image::new(1, 1, vec![1, 1, 1, 1]));
+        assert!(resource_manager.delete_image(stale).is_some());
+        assert!(resource_manager.delete_image(stale).is_none());
+        let fresh = resource_manager.add_image(Image::new(1, 1, vec![2, 2, 2, 2]));
+        assert_ne!(stale, fresh);
+        assert!(resource_manager.get_image(stale).is_none());
+        assert!(matches!(
+            resource_manager.try_get_image(stale),
+            Err(ResourceError::InvalidHandle)
+        ));
+        assert_eq!(resource_manager.get_image(fresh).unwrap().get_buf(), [2; 4]);
     }
 }
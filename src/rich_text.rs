@@ -0,0 +1,671 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use fontdue::Font;
+
+use crate::resource::{FontHandle, ImageResource, ResourceManager};
+use crate::text::{FontHelper, PlacedGlyph, TextBox, TextSpan, TextStyle};
+use crate::types::{Color, Rect, Vec2};
+
+/// Fonts that markup can switch between, `[b]` and `[i]` fall back to `regular` when unset
+#[derive(Debug, Clone)]
+pub struct FontSet {
+    pub regular: FontHandle,
+    pub bold: Option<FontHandle>,
+    pub italic: Option<FontHandle>,
+    named: HashMap<String, FontHandle>,
+}
+
+impl FontSet {
+    pub fn new(regular: FontHandle) -> Self {
+        Self {
+            regular,
+            bold: None,
+            italic: None,
+            named: HashMap::new(),
+        }
+    }
+    pub fn with_bold(mut self, bold: FontHandle) -> Self {
+        self.bold = Some(bold);
+        self
+    }
+    pub fn with_italic(mut self, italic: FontHandle) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+    /// Make the font available as `[font=name]`
+    pub fn with_named(mut self, name: &str, font: FontHandle) -> Self {
+        self.named.insert(name.to_owned(), font);
+        self
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum TextEffect {
+    #[default]
+    None,
+    /// Characters bob up and down one after another, by up to `amplitude` pixels
+    Wave { amplitude: f32 },
+    /// Characters jitter randomly, by up to `amplitude` pixels
+    Shake { amplitude: f32 },
+}
+
+impl TextEffect {
+    const WAVE_SPEED: f32 = 6.0;
+    const WAVE_PHASE_STEP: f32 = 0.6;
+    const SHAKE_RATE: f32 = 30.0;
+
+    /// Offset of the `n`th character of a text at `time` seconds
+    pub fn offset(self, n: usize, time: f32) -> Vec2 {
+        match self {
+            Self::None => Vec2::new(0, 0),
+            Self::Wave { amplitude } => {
+                let phase = time * Self::WAVE_SPEED - n as f32 * Self::WAVE_PHASE_STEP;
+                Vec2::new(0, (phase.sin() * amplitude).round() as i32)
+            }
+            Self::Shake { amplitude } => {
+                let step = (time * Self::SHAKE_RATE) as u32;
+                let hash = hash((n as u32).wrapping_mul(0x9E37_79B9) ^ step);
+                let unit = |bits: u32| (bits & 0xFFFF) as f32 / 0xFFFF as f32 * 2.0 - 1.0;
+                Vec2::new(
+                    (unit(hash) * amplitude).round() as i32,
+                    (unit(hash >> 16) * amplitude).round() as i32,
+                )
+            }
+        }
+    }
+}
+
+/// Cheap integer hash so shaking is deterministic for a given time
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^ (x >> 16)
+}
+
+/// A run of text with everything the markup around it asked for, unset values come from the
+/// `TextStyle` it is drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichSpan {
+    pub text: String,
+    pub font: FontHandle,
+    pub size: Option<f32>,
+    pub color: Option<Color>,
+    pub effect: TextEffect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkupError {
+    UnknownTag(String),
+    /// A closing tag without a matching opening tag
+    UnexpectedClose(String),
+    InvalidValue {
+        tag: String,
+        value: String,
+    },
+    UnknownFont(String),
+    /// A `[` with no `]` after it
+    Unterminated,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownTag(tag) => write!(f, "Unknown tag [{}]", tag),
+            Self::UnexpectedClose(tag) => write!(f, "[/{}] does not close anything", tag),
+            Self::InvalidValue { tag, value } => {
+                write!(f, "Invalid value for [{}]: {}", tag, value)
+            }
+            Self::UnknownFont(name) => write!(f, "No font named {}", name),
+            Self::Unterminated => write!(f, "Tag is missing its closing ]"),
+        }
+    }
+}
+
+impl Error for MarkupError {}
+
+/// Text split into styled spans by markup like `Press [color=#ff0]A[/color] to [b]jump[/b]`.
+///
+/// Tags are `[color=#rgb]`, `[color=#rrggbb]`, `[color=#rrggbbaa]`, `[size=24]`, `[b]`, `[i]`,
+/// `[font=name]`, `[wave]`, `[wave=4]`, `[shake]` and `[shake=2]`, each closed by `[/tag]`.
+/// Unclosed tags run to the end of the text, `[[` is a literal `[`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichText {
+    spans: Vec<RichSpan>,
+}
+
+#[derive(Default)]
+struct MarkupState {
+    colors: Vec<Color>,
+    sizes: Vec<f32>,
+    fonts: Vec<FontHandle>,
+    effects: Vec<TextEffect>,
+    bold: u32,
+    italic: u32,
+}
+
+impl RichText {
+    const DEFAULT_WAVE: f32 = 3.0;
+    const DEFAULT_SHAKE: f32 = 1.0;
+
+    pub fn parse(markup: &str, fonts: &FontSet) -> Result<Self, MarkupError> {
+        let mut spans = Vec::new();
+        let mut state = MarkupState::default();
+        let mut text = String::new();
+        let mut rest = markup;
+        while let Some(start) = rest.find('[') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            if let Some(after) = rest.strip_prefix('[') {
+                text.push('[');
+                rest = after;
+                continue;
+            }
+            let end = rest.find(']').ok_or(MarkupError::Unterminated)?;
+            let tag = &rest[..end];
+            rest = &rest[end + 1..];
+            if !text.is_empty() {
+                spans.push(state.span(std::mem::take(&mut text), fonts));
+            }
+            match tag.strip_prefix('/') {
+                Some(closing) => state.close(closing)?,
+                None => state.open(tag, fonts)?,
+            }
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            spans.push(state.span(text, fonts));
+        }
+        Ok(Self { spans })
+    }
+    /// Unstyled text in a single font, for drawing with `draw_rich_text` without parsing
+    pub fn plain(text: &str, font: FontHandle) -> Self {
+        Self {
+            spans: vec![RichSpan {
+                text: text.to_owned(),
+                font,
+                size: None,
+                color: None,
+                effect: TextEffect::None,
+            }],
+        }
+    }
+    pub fn spans(&self) -> &[RichSpan] {
+        &self.spans
+    }
+    /// The text with all markup removed
+    pub fn to_plain_string(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+impl MarkupState {
+    fn span(&self, text: String, fonts: &FontSet) -> RichSpan {
+        let font = match self.fonts.last() {
+            Some(font) => *font,
+            None if self.bold > 0 && fonts.bold.is_some() => fonts.bold.unwrap(),
+            None if self.italic > 0 && fonts.italic.is_some() => fonts.italic.unwrap(),
+            None => fonts.regular,
+        };
+        RichSpan {
+            text,
+            font,
+            size: self.sizes.last().copied(),
+            color: self.colors.last().copied(),
+            effect: self.effects.last().copied().unwrap_or_default(),
+        }
+    }
+    fn open(&mut self, tag: &str, fonts: &FontSet) -> Result<(), MarkupError> {
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (tag, None),
+        };
+        let invalid = || MarkupError::InvalidValue {
+            tag: name.to_owned(),
+            value: value.unwrap_or_default().to_owned(),
+        };
+        match (name, value) {
+            ("color", Some(value)) => self.colors.push(parse_color(value).ok_or_else(invalid)?),
+            ("size", Some(value)) => {
+                let size = value.parse::<f32>().map_err(|_| invalid())?;
+                if size <= 0.0 || !size.is_finite() {
+                    return Err(invalid());
+                }
+                self.sizes.push(size);
+            }
+            ("font", Some(value)) => {
+                let font = fonts
+                    .named
+                    .get(value)
+                    .ok_or_else(|| MarkupError::UnknownFont(value.to_owned()))?;
+                self.fonts.push(*font);
+            }
+            ("wave", value) => {
+                let amplitude =
+                    parse_amplitude(value, RichText::DEFAULT_WAVE).ok_or_else(invalid)?;
+                self.effects.push(TextEffect::Wave { amplitude });
+            }
+            ("shake", value) => {
+                let amplitude =
+                    parse_amplitude(value, RichText::DEFAULT_SHAKE).ok_or_else(invalid)?;
+                self.effects.push(TextEffect::Shake { amplitude });
+            }
+            ("b", None) => self.bold += 1,
+            ("i", None) => self.italic += 1,
+            _ => return Err(MarkupError::UnknownTag(tag.to_owned())),
+        }
+        Ok(())
+    }
+    fn close(&mut self, tag: &str) -> Result<(), MarkupError> {
+        let closed = match tag {
+            "color" => self.colors.pop().is_some(),
+            "size" => self.sizes.pop().is_some(),
+            "font" => self.fonts.pop().is_some(),
+            "wave" | "shake" => self.effects.pop().is_some(),
+            "b" => decrement(&mut self.bold),
+            "i" => decrement(&mut self.italic),
+            _ => return Err(MarkupError::UnknownTag(format!("/{}", tag))),
+        };
+        if closed {
+            Ok(())
+        } else {
+            Err(MarkupError::UnexpectedClose(tag.to_owned()))
+        }
+    }
+}
+
+fn decrement(depth: &mut u32) -> bool {
+    if *depth == 0 {
+        return false;
+    }
+    *depth -= 1;
+    true
+}
+
+fn parse_amplitude(value: Option<&str>, default: f32) -> Option<f32> {
+    match value {
+        None => Some(default),
+        Some(value) => value
+            .parse::<f32>()
+            .ok()
+            .filter(|amplitude| amplitude.is_finite()),
+    }
+}
+
+/// `#rgb`, `#rrggbb` or `#rrggbbaa`
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    match hex.len() {
+        3 => {
+            let short = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|c| c * 17);
+            Some(Color::new(short(0)?, short(1)?, short(2)?, 255))
+        }
+        6 => Some(Color::new(channel(0)?, channel(1)?, channel(2)?, 255)),
+        8 => Some(Color::new(
+            channel(0)?,
+            channel(1)?,
+            channel(2)?,
+            channel(3)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Fonts used by `text`, in the order spans refer to them by index
+fn gather_fonts<'a>(
+    resource_manager: &'a ResourceManager,
+    text: &RichText,
+) -> (Vec<(FontHandle, &'a Font)>, Vec<Option<usize>>) {
+    let mut fonts: Vec<(FontHandle, &Font)> = Vec::new();
+    let indexes = text
+        .spans
+        .iter()
+        .map(|span| {
+            if let Some(i) = fonts.iter().position(|(handle, _)| *handle == span.font) {
+                return Some(i);
+            }
+            let font = resource_manager.get_font(span.font)?;
+            fonts.push((span.font, font));
+            Some(fonts.len() - 1)
+        })
+        .collect();
+    (fonts, indexes)
+}
+
+/// Lay out `text`, skipping spans whose font is not loaded, and call `draw` for every glyph
+fn layout_rich_text(
+    font_helper: &mut FontHelper,
+    resource_manager: &ResourceManager,
+    text: &RichText,
+    style: &TextStyle,
+    text_box: &TextBox,
+    mut draw: impl FnMut(&mut FontHelper, &[(FontHandle, &Font)], PlacedGlyph),
+) -> Rect {
+    let (fonts, indexes) = gather_fonts(resource_manager, text);
+    let spans = text
+        .spans
+        .iter()
+        .zip(indexes)
+        .filter_map(|(span, font_index)| {
+            Some(TextSpan {
+                text: &span.text,
+                font_index: font_index?,
+                size: span.size.unwrap_or(style.size),
+                color: span.color.unwrap_or(style.color),
+            })
+        })
+        .collect::<Vec<_>>();
+    let font_refs = fonts.iter().map(|(_, font)| *font).collect::<Vec<_>>();
+    let (glyphs, bounds) =
+        font_helper.layout_spans(&font_refs, &spans, style.line_spacing, text_box);
+    for glyph in glyphs {
+        draw(font_helper, &fonts, glyph);
+    }
+    bounds
+}
+
+/// The area `text` would cover if drawn, ignoring effects
+pub fn measure_rich_text(
+    font_helper: &mut FontHelper,
+    resource_manager: &ResourceManager,
+    text: &RichText,
+    style: &TextStyle,
+    text_box: &TextBox,
+) -> Rect {
+    layout_rich_text(
+        font_helper,
+        resource_manager,
+        text,
+        style,
+        text_box,
+        |_, _, _| {},
+    )
+}
+
+/// Draw `text` like `FontHelper::draw_text`, with `time` in seconds driving any effects.
+/// Returns the area the text covers before effects are applied.
+pub fn draw_rich_text(
+    font_helper: &mut FontHelper,
+    resource_manager: &ResourceManager,
+    text: &RichText,
+    style: &TextStyle,
+    text_box: &TextBox,
+    time: f32,
+    dst: &mut impl ImageResource,
+) -> Rect {
+    let origin = Vec2::new(0, 0);
+    if style.has_effects() {
+        let bounds = measure_rich_text(font_helper, resource_manager, text, style, text_box);
+        font_helper.draw_text_effects(style, bounds, dst, origin, |font_helper, mask, offset| {
+            draw_rich_layer(
+                font_helper,
+                resource_manager,
+                text,
+                style,
+                text_box,
+                time,
+                true,
+                mask,
+                offset,
+            );
+        });
+    }
+    draw_rich_layer(
+        font_helper,
+        resource_manager,
+        text,
+        style,
+        text_box,
+        time,
+        false,
+        dst,
+        origin,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_rich_layer(
+    font_helper: &mut FontHelper,
+    resource_manager: &ResourceManager,
+    text: &RichText,
+    style: &TextStyle,
+    text_box: &TextBox,
+    time: f32,
+    coverage_only: bool,
+    dst: &mut impl ImageResource,
+    offset: Vec2,
+) -> Rect {
+    // spans with a missing font are skipped, so map glyphs back through the visible spans
+    let visible = text
+        .spans
+        .iter()
+        .filter(|span| resource_manager.get_font(span.font).is_some())
+        .collect::<Vec<_>>();
+    let mut n = 0;
+    layout_rich_text(
+        font_helper,
+        resource_manager,
+        text,
+        style,
+        text_box,
+        |font_helper, fonts, mut glyph| {
+            // outlines and shadows take only the glyph's coverage, not the span's alpha
+            if coverage_only {
+                glyph.color = Color::new(255, 255, 255, 255);
+            }
+            let effect = visible[glyph.span].effect.offset(n, time);
+            n += 1;
+            let offset = Vec2::new(offset.x + effect.x, offset.y + effect.y);
+            font_helper.draw_glyph(fonts, &glyph, dst, offset);
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Image;
+    use crate::text::TextOutline;
+    use crate::types::FontSettings;
+    use std::path::Path;
+
+    fn load_fonts() -> (ResourceManager, FontSet) {
+        let mut resource_manager = ResourceManager::new();
+        let path = Path::new("resources/fonts/JetbrainsMonoRegular.ttf");
+        let regular = resource_manager
+            .try_load_font(path, FontSettings::default())
+            .unwrap();
+        let bold = resource_manager
+            .try_load_font(path, FontSettings::default())
+            .unwrap();
+        (resource_manager, FontSet::new(regular).with_bold(bold))
+    }
+
+    #[test]
+    fn test_parse_markup() {
+        let (_, fonts) = load_fonts();
+        let text = RichText::parse(
+            "Press [color=#ff0]A[/color] to [b]jump [size=30][color=#11223380]now[/color][/size][/b] [[ok]",
+            &fonts,
+        )
+        .unwrap();
+        let spans = text.spans();
+        assert_eq!(text.to_plain_string(), "Press A to jump now [ok]");
+        assert_eq!(spans.len(), 6);
+        assert_eq!(spans[1].text, "A");
+        assert_eq!(spans[1].color, Some(Color::new(255, 255, 0, 255)));
+        assert_eq!(spans[2].color, None);
+        assert_eq!(spans[3].text, "jump ");
+        assert_eq!(spans[3].font, fonts.bold.unwrap());
+        assert_eq!(spans[4].size, Some(30.0));
+        assert_eq!(spans[4].color, Some(Color::new(0x11, 0x22, 0x33, 0x80)));
+        assert_eq!(spans[5].text, " [ok]");
+        assert_eq!(spans[5].font, fonts.regular);
+    }
+
+    #[test]
+    fn test_parse_markup_errors() {
+        let (_, fonts) = load_fonts();
+        let parse = |markup| RichText::parse(markup, &fonts).unwrap_err();
+        assert_eq!(
+            parse("[blink]"),
+            MarkupError::UnknownTag("blink".to_owned())
+        );
+        assert_eq!(
+            parse("a[/color]"),
+            MarkupError::UnexpectedClose("color".to_owned())
+        );
+        assert_eq!(
+            parse("[color=red]"),
+            MarkupError::InvalidValue {
+                tag: "color".to_owned(),
+                value: "red".to_owned()
+            }
+        );
+        assert_eq!(
+            parse("[font=serif]"),
+            MarkupError::UnknownFont("serif".to_owned())
+        );
+        assert_eq!(parse("oops [b"), MarkupError::Unterminated);
+    }
+
+    #[test]
+    fn test_draw_rich_text_colors() {
+        let (resource_manager, fonts) = load_fonts();
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(20.0, Color::new(255, 255, 255, 255));
+        let text_box = TextBox::at(Vec2::new(2, 2));
+        let text = RichText::parse("ab[color=#f00]cd[/color][color=#0000ff80]ef", &fonts).unwrap();
+        let mut result = Image::blank(120, 40);
+        let bounds = draw_rich_text(
+            &mut font_helper,
+            &resource_manager,
+            &text,
+            &style,
+            &text_box,
+            0.0,
+            &mut result,
+        );
+        let mut plain = Image::blank(120, 40);
+        font_helper.draw_text(
+            &resource_manager,
+            fonts.regular,
+            "abcdef",
+            &style,
+            &text_box,
+            &mut plain,
+        );
+        let third = bounds.width as usize / 3;
+        let (mut whites, mut reds, mut blues) = (0, 0, 0);
+        for (i, (pixel, plain_pixel)) in result
+            .get_buf_u32()
+            .iter()
+            .zip(plain.get_buf_u32())
+            .enumerate()
+        {
+            // same glyph coverage as the plain text
+            assert_eq!(pixel >> 24 == 0, plain_pixel >> 24 == 0);
+            if pixel >> 24 == 0 {
+                continue;
+            }
+            // only fully covered pixels keep their exact color when blended onto black
+            if plain_pixel >> 24 != 0xFF {
+                continue;
+            }
+            let x = (i % 120) as i32 - bounds.left();
+            let [r, g, b, a] = pixel.to_le_bytes();
+            if x < third as i32 - 2 {
+                assert_eq!((r, g, b, a), (255, 255, 255, 255));
+                whites += 1;
+            } else if (third as i32 + 2..2 * third as i32 - 2).contains(&x) {
+                assert_eq!((r, g, b, a), (255, 0, 0, 255));
+                reds += 1;
+            } else if x > 2 * third as i32 + 2 {
+                assert_eq!((r, g), (0, 0));
+                assert!((b as i32 - 0x80).abs() <= 1);
+                assert!(a <= 0x80);
+                blues += 1;
+            }
+        }
+        assert!(whites > 0 && reds > 0 && blues > 0);
+    }
+
+    #[test]
+    fn test_effects_move_glyphs() {
+        let (resource_manager, fonts) = load_fonts();
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(20.0, Color::new(255, 255, 255, 255));
+        let text_box = TextBox::at(Vec2::new(10, 10));
+        let draw = |font_helper: &mut FontHelper, markup: &str, time: f32| {
+            let mut result = Image::blank(120, 50);
+            let text = RichText::parse(markup, &fonts).unwrap();
+            draw_rich_text(
+                font_helper,
+                &resource_manager,
+                &text,
+                &style,
+                &text_box,
+                time,
+                &mut result,
+            );
+            result
+        };
+        let still = draw(&mut font_helper, "wavy", 0.0);
+        let wave_a = draw(&mut font_helper, "[wave=4]wavy", 0.0);
+        let wave_b = draw(&mut font_helper, "[wave=4]wavy", 0.3);
+        assert_ne!(still.get_buf(), wave_a.get_buf());
+        assert_ne!(wave_a.get_buf(), wave_b.get_buf());
+        let shake_a = draw(&mut font_helper, "[shake=2]wavy", 0.5);
+        let shake_b = draw(&mut font_helper, "[shake=2]wavy", 0.5);
+        assert_eq!(shake_a.get_buf(), shake_b.get_buf());
+        for n in 0..16 {
+            let offset = TextEffect::Shake { amplitude: 2.0 }.offset(n, n as f32);
+            assert!(offset.x.abs() <= 2 && offset.y.abs() <= 2);
+        }
+    }
+
+    #[test]
+    fn test_effects_ignore_span_alpha() {
+        let (resource_manager, fonts) = load_fonts();
+        let mut font_helper = FontHelper::new();
+        let clear = Color::new(255, 0, 0, 0);
+        let style = TextStyle {
+            outline: Some(TextOutline {
+                thickness: 1,
+                color: Color::new(0, 255, 0, 255),
+            }),
+            ..TextStyle::new(20.0, clear)
+        };
+        let text_box = TextBox::at(Vec2::new(4, 4));
+        let text = RichText::parse("[color=#ff000000]ab", &fonts).unwrap();
+        let mut result = Image::blank(60, 40);
+        draw_rich_text(
+            &mut font_helper,
+            &resource_manager,
+            &text,
+            &style,
+            &text_box,
+            0.0,
+            &mut result,
+        );
+        // invisible text still gets the full outline, as it does with plain text
+        let mut plain = Image::blank(60, 40);
+        font_helper.draw_text(
+            &resource_manager,
+            fonts.regular,
+            "ab",
+            &style,
+            &text_box,
+            &mut plain,
+        );
+        assert!(plain.get_buf_u32().iter().any(|pixel| pixel >> 24 == 0xFF));
+        assert_eq!(result.get_buf(), plain.get_buf());
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::drawing::{blit_rect_with_alpha, blit_transformed, Transform};
+use crate::resource::{ImageHandle, ImageResource};
+use crate::types::{Rect, Vec2, Vec2F};
+
+/// One sprite on a sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub rect: Rect,
+    /// Point inside `rect`, in pixels from its top left, that gets placed at the draw position
+    pub pivot: Vec2F,
+    /// How long the frame is shown for when the sheet was exported with timing, like Aseprite
+    /// JSON exports
+    pub duration: Option<Duration>,
+}
+
+/// How `SpriteSheet::from_grid` cuts an image into equally sized cells
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Grid {
+    pub cell_width: u32,
+    pub cell_height: u32,
+    /// Empty border around the whole grid
+    pub margin: u32,
+    /// Empty gap between neighbouring cells
+    pub spacing: u32,
+    /// Pivot of every frame, in pixels from the top left of its cell
+    pub pivot: Vec2F,
+}
+
+impl Grid {
+    pub fn new(cell_width: u32, cell_height: u32) -> Self {
+        Self {
+            cell_width,
+            cell_height,
+            margin: 0,
+            spacing: 0,
+            pivot: Vec2F::new(0.0, 0.0),
+        }
+    }
+}
+
+/// Order in which the frames of an `AnimationTag` play
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AnimationDirection {
+    #[default]
+    Forward,
+    Reverse,
+    /// Forward then back again, without repeating the end frames
+    PingPong,
+    /// Backward then forward again, without repeating the end frames
+    PingPongReverse,
+}
+
+/// A named run of frames, like the tags on an Aseprite timeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationTag {
+    pub name: String,
+    /// First frame index
+    pub from: usize,
+    /// Last frame index, inclusive
+    pub to: usize,
+    pub direction: AnimationDirection,
+    /// How many times the tag plays, 0 for forever
+    pub repeat: u32,
+}
+
+/// Frames cut out of a single image, looked up by index or by name
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteSheet {
+    pub image: ImageHandle,
+    frames: Vec<Frame>,
+    names: HashMap<String, usize>,
+    tags: Vec<AnimationTag>,
+}
+
+impl SpriteSheet {
+    pub fn new(image: ImageHandle) -> Self {
+        Self {
+            image,
+            frames: Vec::new(),
+            names: HashMap::new(),
+            tags: Vec::new(),
+        }
+    }
+    /// Cells of an `image_width` by `image_height` image, left to right then top to bottom.
+    /// Partial cells at the right and bottom edges are left out.
+    pub fn from_grid(image: ImageHandle, image_width: u32, image_height: u32, grid: &Grid) -> Self {
+        let mut sheet = Self::new(image);
+        let count = |size: u32, cell: u32| {
+            let usable = size.saturating_sub(grid.margin * 2) + grid.spacing;
+            usable / (cell + grid.spacing).max(1)
+        };
+        let columns = count(image_width, grid.cell_width);
+        let rows = count(image_height, grid.cell_height);
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = Vec2::new(
+                    (grid.margin + column * (grid.cell_width + grid.spacing)) as i32,
+                    (grid.margin + row * (grid.cell_height + grid.spacing)) as i32,
+                );
+                sheet.add_frame(Frame {
+                    rect: Rect::new(top_left, grid.cell_width, grid.cell_height),
+                    pivot: grid.pivot,
+                    duration: None,
+                });
+            }
+        }
+        sheet
+    }
+    /// Frames from a TexturePacker style JSON frame list, in either its hash or array form.
+    /// Frames are named after their file names, normalized pivots are converted to pixels.
+    /// Aseprite's `meta.frameTags` become animation tags.
+    pub fn from_json(image: ImageHandle, json: &str) -> Result<Self, String> {
+        let root = serde_json::from_str::<Value>(json).map_err(|error| error.to_string())?;
+        let mut sheet = Self::new(image);
+        let frames = root.get("frames").ok_or("no frames")?;
+        let named = match frames {
+            Value::Object(pairs) => pairs
+                .iter()
+                .map(|(name, frame)| (name.as_str(), frame))
+                .collect::<Vec<_>>(),
+            Value::Array(frames) => frames
+                .iter()
+                .map(|frame| {
+                    let name = frame.get("filename").and_then(Value::as_str);
+                    (name.unwrap_or_default(), frame)
+                })
+                .collect(),
+            _ => return Err("frames is neither an object nor an array".to_owned()),
+        };
+        for (name, frame) in named {
+            let index = sheet.add_frame(
+                parse_frame(frame).map_err(|reason| format!("frame {:?}: {}", name, reason))?,
+            );
+            if !name.is_empty() {
+                sheet.set_name(index, name);
+            }
+        }
+        let tags = root
+            .get("meta")
+            .and_then(|meta| meta.get("frameTags"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for tag in tags {
+            let tag = parse_tag(tag).map_err(|reason| format!("frame tag: {}", reason))?;
+            if tag.from > tag.to || tag.to >= sheet.len() {
+                return Err(format!("frame tag {:?} is out of range", tag.name));
+            }
+            sheet.add_tag(tag);
+        }
+        Ok(sheet)
+    }
+    /// Append a frame, returning its index
+    pub fn add_frame(&mut self, frame: Frame) -> usize {
+        self.frames.push(frame);
+        self.frames.len() - 1
+    }
+    /// Make the frame at `index` available by `name` as well, replacing any frame of that name
+    pub fn set_name(&mut self, index: usize, name: &str) {
+        self.names.insert(name.to_owned(), index);
+    }
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+    pub fn frame_mut(&mut self, index: usize) -> Option<&mut Frame> {
+        self.frames.get_mut(index)
+    }
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+    pub fn frame_by_name(&self, name: &str) -> Option<&Frame> {
+        self.frame(self.index_of(name)?)
+    }
+    /// Add a tag, replacing any tag of the same name
+    pub fn add_tag(&mut self, tag: AnimationTag) {
+        match self.tags.iter_mut().find(|old| old.name == tag.name) {
+            Some(old) => *old = tag,
+            None => self.tags.push(tag),
+        }
+    }
+    pub fn tags(&self) -> &[AnimationTag] {
+        &self.tags
+    }
+    pub fn tag(&self, name: &str) -> Option<&AnimationTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+    /// Whether every frame lies inside an image of the given size
+    pub fn fits(&self, image_width: u32, image_height: u32) -> bool {
+        // in i64 since the far edges of a frame may not fit in an i32
+        self.frames.iter().all(|frame| {
+            let Rect {
+                top_left,
+                width,
+                height,
+            } = frame.rect;
+            top_left.x >= 0
+                && top_left.y >= 0
+                && top_left.x as i64 + width as i64 <= image_width as i64
+                && top_left.y as i64 + height as i64 <= image_height as i64
+        })
+    }
+}
+
+fn parse_frame(frame: &Value) -> Result<Frame, &'static str> {
+    let number = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_f64)
+            .ok_or("missing or invalid number")
+    };
+    if frame.get("rotated").and_then(Value::as_bool) == Some(true) {
+        return Err("rotated frames are not supported");
+    }
+    let rect = frame.get("frame").ok_or("no frame rect")?;
+    let (width, height) = (number(rect, "w")?, number(rect, "h")?);
+    if width < 0.0 || height < 0.0 {
+        return Err("negative frame size");
+    }
+    let (x, y) = (number(rect, "x")?, number(rect, "y")?);
+    let in_range = |value: f64| value >= i32::MIN as f64 && value <= i32::MAX as f64;
+    if ![x, y, x + width, y + height].into_iter().all(in_range) {
+        return Err("frame rect out of range");
+    }
+    let rect = Rect::new(Vec2::new(x as i32, y as i32), width as u32, height as u32);
+    // trimmed frames are offset inside their original, untrimmed size
+    let (trim_x, trim_y) = match frame.get("spriteSourceSize") {
+        Some(source) => (number(source, "x")?, number(source, "y")?),
+        None => (0.0, 0.0),
+    };
+    let (source_width, source_height) = match frame.get("sourceSize") {
+        Some(size) => (number(size, "w")?, number(size, "h")?),
+        None => (width, height),
+    };
+    let pivot = match frame.get("pivot") {
+        Some(pivot) => Vec2F::new(
+            (number(pivot, "x")? * source_width - trim_x) as f32,
+            (number(pivot, "y")? * source_height - trim_y) as f32,
+        ),
+        None => Vec2F::new(-trim_x as f32, -trim_y as f32),
+    };
+    let duration = frame
+        .get("duration")
+        .and_then(Value::as_f64)
+        .filter(|millis| *millis >= 0.0)
+        .map(|millis| Duration::from_secs_f64(millis / 1000.0));
+    Ok(Frame {
+        rect,
+        pivot,
+        duration,
+    })
+}
+
+fn parse_tag(tag: &Value) -> Result<AnimationTag, &'static str> {
+    let index = |key: &str| {
+        tag.get(key)
+            .and_then(Value::as_i64)
+            .and_then(|index| usize::try_from(index).ok())
+            .ok_or("missing or invalid frame index")
+    };
+    let direction = match tag.get("direction").and_then(Value::as_str) {
+        None | Some("forward") => AnimationDirection::Forward,
+        Some("reverse") => AnimationDirection::Reverse,
+        Some("pingpong") => AnimationDirection::PingPong,
+        Some("pingpong_reverse") => AnimationDirection::PingPongReverse,
+        Some(_) => return Err("unknown direction"),
+    };
+    let repeat = match tag.get("repeat") {
+        // Aseprite writes the repeat count as a string
+        Some(Value::String(repeat)) => repeat.parse().map_err(|_| "invalid repeat")?,
+        Some(repeat) => repeat
+            .as_i64()
+            .and_then(|repeat| u32::try_from(repeat).ok())
+            .ok_or("invalid repeat")?,
+        None => 0,
+    };
+    Ok(AnimationTag {
+        name: tag
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("no name")?
+            .to_owned(),
+        from: index("from")?,
+        to: index("to")?,
+        direction,
+        repeat,
+    })
+}
+
+/// Blit `frame` from `image` with its pivot at `position`
+pub fn draw_frame(
+    image: &impl ImageResource,
+    frame: &Frame,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+) {
+    let top_left = Vec2::new(
+        position.x - frame.pivot.x.round() as i32,
+        position.y - frame.pivot.y.round() as i32,
+    );
+    blit_rect_with_alpha(image, frame.rect, dst, top_left);
+}
+
+/// Blit `frame` from `image` with `transform` applied around the frame's pivot, which ends up at
+/// `position`. The pivot of `transform` is ignored.
+pub fn draw_frame_transformed(
+    image: &impl ImageResource,
+    frame: &Frame,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+    transform: &Transform,
+) {
+    let transform = Transform {
+        pivot: frame.pivot,
+        ..*transform
+    };
+    blit_transformed(image, frame.rect, dst, position, &transform);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{Image, ResourceManager};
+    use std::path::Path;
+
+    fn handle() -> ImageHandle {
+        ResourceManager::new().add_image(Image::blank(1, 1))
+    }
+
+    #[test]
+    fn test_grid_with_margin_and_spacing() {
+        let grid = Grid {
+            margin: 2,
+            spacing: 1,
+            pivot: Vec2F::new(4.0, 8.0),
+            ..Grid::new(8, 10)
+        };
+        // 2 + 8 + 1 + 8 + 1 + 8 + 2 = 30 wide, 2 + 10 + 1 + 10 + 2 = 25 tall, with 3 spare pixels
+        let sheet = SpriteSheet::from_grid(handle(), 33, 25, &grid);
+        assert_eq!(sheet.len(), 6);
+        assert!(sheet.fits(33, 25));
+        let rects = sheet
+            .frames()
+            .iter()
+            .map(|frame| frame.rect)
+            .collect::<Vec<_>>();
+        assert_eq!(rects[0], Rect::new(Vec2::new(2, 2), 8, 10));
+        assert_eq!(rects[2], Rect::new(Vec2::new(20, 2), 8, 10));
+        assert_eq!(rects[4], Rect::new(Vec2::new(11, 13), 8, 10));
+        assert_eq!(sheet.frame(5).unwrap().pivot, Vec2F::new(4.0, 8.0));
+        assert!(sheet.frame(6).is_none());
+    }
+
+    #[test]
+    fn test_texture_packer_json() {
+        let hash = r#"{"frames": {
+            "idle.png": {"frame": {"x": 0, "y": 0, "w": 16, "h": 16}, "rotated": false,
+                "trimmed": false, "pivot": {"x": 0.5, "y": 1.0}, "duration": 100},
+            "jump.png": {"frame": {"x": 16, "y": 0, "w": 10, "h": 12}, "rotated": false,
+                "trimmed": true, "spriteSourceSize": {"x": 3, "y": 4, "w": 10, "h": 12},
+                "sourceSize": {"w": 16, "h": 16}, "pivot": {"x": 0.5, "y": 1.0}}
+        }, "meta": {"image": "sheet.png"}}"#;
+        let sheet = SpriteSheet::from_json(handle(), hash).unwrap();
+        assert_eq!(sheet.len(), 2);
+        let idle = sheet.frame_by_name("idle.png").unwrap();
+        assert_eq!(idle.pivot, Vec2F::new(8.0, 16.0));
+        assert_eq!(idle.duration, Some(Duration::from_millis(100)));
+        let jump = sheet.frame(1).unwrap();
+        assert_eq!(jump.rect, Rect::new(Vec2::new(16, 0), 10, 12));
+        assert_eq!(jump.pivot, Vec2F::new(5.0, 12.0));
+        assert!(sheet.fits(26, 16));
+        assert!(!sheet.fits(25, 16));
+
+        let array = r#"{"frames": [
+            {"filename": "a", "frame": {"x": 0, "y": 0, "w": 4, "h": 4}},
+            {"filename": "b", "frame": {"x": 4, "y": 0, "w": 4, "h": 4}}
+        ], "meta": {"frameTags": [
+            {"name": "run", "from": 0, "to": 1, "direction": "pingpong", "repeat": "2"}
+        ]}}"#;
+        let sheet = SpriteSheet::from_json(handle(), array).unwrap();
+        assert_eq!(sheet.index_of("b"), Some(1));
+        let run = sheet.tag("run").unwrap();
+        assert_eq!((run.from, run.to, run.repeat), (0, 1, 2));
+        assert_eq!(run.direction, AnimationDirection::PingPong);
+        let bad_tag = array.replace("\"to\": 1", "\"to\": 2");
+        assert!(SpriteSheet::from_json(handle(), &bad_tag).is_err());
+        assert_eq!(sheet.frame(0).unwrap().pivot, Vec2F::new(0.0, 0.0));
+
+        let rotated =
+            r#"{"frames": [{"frame": {"x": 0, "y": 0, "w": 4, "h": 4}, "rotated": true}]}"#;
+        assert!(SpriteSheet::from_json(handle(), rotated).is_err());
+        assert!(SpriteSheet::from_json(handle(), "{}").is_err());
+        // far edges that don't fit in an i32
+        for rect in [
+            r#"{"x": 2147483647, "y": 0, "w": 4, "h": 4}"#,
+            r#"{"x": 0, "y": -3e9, "w": 4, "h": 4}"#,
+            r#"{"x": 0, "y": 0, "w": 1e12, "h": 4}"#,
+        ] {
+            let frames = format!(r#"{{"frames": [{{"frame": {}}}]}}"#, rect);
+            assert!(
+                SpriteSheet::from_json(handle(), &frames).is_err(),
+                "{}",
+                rect
+            );
+        }
+        let mut sheet = SpriteSheet::new(handle());
+        sheet.add_frame(Frame {
+            rect: Rect::new(Vec2::new(i32::MAX, 0), u32::MAX, 1),
+            pivot: Vec2F::new(0.0, 0.0),
+            duration: None,
+        });
+        assert!(!sheet.fits(u32::MAX, 1));
+        // not JSON numbers
+        for number in ["01", "1.", "-.5", "+1", ".5", "1e"] {
+            let frames = array.replace("\"w\": 4", &format!("\"w\": {}", number));
+            assert!(
+                SpriteSheet::from_json(handle(), &frames).is_err(),
+                "{}",
+                number
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_sprite_sheets() {
+        let mut resource_manager = ResourceManager::new();
+        let json = resource_manager
+            .try_load_sprite_sheet_json(Path::new("resources/images/sprite_sheet.json"))
+            .unwrap();
+        let grid = resource_manager
+            .try_load_sprite_sheet_grid(
+                Path::new("resources/images/sprite_sheet.png"),
+                &Grid::new(16, 16),
+            )
+            .unwrap();
+        let json = resource_manager.get_sprite_sheet(json).unwrap();
+        let grid = resource_manager.get_sprite_sheet(grid).unwrap();
+        assert_eq!(json.len(), grid.len());
+        for (a, b) in json.frames().iter().zip(grid.frames()) {
+            assert_eq!(a.rect, b.rect);
+        }
+        let image = resource_manager.get_image(json.image).unwrap();
+        let mut dst = Image::blank(16, 16);
+        let frame = json.frame_by_name("walk_1").unwrap();
+        draw_frame(image, frame, &mut dst, frame.pivot.into());
+        let mut expected = Image::blank(16, 16);
+        blit_rect_with_alpha(image, frame.rect, &mut expected, Vec2::new(0, 0));
+        assert_eq!(dst.get_buf(), expected.get_buf());
+
+        let bad = resource_manager
+            .try_load_sprite_sheet_grid(Path::new("resources/images/nope.png"), &Grid::new(16, 16));
+        assert!(bad.is_err());
+    }
+}
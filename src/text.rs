@@ -0,0 +1,1248 @@
+use std::collections::HashMap;
+
+use fontdue::layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle as LayoutStyle};
+use fontdue::Font;
+
+use crate::bitmap_font::{BitmapFont, BitmapGlyph};
+use crate::drawing::{
+    blit_rect_tinted, blit_rect_with_alpha, blit_transformed, blit_with_alpha, BlendMode, Transform,
+};
+use crate::resource::{BitmapFontHandle, FontHandle, Image, ImageResource, ResourceManager};
+use crate::types::{Color, Rect, Vec2, Vec2F};
+
+pub const DEFAULT_ATLAS_SIZE: u32 = 512;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct GlyphCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Glyphs dropped from the atlas to make room for others
+    pub evictions: u64,
+    /// Glyphs currently in the atlas
+    pub glyphs: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: FontHandle,
+    glyph_index: u16,
+    size_bits: u32,
+    color: Color,
+}
+
+/// A row of the atlas holding glyphs no taller than it, evicted as a whole
+struct Shelf {
+    top: u32,
+    height: u32,
+    cursor_x: u32,
+    last_used: u64,
+    keys: Vec<GlyphKey>,
+}
+
+/// Rasterized glyphs packed into a single atlas image so text can be blitted from it instead of
+/// rasterizing every glyph every frame. When the atlas is full the least recently used shelf of
+/// glyphs is evicted.
+pub struct GlyphCache {
+    atlas: Image,
+    shelves: Vec<Shelf>,
+    entries: HashMap<GlyphKey, (Rect, usize)>,
+    tick: u64,
+    stats: GlyphCacheStats,
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_ATLAS_SIZE, DEFAULT_ATLAS_SIZE)
+    }
+}
+
+impl GlyphCache {
+    pub fn new(atlas_width: u32, atlas_height: u32) -> Self {
+        Self {
+            atlas: Image::blank(atlas_width, atlas_height),
+            shelves: Vec::new(),
+            entries: HashMap::new(),
+            tick: 0,
+            stats: GlyphCacheStats::default(),
+        }
+    }
+    pub fn atlas(&self) -> &Image {
+        &self.atlas
+    }
+    pub fn stats(&self) -> GlyphCacheStats {
+        GlyphCacheStats {
+            glyphs: self.entries.len(),
+            ..self.stats
+        }
+    }
+    pub fn reset_stats(&mut self) {
+        self.stats = GlyphCacheStats::default();
+    }
+    pub fn clear(&mut self) {
+        self.stats.evictions += self.entries.len() as u64;
+        self.shelves.clear();
+        self.entries.clear();
+    }
+    /// The atlas region holding the glyph, rasterizing it first if needed.
+    /// `None` for glyphs without any pixels or too large to ever fit the atlas.
+    pub fn get_or_insert(
+        &mut self,
+        font_handle: FontHandle,
+        font: &Font,
+        glyph_index: u16,
+        size: f32,
+        color: Color,
+    ) -> Option<Rect> {
+        self.tick += 1;
+        let key = GlyphKey {
+            font: font_handle,
+            glyph_index,
+            size_bits: size.to_bits(),
+            color,
+        };
+        if let Some((rect, shelf)) = self.entries.get(&key) {
+            self.stats.hits += 1;
+            self.shelves[*shelf].last_used = self.tick;
+            return Some(*rect);
+        }
+        self.stats.misses += 1;
+        let (metrics, coverage) = font.rasterize_indexed(glyph_index, size);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let shelf = self.allocate(width, height)?;
+        let top_left = Vec2::new(
+            self.shelves[shelf].cursor_x as i32,
+            self.shelves[shelf].top as i32,
+        );
+        self.shelves[shelf].cursor_x += width;
+        self.shelves[shelf].last_used = self.tick;
+        self.shelves[shelf].keys.push(key);
+        let rect = Rect::new(top_left, width, height);
+        let atlas_width = self.atlas.width();
+        let atlas_buf = self.atlas.get_buf_u32_mut();
+        for (i, mask) in coverage.into_iter().enumerate() {
+            let x = rect.left() as u32 + i as u32 % width;
+            let y = rect.top() as u32 + i as u32 / width;
+            let alpha = (mask as u32 * color.a as u32 / 255) as u8;
+            atlas_buf[(x + y * atlas_width) as usize] =
+                Color::new(color.r, color.g, color.b, alpha).into();
+        }
+        self.entries.insert(key, (rect, shelf));
+        Some(rect)
+    }
+    /// Find a shelf with room for a `width` by `height` glyph, evicting if the atlas is full
+    fn allocate(&mut self, width: u32, height: u32) -> Option<usize> {
+        let atlas_width = self.atlas.width();
+        let atlas_height = self.atlas.height();
+        if width > atlas_width || height > atlas_height {
+            return None;
+        }
+        // the tightest shelf that still has room, so short glyphs don't use up tall shelves
+        let fitting = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= height && shelf.cursor_x + width <= atlas_width)
+            .min_by_key(|(_, shelf)| shelf.height)
+            .map(|(i, _)| i);
+        if let Some(i) = fitting {
+            if self.shelves[i].height <= height + height / 2 {
+                return Some(i);
+            }
+        }
+        let next_top = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.top + shelf.height);
+        if next_top + height <= atlas_height {
+            self.shelves.push(Shelf {
+                top: next_top,
+                height,
+                cursor_x: 0,
+                last_used: self.tick,
+                keys: Vec::new(),
+            });
+            return Some(self.shelves.len() - 1);
+        }
+        if fitting.is_some() {
+            return fitting;
+        }
+        let victim = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= height)
+            .min_by_key(|(_, shelf)| shelf.last_used)
+            .map(|(i, _)| i);
+        match victim {
+            Some(i) => {
+                let shelf = &mut self.shelves[i];
+                self.stats.evictions += shelf.keys.len() as u64;
+                for key in shelf.keys.drain(..) {
+                    self.entries.remove(&key);
+                }
+                shelf.cursor_x = 0;
+                Some(i)
+            }
+            None => {
+                self.clear();
+                self.allocate(width, height)
+            }
+        }
+    }
+}
+
+/// Same as `drawing::draw_text`, but blits glyphs from the glyph cache in `font_helper`
+/// instead of rasterizing them on every call
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_cached(
+    resource_manager: &ResourceManager,
+    font_helper: &mut FontHelper,
+    font_handle: FontHandle,
+    text: &str,
+    size: f32,
+    color: Color,
+    dst: &mut impl ImageResource,
+    offset: Vec2,
+) {
+    font_helper.draw_text(
+        resource_manager,
+        font_handle,
+        text,
+        &TextStyle::new(size, color),
+        &TextBox::at(offset),
+        dst,
+    );
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum WrapStyle {
+    /// Break lines between words where possible
+    #[default]
+    Word,
+    /// Break lines between any two characters
+    Letter,
+}
+
+/// How the glyphs themselves look
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextStyle {
+    pub size: f32,
+    pub color: Color,
+    /// Multiplier on the font's own line height
+    pub line_spacing: f32,
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
+}
+
+/// A border of `thickness` pixels around every glyph, drawn under the text
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextOutline {
+    pub thickness: u32,
+    pub color: Color,
+}
+
+/// A copy of the text, and its outline if any, drawn under it at `offset`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextShadow {
+    pub offset: Vec2,
+    pub color: Color,
+    /// Radius of the box blur softening the shadow, 0 for a hard shadow
+    pub blur: u32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: Color::new(255, 255, 255, 255),
+            line_spacing: 1.0,
+            outline: None,
+            shadow: None,
+        }
+    }
+}
+
+impl TextStyle {
+    pub fn new(size: f32, color: Color) -> Self {
+        Self {
+            size,
+            color,
+            ..Self::default()
+        }
+    }
+    pub(crate) fn has_effects(&self) -> bool {
+        self.outline.is_some() || self.shadow.is_some()
+    }
+    /// The same style without outline and shadow, in opaque white
+    fn coverage_only(&self) -> Self {
+        Self {
+            color: Color::new(255, 255, 255, 255),
+            outline: None,
+            shadow: None,
+            ..*self
+        }
+    }
+    /// `rect` grown by how far the outline and shadow reach past it
+    fn with_effects(&self, rect: Rect) -> Rect {
+        let outline = self.outline.map_or(0, |outline| outline.thickness as i32);
+        let (mut left, mut top, mut right, mut bottom) = (outline, outline, outline, outline);
+        if let Some(shadow) = self.shadow {
+            let reach = outline + shadow.blur as i32;
+            left = left.max(reach - shadow.offset.x);
+            right = right.max(reach + shadow.offset.x);
+            top = top.max(reach - shadow.offset.y);
+            bottom = bottom.max(reach + shadow.offset.y);
+        }
+        Rect::new(
+            Vec2::new(rect.left() - left, rect.top() - top),
+            (rect.width as i32 + left + right) as u32,
+            (rect.height as i32 + top + bottom) as u32,
+        )
+    }
+}
+
+/// Where text goes and how it is arranged inside that area
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TextBox {
+    pub position: Vec2,
+    /// Lines longer than this are wrapped, horizontal alignment only applies when set
+    pub max_width: Option<u32>,
+    /// Vertical alignment only applies when set, text taller than this still overflows
+    pub max_height: Option<u32>,
+    pub horizontal_align: HorizontalAlign,
+    pub vertical_align: VerticalAlign,
+    pub wrap_style: WrapStyle,
+    /// Whether newlines in the text start a new line
+    pub ignore_newlines: bool,
+}
+
+impl TextBox {
+    pub fn at(position: Vec2) -> Self {
+        Self {
+            position,
+            ..Self::default()
+        }
+    }
+    pub fn sized(position: Vec2, width: u32, height: u32) -> Self {
+        Self {
+            position,
+            max_width: Some(width),
+            max_height: Some(height),
+            ..Self::default()
+        }
+    }
+}
+
+/// A run of text sharing one font, size and color
+pub(crate) struct TextSpan<'a> {
+    pub text: &'a str,
+    pub font_index: usize,
+    pub size: f32,
+    pub color: Color,
+}
+
+impl<'a> TextSpan<'a> {
+    pub fn new(text: &'a str, style: &TextStyle) -> Self {
+        Self {
+            text,
+            font_index: 0,
+            size: style.size,
+            color: style.color,
+        }
+    }
+}
+
+/// A glyph with its final position in the destination
+pub(crate) struct PlacedGlyph {
+    pub span: usize,
+    pub font_index: usize,
+    pub glyph_index: u16,
+    pub size: f32,
+    pub color: Color,
+    pub position: Vec2,
+}
+
+/// Either kind of font the text functions can draw with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextFont {
+    Vector(FontHandle),
+    Bitmap(BitmapFontHandle),
+}
+
+impl From<FontHandle> for TextFont {
+    fn from(handle: FontHandle) -> Self {
+        Self::Vector(handle)
+    }
+}
+
+impl From<BitmapFontHandle> for TextFont {
+    fn from(handle: BitmapFontHandle) -> Self {
+        Self::Bitmap(handle)
+    }
+}
+
+/// Bitmap glyphs are tinted by the text color, so white glyphs take it on exactly
+fn draw_bitmap_glyph(
+    font: &BitmapFont,
+    glyph: &BitmapGlyph,
+    scale: i32,
+    color: Color,
+    dst: &mut impl ImageResource,
+    position: Vec2,
+) {
+    let page = match font.pages.get(glyph.page) {
+        Some(page) => page,
+        None => return,
+    };
+    if scale == 1 {
+        blit_rect_tinted(
+            page,
+            glyph.rect,
+            dst,
+            position,
+            color,
+            1.0,
+            BlendMode::Alpha,
+        );
+    } else {
+        let transform = Transform {
+            scale: Vec2F::new(scale as f32, scale as f32),
+            tint: color,
+            ..Transform::default()
+        };
+        blit_transformed(page, glyph.rect, dst, position, &transform);
+    }
+}
+
+pub struct FontHelper {
+    pub default_layout: Layout,
+    pub glyph_cache: GlyphCache,
+    span_layout: Layout<usize>,
+}
+
+impl Default for FontHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontHelper {
+    pub fn new() -> Self {
+        Self {
+            default_layout: Layout::new(CoordinateSystem::PositiveYDown),
+            glyph_cache: GlyphCache::default(),
+            span_layout: Layout::new(CoordinateSystem::PositiveYDown),
+        }
+    }
+    /// The area `text` would cover if drawn, without drawing it
+    pub fn measure_text(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: impl Into<TextFont>,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+    ) -> Rect {
+        match font.into() {
+            TextFont::Vector(handle) => match resource_manager.get_font(handle) {
+                Some(font) => {
+                    let spans = [TextSpan::new(text, style)];
+                    self.layout_spans(&[font], &spans, style.line_spacing, text_box)
+                        .1
+                }
+                None => Rect::new(text_box.position, 0, 0),
+            },
+            TextFont::Bitmap(handle) => match resource_manager.get_bitmap_font(handle) {
+                Some(font) => font.layout(text, style, text_box).1,
+                None => Rect::new(text_box.position, 0, 0),
+            },
+        }
+    }
+    /// How far `text` moves the pen on a single line, trailing spaces included. Where
+    /// `measure_text` covers the drawn pixels, this is where the next character would go.
+    pub fn text_width(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: impl Into<TextFont>,
+        text: &str,
+        style: &TextStyle,
+    ) -> i32 {
+        let positions = self.caret_positions(resource_manager, font, text, style);
+        positions.last().map_or(0, |(_, x)| *x)
+    }
+    /// Pen position at every character boundary of `text` laid out on a single line the way
+    /// `draw_text` lays it out, as `(byte offset, x)` pairs from 0 up to `text.len()`. Where
+    /// carets and selection edges go.
+    pub fn caret_positions(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: impl Into<TextFont>,
+        text: &str,
+        style: &TextStyle,
+    ) -> Vec<(usize, i32)> {
+        let mut positions = Vec::new();
+        let mut end = 0;
+        match font.into() {
+            TextFont::Vector(handle) => {
+                if let Some(font) = resource_manager.get_font(handle) {
+                    let layout = &mut self.span_layout;
+                    layout.reset(&LayoutSettings {
+                        wrap_hard_breaks: false,
+                        ..LayoutSettings::default()
+                    });
+                    layout.append(
+                        &[font],
+                        &LayoutStyle::with_user_data(text, style.size, 0, 0),
+                    );
+                    for glyph in layout.glyphs() {
+                        let metrics = font.metrics_indexed(glyph.key.glyph_index, style.size);
+                        // fontdue places glyphs at their left bearing from a whole pixel pen
+                        // and zeroes the metrics of control characters
+                        let (xmin, advance) = match glyph.char_data.is_control() {
+                            true => (0.0, 0.0),
+                            false => (metrics.bounds.xmin, metrics.advance_width),
+                        };
+                        let pen = glyph.x - xmin.floor();
+                        positions.push((glyph.byte_offset, pen as i32));
+                        end = (pen + advance.ceil()) as i32;
+                    }
+                }
+            }
+            TextFont::Bitmap(handle) => {
+                if let Some(font) = resource_manager.get_bitmap_font(handle) {
+                    let scale = font.scale(style.size);
+                    let pens = font.pen_positions(&text.chars().collect::<Vec<_>>());
+                    positions.extend(
+                        text.char_indices()
+                            .zip(&pens)
+                            .map(|((offset, _), pen)| (offset, pen * scale)),
+                    );
+                    end = pens.last().map_or(0, |pen| pen * scale);
+                }
+            }
+        }
+        positions.push((text.len(), end));
+        positions
+    }
+    /// Draw `text` wrapped and aligned inside `text_box`, returning the area it covers
+    pub fn draw_text(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: impl Into<TextFont>,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+        dst: &mut impl ImageResource,
+    ) -> Rect {
+        self.draw_text_offset(
+            resource_manager,
+            font.into(),
+            text,
+            style,
+            text_box,
+            dst,
+            Vec2::new(0, 0),
+        )
+    }
+    /// Render `text` into a new image exactly covering it, the position of `text_box` is ignored
+    pub fn draw_text_to_image(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: impl Into<TextFont>,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+    ) -> Image {
+        let font = font.into();
+        let text_box = TextBox {
+            position: Vec2::new(0, 0),
+            ..*text_box
+        };
+        let bounds =
+            style.with_effects(self.measure_text(resource_manager, font, text, style, &text_box));
+        let mut result = Image::blank(bounds.width, bounds.height);
+        let offset = Vec2::new(-bounds.left(), -bounds.top());
+        self.draw_text_offset(
+            resource_manager,
+            font,
+            text,
+            style,
+            &text_box,
+            &mut result,
+            offset,
+        );
+        result
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text_offset(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: TextFont,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+        dst: &mut impl ImageResource,
+        offset: Vec2,
+    ) -> Rect {
+        if style.has_effects() {
+            let bounds = self.measure_text(resource_manager, font, text, style, text_box);
+            let coverage_style = style.coverage_only();
+            self.draw_text_effects(style, bounds, dst, offset, |font_helper, mask, offset| {
+                font_helper.draw_text_layer(
+                    resource_manager,
+                    font,
+                    text,
+                    &coverage_style,
+                    text_box,
+                    mask,
+                    offset,
+                );
+            });
+        }
+        self.draw_text_layer(resource_manager, font, text, style, text_box, dst, offset)
+    }
+    /// Draw the outline and shadow of `style` for text covering `bounds`, `draw_coverage` is
+    /// called to draw the text in opaque white into a mask with the offset to draw it at
+    pub(crate) fn draw_text_effects(
+        &mut self,
+        style: &TextStyle,
+        bounds: Rect,
+        dst: &mut impl ImageResource,
+        offset: Vec2,
+        draw_coverage: impl FnOnce(&mut FontHelper, &mut Image, Vec2),
+    ) {
+        // room for the outline and blur around the text, the shadow offset is applied when drawing
+        let padding = style.outline.map_or(0, |outline| outline.thickness)
+            + style.shadow.map_or(0, |shadow| shadow.blur);
+        let area = Rect::new(
+            Vec2::new(
+                bounds.left() - padding as i32,
+                bounds.top() - padding as i32,
+            ),
+            bounds.width + padding * 2,
+            bounds.height + padding * 2,
+        );
+        if area.area() == 0 {
+            return;
+        }
+        let mut mask = Image::blank(area.width, area.height);
+        draw_coverage(self, &mut mask, Vec2::new(-area.left(), -area.top()));
+        let (width, height) = (area.width as usize, area.height as usize);
+        let mut coverage = mask
+            .get_buf_u32()
+            .iter()
+            .map(|pixel| (pixel >> 24) as u8)
+            .collect::<Vec<u8>>();
+        let position = Vec2::new(area.left() + offset.x, area.top() + offset.y);
+        let mut layers = Vec::new();
+        if let Some(outline) = style.outline {
+            coverage = dilate(&coverage, width, height, outline.thickness as i32);
+            layers.push((coverage.clone(), outline.color, position));
+        }
+        if let Some(shadow) = style.shadow {
+            let blurred = box_blur(&coverage, width, height, shadow.blur as usize);
+            let shadow_position =
+                Vec2::new(position.x + shadow.offset.x, position.y + shadow.offset.y);
+            layers.push((blurred, shadow.color, shadow_position));
+        }
+        // shadow first, then the outline over it
+        for (coverage, color, position) in layers.into_iter().rev() {
+            let buf = coverage
+                .into_iter()
+                .flat_map(|mask| {
+                    [
+                        color.r,
+                        color.g,
+                        color.b,
+                        (mask as u32 * color.a as u32 / 255) as u8,
+                    ]
+                })
+                .collect();
+            blit_with_alpha(&Image::new(area.width, area.height, buf), dst, position);
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text_layer(
+        &mut self,
+        resource_manager: &ResourceManager,
+        font: TextFont,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+        dst: &mut impl ImageResource,
+        offset: Vec2,
+    ) -> Rect {
+        match font {
+            TextFont::Vector(handle) => {
+                let font = match resource_manager.get_font(handle) {
+                    Some(font) => font,
+                    None => return Rect::new(text_box.position, 0, 0),
+                };
+                let spans = [TextSpan::new(text, style)];
+                let (glyphs, bounds) =
+                    self.layout_spans(&[font], &spans, style.line_spacing, text_box);
+                for glyph in glyphs {
+                    self.draw_glyph(&[(handle, font)], &glyph, dst, offset);
+                }
+                bounds
+            }
+            TextFont::Bitmap(handle) => {
+                let font = match resource_manager.get_bitmap_font(handle) {
+                    Some(font) => font,
+                    None => return Rect::new(text_box.position, 0, 0),
+                };
+                let (glyphs, bounds) = font.layout(text, style, text_box);
+                let scale = font.scale(style.size);
+                for (glyph, position) in glyphs {
+                    let position = Vec2::new(position.x + offset.x, position.y + offset.y);
+                    draw_bitmap_glyph(font, &glyph, scale, style.color, dst, position);
+                }
+                bounds
+            }
+        }
+    }
+    pub(crate) fn draw_glyph(
+        &mut self,
+        fonts: &[(FontHandle, &Font)],
+        glyph: &PlacedGlyph,
+        dst: &mut impl ImageResource,
+        offset: Vec2,
+    ) {
+        let (font_handle, font) = fonts[glyph.font_index];
+        let position = Vec2::new(glyph.position.x + offset.x, glyph.position.y + offset.y);
+        let cached = self.glyph_cache.get_or_insert(
+            font_handle,
+            font,
+            glyph.glyph_index,
+            glyph.size,
+            glyph.color,
+        );
+        match cached {
+            Some(rect) => blit_rect_with_alpha(self.glyph_cache.atlas(), rect, dst, position),
+            None => {
+                // too big for the atlas, draw it the slow way
+                let (metrics, coverage) = font.rasterize_indexed(glyph.glyph_index, glyph.size);
+                let color = glyph.color;
+                let glyph_image_buf = coverage
+                    .into_iter()
+                    .flat_map(|mask| {
+                        [
+                            color.r,
+                            color.g,
+                            color.b,
+                            (mask as u32 * color.a as u32 / 255) as u8,
+                        ]
+                    })
+                    .collect();
+                let glyph_image =
+                    Image::new(metrics.width as u32, metrics.height as u32, glyph_image_buf);
+                blit_with_alpha(&glyph_image, dst, position);
+            }
+        }
+    }
+    /// Position the visible glyphs of `spans` inside `text_box`, along with the area the text
+    /// covers: the line boxes vertically and the glyphs horizontally
+    pub(crate) fn layout_spans(
+        &mut self,
+        fonts: &[&Font],
+        spans: &[TextSpan],
+        line_spacing: f32,
+        text_box: &TextBox,
+    ) -> (Vec<PlacedGlyph>, Rect) {
+        let layout = &mut self.span_layout;
+        layout.reset(&LayoutSettings {
+            max_width: text_box.max_width.map(|width| width as f32),
+            horizontal_align: match text_box.horizontal_align {
+                HorizontalAlign::Left => fontdue::layout::HorizontalAlign::Left,
+                HorizontalAlign::Center => fontdue::layout::HorizontalAlign::Center,
+                HorizontalAlign::Right => fontdue::layout::HorizontalAlign::Right,
+            },
+            wrap_style: match text_box.wrap_style {
+                WrapStyle::Word => fontdue::layout::WrapStyle::Word,
+                WrapStyle::Letter => fontdue::layout::WrapStyle::Letter,
+            },
+            wrap_hard_breaks: !text_box.ignore_newlines,
+            ..LayoutSettings::default()
+        });
+        for (i, span) in spans.iter().enumerate() {
+            layout.append(
+                fonts,
+                &LayoutStyle::with_user_data(span.text, span.size, span.font_index, i),
+            );
+        }
+        let lines = match layout.lines() {
+            Some(lines) => lines,
+            None => return (Vec::new(), Rect::new(text_box.position, 0, 0)),
+        };
+        // fontdue has no line height setting, so spread the lines out afterwards
+        let mut line_shifts = Vec::with_capacity(lines.len());
+        let mut shift = 0.0;
+        for line in lines {
+            line_shifts.push(shift);
+            shift += line.max_new_line_size * (line_spacing - 1.0);
+        }
+        let height = layout.height() + line_shifts.last().unwrap();
+        let free_height = text_box
+            .max_height
+            .map_or(0.0, |max_height| max_height as f32 - height);
+        let align_shift = match text_box.vertical_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => free_height / 2.0,
+            VerticalAlign::Bottom => free_height,
+        };
+        let origin = Vec2::new(
+            text_box.position.x,
+            text_box.position.y + align_shift.round() as i32,
+        );
+        let mut left = i32::MAX;
+        let mut right = i32::MIN;
+        let mut placed = Vec::new();
+        let mut line_index = 0;
+        for (i, glyph) in layout.glyphs().iter().enumerate() {
+            while i > lines[line_index].glyph_end && line_index + 1 < lines.len() {
+                line_index += 1;
+            }
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+            let position = Vec2::new(
+                glyph.x as i32 + origin.x,
+                glyph.y as i32 + line_shifts[line_index].round() as i32 + origin.y,
+            );
+            left = left.min(position.x);
+            right = right.max(position.x + glyph.width as i32);
+            let span = &spans[glyph.user_data];
+            placed.push(PlacedGlyph {
+                span: glyph.user_data,
+                font_index: span.font_index,
+                glyph_index: glyph.key.glyph_index,
+                size: span.size,
+                color: span.color,
+                position,
+            });
+        }
+        if placed.is_empty() {
+            left = origin.x;
+            right = origin.x;
+        }
+        let bounds = Rect::new(
+            Vec2::new(left, origin.y),
+            (right - left) as u32,
+            height.ceil() as u32,
+        );
+        (placed, bounds)
+    }
+}
+
+/// Grow coverage by `radius` pixels in every direction
+fn dilate(coverage: &[u8], width: usize, height: usize, radius: i32) -> Vec<u8> {
+    let offsets = (-radius..=radius)
+        .flat_map(|y| (-radius..=radius).map(move |x| (x, y)))
+        .filter(|(x, y)| x * x + y * y <= radius * radius)
+        .collect::<Vec<_>>();
+    let mut result = vec![0; coverage.len()];
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let value = coverage[(x + y * width as i32) as usize];
+            if value == 0 {
+                continue;
+            }
+            for (dx, dy) in &offsets {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                    continue;
+                }
+                let target = &mut result[(nx + ny * width as i32) as usize];
+                *target = (*target).max(value);
+            }
+        }
+    }
+    result
+}
+
+/// Separable box blur, edges are treated as empty
+fn box_blur(coverage: &[u8], width: usize, height: usize, radius: usize) -> Vec<u8> {
+    if radius == 0 {
+        return coverage.to_vec();
+    }
+    let window = (radius * 2 + 1) as u32;
+    let pass = |src: &[u8], stride: usize, step: usize, lines: usize, length: usize| {
+        let mut dst = vec![0; src.len()];
+        for line in 0..lines {
+            let at = |i: usize| line * stride + i * step;
+            let mut sum = 0u32;
+            for i in 0..radius.min(length) {
+                sum += src[at(i)] as u32;
+            }
+            for i in 0..length {
+                if i + radius < length {
+                    sum += src[at(i + radius)] as u32;
+                }
+                dst[at(i)] = (sum / window) as u8;
+                if i >= radius {
+                    sum -= src[at(i - radius)] as u32;
+                }
+            }
+        }
+        dst
+    };
+    let horizontal = pass(coverage, width, 1, height, width);
+    pass(&horizontal, 1, width, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawing::draw_text;
+    use crate::types::FontSettings;
+    use std::path::Path;
+
+    const TEXT: &str = "Render time: 16ms";
+    const VISIBLE_GLYPHS: u64 = 15;
+
+    fn load_font() -> (ResourceManager, FontHandle) {
+        let mut resource_manager = ResourceManager::new();
+        let handle = resource_manager
+            .try_load_font(
+                Path::new("resources/fonts/JetbrainsMonoRegular.ttf"),
+                FontSettings::default(),
+            )
+            .unwrap();
+        (resource_manager, handle)
+    }
+
+    fn uncached(resource_manager: &ResourceManager, handle: FontHandle, size: f32) -> Image {
+        let mut expected = Image::blank(320, 40);
+        let font = resource_manager.get_font(handle).unwrap();
+        let mut layout = ResourceManager::new_layout();
+        draw_text(
+            font,
+            &mut layout,
+            TEXT,
+            size,
+            Color::new(200, 100, 50, 255),
+            &mut expected,
+            Vec2::new(3, 4),
+        );
+        expected
+    }
+
+    fn cached(
+        resource_manager: &ResourceManager,
+        font_helper: &mut FontHelper,
+        handle: FontHandle,
+        size: f32,
+    ) -> Image {
+        let mut result = Image::blank(320, 40);
+        draw_text_cached(
+            resource_manager,
+            font_helper,
+            handle,
+            TEXT,
+            size,
+            Color::new(200, 100, 50, 255),
+            &mut result,
+            Vec2::new(3, 4),
+        );
+        result
+    }
+
+    #[test]
+    fn test_draw_text_cached_matches_uncached() {
+        let (resource_manager, handle) = load_font();
+        let mut font_helper = FontHelper::new();
+        let expected = uncached(&resource_manager, handle, 20.0);
+        let first = cached(&resource_manager, &mut font_helper, handle, 20.0);
+        let stats = font_helper.glyph_cache.stats();
+        assert_eq!(stats.hits + stats.misses, VISIBLE_GLYPHS);
+        let second = cached(&resource_manager, &mut font_helper, handle, 20.0);
+        assert_eq!(first.get_buf(), expected.get_buf());
+        assert_eq!(second.get_buf(), expected.get_buf());
+        let second_stats = font_helper.glyph_cache.stats();
+        assert_eq!(second_stats.misses, stats.misses);
+        assert_eq!(second_stats.hits, stats.hits + VISIBLE_GLYPHS);
+        assert_eq!(second_stats.evictions, 0);
+    }
+
+    #[test]
+    fn test_glyph_cache_eviction() {
+        let (resource_manager, handle) = load_font();
+        let mut font_helper = FontHelper::new();
+        font_helper.glyph_cache = GlyphCache::new(48, 48);
+        for size in [20.0, 24.0, 20.0] {
+            let expected = uncached(&resource_manager, handle, size);
+            let result = cached(&resource_manager, &mut font_helper, handle, size);
+            assert_eq!(result.get_buf(), expected.get_buf());
+        }
+        assert!(font_helper.glyph_cache.stats().evictions > 0);
+    }
+
+    fn measure(
+        font_helper: &mut FontHelper,
+        text: &str,
+        style: &TextStyle,
+        text_box: &TextBox,
+    ) -> Rect {
+        let (resource_manager, handle) = load_font();
+        font_helper.measure_text(&resource_manager, handle, text, style, text_box)
+    }
+
+    #[test]
+    fn test_measure_text_lines_and_spacing() {
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(20.0, Color::new(255, 255, 255, 255));
+        let text_box = TextBox::at(Vec2::new(5, 7));
+        let one = measure(&mut font_helper, "one", &style, &text_box);
+        let three = measure(&mut font_helper, "one\ntwo\nthree", &style, &text_box);
+        assert_eq!(one.top_left.y, 7);
+        assert!((three.height as i32 - 3 * one.height as i32).abs() <= 2);
+        assert!(three.width > one.width);
+        let spaced = measure(
+            &mut font_helper,
+            "one\ntwo\nthree",
+            &TextStyle {
+                line_spacing: 2.0,
+                ..style
+            },
+            &text_box,
+        );
+        assert!((spaced.height as i32 - 5 * one.height as i32).abs() <= 3);
+        let ignored = measure(
+            &mut font_helper,
+            "one\ntwo\nthree",
+            &style,
+            &TextBox {
+                ignore_newlines: true,
+                ..text_box
+            },
+        );
+        assert_eq!(ignored.height, one.height);
+    }
+
+    #[test]
+    fn test_measure_text_wrap_and_align() {
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(20.0, Color::new(255, 255, 255, 255));
+        let text = "wrap these words";
+        let line = measure(&mut font_helper, "wrap", &style, &TextBox::default());
+        let wrapped_box = TextBox {
+            max_width: Some(70),
+            ..TextBox::default()
+        };
+        let wrapped = measure(&mut font_helper, text, &style, &wrapped_box);
+        assert!(wrapped.width <= 70);
+        assert!(wrapped.height > 2 * line.height);
+        let mut text_box = TextBox::sized(Vec2::new(10, 10), 200, 100);
+        text_box.horizontal_align = HorizontalAlign::Right;
+        text_box.vertical_align = VerticalAlign::Bottom;
+        let bottom_right = measure(&mut font_helper, text, &style, &text_box);
+        assert!(bottom_right.right() <= 210 && bottom_right.right() >= 205);
+        assert!((bottom_right.bottom() - 110).abs() <= 1);
+        text_box.horizontal_align = HorizontalAlign::Center;
+        text_box.vertical_align = VerticalAlign::Middle;
+        let centered = measure(&mut font_helper, text, &style, &text_box);
+        assert!((centered.left() - 10 - (210 - centered.right())).abs() <= 2);
+        assert!((centered.top() - 10 - (110 - centered.bottom())).abs() <= 2);
+    }
+
+    #[test]
+    fn test_draw_text_multiline_stays_in_bounds() {
+        let (resource_manager, handle) = load_font();
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(16.0, Color::new(255, 255, 255, 255));
+        let text_box = TextBox::sized(Vec2::new(4, 4), 100, 100);
+        let text = "some text that wraps\nover several lines";
+        let mut result = Image::blank(120, 120);
+        let bounds = font_helper.draw_text(
+            &resource_manager,
+            handle,
+            text,
+            &style,
+            &text_box,
+            &mut result,
+        );
+        assert_eq!(
+            bounds,
+            font_helper.measure_text(&resource_manager, handle, text, &style, &text_box)
+        );
+        let mut last_row = 0;
+        for (i, pixel) in result.get_buf_u32().iter().enumerate() {
+            if *pixel != 0 {
+                let point = Vec2::new(i as i32 % 120, i as i32 / 120);
+                assert!(
+                    bounds.point_intersects(point),
+                    "{:?} outside {:?}",
+                    point,
+                    bounds
+                );
+                last_row = last_row.max(point.y);
+            }
+        }
+        assert!(last_row > bounds.top() + 3 * bounds.height as i32 / 4);
+
+        let image =
+            font_helper.draw_text_to_image(&resource_manager, handle, text, &style, &text_box);
+        assert_eq!(
+            (image.width(), image.height()),
+            (bounds.width, bounds.height)
+        );
+        let copy = image.get_buf_u32();
+        for y in 0..bounds.height {
+            for x in 0..bounds.width {
+                let on_screen = result.get_buf_u32()
+                    [((y as i32 + bounds.top()) * 120 + x as i32 + bounds.left()) as usize];
+                assert_eq!(copy[(y * bounds.width + x) as usize], on_screen);
+            }
+        }
+    }
+
+    /// 2x2 cells: "a" is a solid square, "b" only its top left pixel
+    fn tiny_bitmap_font() -> (ResourceManager, BitmapFontHandle) {
+        let mut resource_manager = ResourceManager::new();
+        let mut buf = vec![0u8; 4 * 2 * 4];
+        for i in [0, 1, 4, 5, 2] {
+            buf[i * 4..i * 4 + 4].copy_from_slice(&[255, 255, 255, 255]);
+        }
+        let font = BitmapFont::from_grid(Image::new(4, 2, buf), 2, 2, "ab");
+        let handle = resource_manager.add_bitmap_font(font);
+        (resource_manager, handle)
+    }
+
+    #[test]
+    fn test_draw_bitmap_font_text() {
+        let (resource_manager, handle) = tiny_bitmap_font();
+        let mut font_helper = FontHelper::new();
+        let red = Color::new(255, 0, 0, 255);
+        let mut result = Image::blank(8, 4);
+        let bounds = font_helper.draw_text(
+            &resource_manager,
+            handle,
+            "ab\nba",
+            &TextStyle::new(2.0, red),
+            &TextBox::at(Vec2::new(1, 0)),
+            &mut result,
+        );
+        assert_eq!(bounds, Rect::new(Vec2::new(1, 0), 4, 4));
+        let r = u32::from(red);
+        #[rustfmt::skip]
+        let expected = [
+            0, r, r, r, 0, 0, 0, 0,
+            0, r, r, 0, 0, 0, 0, 0,
+            0, r, 0, r, r, 0, 0, 0,
+            0, 0, 0, r, r, 0, 0, 0,
+        ];
+        assert_eq!(result.get_buf_u32(), expected);
+
+        let image = font_helper.draw_text_to_image(
+            &resource_manager,
+            handle,
+            "b",
+            &TextStyle::new(4.0, red),
+            &TextBox::default(),
+        );
+        assert_eq!((image.width(), image.height()), (4, 4));
+        assert_eq!(
+            image.get_buf_u32(),
+            [r, r, 0, 0, r, r, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_text_outline_and_shadow() {
+        let (resource_manager, handle) = tiny_bitmap_font();
+        let mut font_helper = FontHelper::new();
+        let red = Color::new(255, 0, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+        let black = Color::new(0, 0, 0, 255);
+        let draw = |font_helper: &mut FontHelper, style: &TextStyle| {
+            let mut result = Image::blank(8, 8);
+            font_helper.draw_text(
+                &resource_manager,
+                handle,
+                "b",
+                style,
+                &TextBox::at(Vec2::new(2, 2)),
+                &mut result,
+            );
+            result.get_buf_u32().to_vec()
+        };
+        let (r, u, k) = (u32::from(red), u32::from(blue), u32::from(black));
+        let outlined = TextStyle {
+            outline: Some(TextOutline {
+                thickness: 1,
+                color: blue,
+            }),
+            ..TextStyle::new(2.0, red)
+        };
+        #[rustfmt::skip]
+        let expected = [
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, u, 0, 0, 0, 0, 0,
+            0, u, r, u, 0, 0, 0, 0,
+            0, 0, u, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, k, 0, 0,
+            0, 0, 0, 0, k, k, k, 0,
+            0, 0, 0, 0, 0, k, 0, 0,
+        ];
+        let shadowed = TextStyle {
+            shadow: Some(TextShadow {
+                offset: Vec2::new(3, 4),
+                color: black,
+                blur: 0,
+            }),
+            ..outlined
+        };
+        assert_eq!(draw(&mut font_helper, &shadowed), expected);
+
+        let blurred = TextStyle {
+            shadow: Some(TextShadow {
+                offset: Vec2::new(3, 3),
+                color: black,
+                blur: 1,
+            }),
+            ..TextStyle::new(2.0, red)
+        };
+        let result = draw(&mut font_helper, &blurred);
+        assert_eq!(result[2 + 2 * 8], r);
+        for (x, y) in [(4, 4), (5, 5), (6, 6), (4, 6)] {
+            assert_eq!(result[x + y * 8], 28 << 24);
+        }
+        assert_eq!(result[7 + 7 * 8], 0);
+
+        let image = font_helper.draw_text_to_image(
+            &resource_manager,
+            handle,
+            "b",
+            &outlined,
+            &TextBox::default(),
+        );
+        #[rustfmt::skip]
+        let expected = [
+            0, u, 0, 0,
+            u, r, u, 0,
+            0, u, 0, 0,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(image.get_buf_u32(), expected);
+    }
+}
@@ -0,0 +1,750 @@
+use std::ops::Range;
+
+use winit::event::{ElementState, Ime, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::canvas::Canvas;
+use crate::drawing::{draw_horizontal, draw_rectangle, draw_vertical, fill_rectangle};
+use crate::resource::{ImageResource, ResourceManager};
+use crate::text::{FontHelper, TextBox, TextFont, TextStyle, VerticalAlign};
+use crate::types::{Color, Rect, Vec2};
+use crate::Engine;
+
+/// One thing typed at a text field, see `TextInput`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextInputEvent {
+    /// A printable character
+    Char(char),
+    Backspace,
+    Delete,
+    /// Move the cursor, extending the selection instead of dropping it when `select` is set
+    Move {
+        motion: Motion,
+        select: bool,
+    },
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    /// Enter was pressed
+    Submit,
+    /// The IME is composing `text`, which isn't part of the string yet and replaces what was
+    /// composed before. `cursor` is a byte range into `text`, `None` when the cursor is hidden.
+    /// An empty `text` ends the composition.
+    Preedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+    /// The IME finished composing `text`, it goes in like typed characters
+    Commit(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+}
+
+/// Turns window events into `TextInputEvent`s, available to every update until the next frame.
+/// Shortcuts follow the usual conventions: ctrl (or cmd) with the arrows moves by word, with A,
+/// C, X and V selects all, copies, cuts and pastes, and shift extends the selection.
+#[derive(Debug, Default)]
+pub struct TextInput {
+    events: Vec<TextInputEvent>,
+    shift: bool,
+    control: bool,
+    alt: bool,
+    logo: bool,
+    /// Where copy and cut put text and paste takes it from, shared by every field. This is not
+    /// the system clipboard.
+    pub clipboard: String,
+    ime_request: Option<Vec2>,
+    ime_position: Option<Vec2>,
+    ime_allowed: bool,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Everything typed since the last frame, in order
+    pub fn events(&self) -> &[TextInputEvent] {
+        &self.events
+    }
+    /// Keep IME composition enabled for this frame, with the candidate window below `position`
+    /// on the frame buffer. Focused text fields call this every frame, and the IME is turned off
+    /// again once none do.
+    pub fn request_ime(&mut self, position: Vec2) {
+        self.ime_request = Some(position);
+    }
+    /// Whether the window currently accepts IME composition
+    pub fn ime_allowed(&self) -> bool {
+        self.ime_allowed
+    }
+    pub(crate) fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.handle_key(*keycode, *state == ElementState::Pressed),
+            WindowEvent::Focused(false) => {
+                self.shift = false;
+                self.control = false;
+                self.alt = false;
+                self.logo = false;
+            }
+            WindowEvent::ReceivedCharacter(c) => {
+                // ctrl+alt is AltGr on some layouts, which does type characters
+                let shortcut = self.logo || (self.control && !self.alt);
+                if !c.is_control() && !shortcut {
+                    self.events.push(TextInputEvent::Char(*c));
+                }
+            }
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+                self.events.push(TextInputEvent::Preedit {
+                    text: text.clone(),
+                    cursor: *cursor,
+                });
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => {
+                self.events.push(TextInputEvent::Commit(text.clone()));
+            }
+            WindowEvent::Ime(Ime::Disabled) => {
+                self.events.push(TextInputEvent::Preedit {
+                    text: String::new(),
+                    cursor: None,
+                });
+            }
+            _ => {}
+        }
+    }
+    fn handle_key(&mut self, keycode: VirtualKeyCode, pressed: bool) {
+        use VirtualKeyCode::*;
+        match keycode {
+            LShift | RShift => self.shift = pressed,
+            LControl | RControl => self.control = pressed,
+            LAlt | RAlt => self.alt = pressed,
+            LWin | RWin => self.logo = pressed,
+            _ => {}
+        }
+        if !pressed {
+            return;
+        }
+        let shortcut = self.control || self.logo;
+        let motion = |motion| TextInputEvent::Move {
+            motion,
+            select: self.shift,
+        };
+        let event = match keycode {
+            Back => TextInputEvent::Backspace,
+            Delete => TextInputEvent::Delete,
+            Left if shortcut => motion(Motion::WordLeft),
+            Left => motion(Motion::Left),
+            Right if shortcut => motion(Motion::WordRight),
+            Right => motion(Motion::Right),
+            Home => motion(Motion::Home),
+            End => motion(Motion::End),
+            Return | NumpadEnter => TextInputEvent::Submit,
+            A if shortcut => TextInputEvent::SelectAll,
+            C if shortcut => TextInputEvent::Copy,
+            X if shortcut => TextInputEvent::Cut,
+            V if shortcut => TextInputEvent::Paste,
+            _ => return,
+        };
+        self.events.push(event);
+    }
+    /// Forget this frame's events and requests. Returns whether the IME has to be turned on or
+    /// off, and where it moved to, if either changed.
+    pub(crate) fn end_frame(&mut self) -> (Option<bool>, Option<Vec2>) {
+        let request = self.ime_request.take();
+        let allowed = request.is_some();
+        let toggled = (allowed != self.ime_allowed).then_some(allowed);
+        let moved = request.filter(|position| self.ime_position != Some(*position));
+        self.ime_allowed = allowed;
+        self.ime_position = request;
+        self.events.clear();
+        (toggled, moved)
+    }
+}
+
+/// A single line text box for name entry, consoles and the like. Call `update` every frame to
+/// feed it input, then `draw` to put it on screen.
+#[derive(Debug, Clone)]
+pub struct TextField {
+    pub rect: Rect,
+    /// Space between the border and the text
+    pub padding: u32,
+    /// Characters past this many aren't accepted, `None` for no limit
+    pub max_length: Option<usize>,
+    /// Only a focused field takes input, clicking on a field focuses it and clicking anywhere
+    /// else unfocuses it
+    pub focused: bool,
+    pub background: Color,
+    pub border: Color,
+    pub selection: Color,
+    text: String,
+    /// Byte offset into `text`
+    cursor: usize,
+    /// The other end of the selection, the same as `cursor` when nothing is selected
+    anchor: usize,
+    preedit: String,
+    preedit_cursor: Option<(usize, usize)>,
+}
+
+impl TextField {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            padding: 2,
+            max_length: None,
+            focused: false,
+            background: Color::new(20, 20, 30, 255),
+            border: Color::new(120, 120, 140, 255),
+            selection: Color::new(60, 90, 160, 255),
+            text: String::new(),
+            cursor: 0,
+            anchor: 0,
+            preedit: String::new(),
+            preedit_cursor: None,
+        }
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Replace the text, cut down to `max_length`, with the cursor at its end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = match self.max_length {
+            Some(max_length) => text.chars().take(max_length).collect(),
+            None => text.to_string(),
+        };
+        self.cursor = self.text.len();
+        self.anchor = self.cursor;
+    }
+    /// Byte offset of the cursor in `text`
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    /// Byte range of the selected text, empty when nothing is selected
+    pub fn selection(&self) -> Range<usize> {
+        self.cursor.min(self.anchor)..self.cursor.max(self.anchor)
+    }
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+    /// What the IME is composing at the cursor, empty when it isn't
+    pub fn preedit(&self) -> &str {
+        &self.preedit
+    }
+    /// Take this frame's text input when focused, returning true when enter was pressed
+    pub fn update(&mut self, engine: &mut Engine) -> bool {
+        if engine.input.mouse_pressed(0) {
+            self.focused = engine
+                .mouse_position()
+                .is_some_and(|mouse| self.rect.point_intersects(mouse.into()));
+        }
+        if !self.focused {
+            return false;
+        }
+        let text_input = &mut engine.text_input;
+        let mut submitted = false;
+        for event in &text_input.events {
+            submitted |= self.apply(event, &mut text_input.clipboard);
+        }
+        text_input.request_ime(self.rect.bottom_left());
+        submitted
+    }
+    /// Apply a single event, with `clipboard` as the copy and paste buffer. Returns true for
+    /// `TextInputEvent::Submit`.
+    pub fn apply(&mut self, event: &TextInputEvent, clipboard: &mut String) -> bool {
+        // keys pressed while composing are meant for the IME
+        if !self.preedit.is_empty()
+            && !matches!(
+                event,
+                TextInputEvent::Preedit { .. } | TextInputEvent::Commit(_)
+            )
+        {
+            return false;
+        }
+        match event {
+            TextInputEvent::Char(c) => self.insert(c.encode_utf8(&mut [0; 4])),
+            TextInputEvent::Commit(text) => self.insert(text),
+            TextInputEvent::Backspace => {
+                if self.selection().is_empty() {
+                    self.anchor = previous_boundary(&self.text, self.cursor);
+                }
+                self.insert("");
+            }
+            TextInputEvent::Delete => {
+                if self.selection().is_empty() {
+                    self.anchor = next_boundary(&self.text, self.cursor);
+                }
+                self.insert("");
+            }
+            TextInputEvent::Move { motion, select } => {
+                let selection = self.selection();
+                self.cursor = match motion {
+                    // an arrow press drops the selection at the end it points at
+                    Motion::Left if !select && !selection.is_empty() => selection.start,
+                    Motion::Right if !select && !selection.is_empty() => selection.end,
+                    Motion::Left => previous_boundary(&self.text, self.cursor),
+                    Motion::Right => next_boundary(&self.text, self.cursor),
+                    Motion::WordLeft => previous_word(&self.text, self.cursor),
+                    Motion::WordRight => next_word(&self.text, self.cursor),
+                    Motion::Home => 0,
+                    Motion::End => self.text.len(),
+                };
+                if !select {
+                    self.anchor = self.cursor;
+                }
+            }
+            TextInputEvent::SelectAll => {
+                self.anchor = 0;
+                self.cursor = self.text.len();
+            }
+            TextInputEvent::Copy | TextInputEvent::Cut => {
+                if !self.selection().is_empty() {
+                    *clipboard = self.selected_text().to_string();
+                    if *event == TextInputEvent::Cut {
+                        self.insert("");
+                    }
+                }
+            }
+            TextInputEvent::Paste => {
+                // a single line has no room for line breaks
+                let pasted = clipboard.replace(['\r', '\n'], " ");
+                self.insert(&pasted);
+            }
+            TextInputEvent::Submit => return true,
+            TextInputEvent::Preedit { text, cursor } => {
+                if !text.is_empty() {
+                    self.insert("");
+                }
+                self.preedit = text.clone();
+                self.preedit_cursor = *cursor;
+            }
+        }
+        false
+    }
+    /// Replace the selection with as much of `text` as `max_length` leaves room for
+    fn insert(&mut self, text: &str) {
+        let selection = self.selection();
+        let room = self.max_length.map_or(usize::MAX, |max_length| {
+            let kept = self.text.chars().count() - self.text[selection.clone()].chars().count();
+            max_length.saturating_sub(kept)
+        });
+        let end = text
+            .char_indices()
+            .nth(room)
+            .map_or(text.len(), |(index, _)| index);
+        self.text.replace_range(selection.clone(), &text[..end]);
+        self.cursor = selection.start + end;
+        self.anchor = self.cursor;
+    }
+    /// Draw the box with its text, selection, IME composition and, when focused, the cursor.
+    /// Text that doesn't fit is scrolled to keep the cursor in view.
+    pub fn draw(
+        &self,
+        font_helper: &mut FontHelper,
+        resource_manager: &ResourceManager,
+        font: impl Into<TextFont>,
+        style: &TextStyle,
+        dst: &mut impl ImageResource,
+    ) {
+        let font = font.into();
+        fill_rectangle(self.rect, dst, self.background);
+        draw_rectangle(self.rect, dst, self.border);
+        let padding = self.padding as i32 + 1;
+        let inner = Rect::new(
+            Vec2::new(self.rect.left() + padding, self.rect.top() + padding),
+            self.rect.width.saturating_sub(padding as u32 * 2),
+            self.rect.height.saturating_sub(padding as u32 * 2),
+        );
+        let (before, after) = self.text.split_at(self.cursor);
+        let text = format!("{}{}{}", before, self.preedit, after);
+        // measured on the composed line as drawn, so kerning across the cursor is accounted for
+        let positions = font_helper.caret_positions(resource_manager, font, &text, style);
+        let x = |offset: usize| {
+            positions
+                .iter()
+                .find(|(boundary, _)| *boundary >= offset)
+                .map_or(0, |(_, x)| *x)
+        };
+        // offsets into `self.text` past the cursor are pushed along by the composition
+        let composed = |offset: usize| match offset > self.cursor {
+            true => offset + self.preedit.len(),
+            false => offset,
+        };
+        let caret = match (self.preedit.is_empty(), self.preedit_cursor) {
+            (true, _) => Some(x(before.len())),
+            (false, Some((_, end))) => Some(x(before.len() + end)),
+            (false, None) => None,
+        };
+        // scrolled just far enough for the cursor to stay inside
+        let scroll = (caret.unwrap_or(0) - inner.width as i32 + 1).max(0);
+        let left = inner.left() - scroll;
+
+        let mut canvas = Canvas::new(dst);
+        canvas.push_clip(inner);
+        let selection = self.selection();
+        if !selection.is_empty() {
+            let start = x(composed(selection.start));
+            let end = x(composed(selection.end));
+            fill_rectangle(
+                Rect::new(
+                    Vec2::new(left + start, inner.top()),
+                    (end - start) as u32,
+                    inner.height,
+                ),
+                &mut canvas,
+                self.selection,
+            );
+        }
+        if !self.preedit.is_empty() {
+            let start = x(before.len());
+            let end = x(before.len() + self.preedit.len());
+            draw_horizontal(
+                Vec2::new(left + start, inner.bottom() - 1),
+                (end - start) as u32,
+                &mut canvas,
+                style.color,
+            );
+        }
+        font_helper.draw_text(
+            resource_manager,
+            font,
+            &text,
+            style,
+            &TextBox {
+                max_height: Some(inner.height),
+                vertical_align: VerticalAlign::Middle,
+                ignore_newlines: true,
+                ..TextBox::at(Vec2::new(left, inner.top()))
+            },
+            &mut canvas,
+        );
+        if let (true, Some(caret)) = (self.focused, caret) {
+            draw_vertical(
+                Vec2::new(left + caret, inner.top()),
+                inner.height,
+                &mut canvas,
+                style.color,
+            );
+        }
+    }
+}
+
+fn previous_boundary(text: &str, index: usize) -> usize {
+    text[..index]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(index, _)| index)
+}
+
+fn next_boundary(text: &str, index: usize) -> usize {
+    text[index..]
+        .chars()
+        .next()
+        .map_or(index, |c| index + c.len_utf8())
+}
+
+/// The start of the word before `index`, skipping any whitespace in between
+fn previous_word(text: &str, index: usize) -> usize {
+    let trimmed = text[..index].trim_end();
+    trimmed
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(index, c)| index + c.len_utf8())
+}
+
+/// The end of the word after `index`, skipping any whitespace in between
+fn next_word(text: &str, index: usize) -> usize {
+    let rest = &text[index..];
+    let start = rest.len() - rest.trim_start().len();
+    rest[start..]
+        .char_indices()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(text.len(), |(end, _)| index + start + end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use crate::headless::{run_headless, Script, ScriptedInput};
+    use crate::resource::Image;
+    use crate::types::FontSettings;
+    use crate::{Context, GameState};
+
+    fn type_text(field: &mut TextField, text: &str) {
+        for c in text.chars() {
+            field.apply(&TextInputEvent::Char(c), &mut String::new());
+        }
+    }
+
+    fn motion(motion: Motion, select: bool) -> TextInputEvent {
+        TextInputEvent::Move { motion, select }
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut field = TextField::new(Rect::new(Vec2::new(0, 0), 100, 20));
+        let mut clipboard = String::new();
+        type_text(&mut field, "héllo wörld");
+        assert_eq!(field.cursor(), field.text().len());
+        for event in [
+            motion(Motion::WordLeft, false),
+            motion(Motion::Left, false),
+            TextInputEvent::Backspace,
+            TextInputEvent::Backspace,
+        ] {
+            field.apply(&event, &mut clipboard);
+        }
+        assert_eq!(field.text(), "hél wörld");
+        for event in [
+            motion(Motion::Home, false),
+            motion(Motion::Right, true),
+            motion(Motion::Right, true),
+        ] {
+            field.apply(&event, &mut clipboard);
+        }
+        assert_eq!(field.selected_text(), "hé");
+        // moving without shift drops the selection at its end
+        field.apply(&motion(Motion::Right, false), &mut clipboard);
+        assert_eq!((field.cursor(), field.selection().len()), (3, 0));
+        field.apply(&TextInputEvent::Delete, &mut clipboard);
+        assert_eq!(field.text(), "hé wörld");
+        field.apply(&motion(Motion::WordRight, true), &mut clipboard);
+        assert_eq!(field.selected_text(), " wörld");
+        type_text(&mut field, "!");
+        assert_eq!(field.text(), "hé!");
+        assert!(field.apply(&TextInputEvent::Submit, &mut clipboard));
+    }
+
+    #[test]
+    fn test_clipboard_and_max_length() {
+        let mut field = TextField::new(Rect::new(Vec2::new(0, 0), 100, 20));
+        field.max_length = Some(8);
+        let mut clipboard = String::new();
+        type_text(&mut field, "one two");
+        for event in [
+            motion(Motion::WordLeft, true),
+            TextInputEvent::Cut,
+            TextInputEvent::Paste,
+            TextInputEvent::Paste,
+        ] {
+            field.apply(&event, &mut clipboard);
+        }
+        assert_eq!(clipboard, "two");
+        // the second paste only fits one more character
+        assert_eq!(field.text(), "one twot");
+        field.apply(&TextInputEvent::SelectAll, &mut clipboard);
+        field.apply(&TextInputEvent::Copy, &mut clipboard);
+        assert_eq!(clipboard, "one twot");
+        clipboard = "a\nb".to_string();
+        field.apply(&TextInputEvent::Paste, &mut clipboard);
+        assert_eq!(field.text(), "a b");
+        field.set_text("far too long for it");
+        assert_eq!(field.text(), "far too ");
+    }
+
+    #[test]
+    fn test_ime_composition() {
+        let mut field = TextField::new(Rect::new(Vec2::new(0, 0), 100, 20));
+        let mut clipboard = String::new();
+        type_text(&mut field, "ab");
+        field.apply(&motion(Motion::Left, true), &mut clipboard);
+        let preedit = |text: &str| TextInputEvent::Preedit {
+            text: text.to_string(),
+            cursor: Some((text.len(), text.len())),
+        };
+        for event in [
+            preedit("ni"),
+            // keys go to the IME while it composes
+            TextInputEvent::Backspace,
+            preedit("你"),
+        ] {
+            field.apply(&event, &mut clipboard);
+        }
+        assert_eq!(field.text(), "a");
+        assert_eq!(field.preedit(), "你");
+        field.apply(&preedit(""), &mut clipboard);
+        field.apply(&TextInputEvent::Commit("你好".to_string()), &mut clipboard);
+        assert_eq!(field.text(), "a你好");
+        assert_eq!(field.preedit(), "");
+        field.apply(&TextInputEvent::Backspace, &mut clipboard);
+        assert_eq!(field.text(), "a你");
+    }
+
+    #[test]
+    fn test_draw_stays_inside() {
+        let mut resource_manager = ResourceManager::new();
+        let font = resource_manager
+            .try_load_font(
+                Path::new("resources/fonts/JetbrainsMonoRegular.ttf"),
+                FontSettings::default(),
+            )
+            .unwrap();
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(14.0, Color::new(255, 255, 255, 255));
+        // trailing spaces still move the cursor along
+        let mut width = |text| font_helper.text_width(&resource_manager, font, text, &style);
+        assert!(width("a ") > width("a"));
+        assert_eq!(width(""), 0);
+        // the pen moves by whole pixels like fontdue's layout does, fractions don't add up
+        let advance = resource_manager
+            .get_font(font)
+            .unwrap()
+            .metrics('a', 14.0)
+            .advance_width;
+        assert_eq!(width("aaaaaaaaaa"), 10 * advance.ceil() as i32);
+
+        let rect = Rect::new(Vec2::new(4, 4), 40, 20);
+        let mut field = TextField::new(rect);
+        field.focused = true;
+        field.set_text("much more text than fits");
+        let mut dst = Image::blank(48, 28);
+        field.draw(&mut font_helper, &resource_manager, font, &style, &mut dst);
+        let pixels = dst.get_buf_u32();
+        for y in 0..28 {
+            for x in 0..48 {
+                let inside = rect.point_intersects(Vec2::new(x, y));
+                assert_eq!(
+                    pixels[(x + y * 48) as usize] != 0,
+                    inside,
+                    "at {}, {}",
+                    x,
+                    y
+                );
+            }
+        }
+        // scrolled to the end, the caret sits against the right edge
+        let caret = u32::from(style.color);
+        assert_eq!(pixels[(rect.right() - 4 + 12 * 48) as usize], caret);
+    }
+
+    #[test]
+    fn test_caret_follows_kerning() {
+        let mut resource_manager = ResourceManager::new();
+        let handle = resource_manager
+            .try_load_bitmap_font(Path::new("resources/fonts/pixel_mono.fnt"))
+            .unwrap();
+        let mut font = resource_manager.delete_bitmap_font(handle).unwrap();
+        font.set_kerning('A', 'V', -3);
+        let advance = font.glyph('A').unwrap().advance;
+        let back = font.kerning('V', 'A');
+        let font = resource_manager.add_bitmap_font(font);
+        let mut font_helper = FontHelper::new();
+        let style = TextStyle::new(13.0, Color::new(255, 255, 255, 255));
+        assert_eq!(
+            font_helper.caret_positions(&resource_manager, font, "AVA", &style),
+            [
+                (0, 0),
+                (1, advance - 3),
+                (2, advance * 2 - 3 + back),
+                (3, advance * 3 - 3 + back)
+            ]
+        );
+
+        // the caret between A and V and the selection of V start where V is drawn
+        let mut field = TextField::new(Rect::new(Vec2::new(0, 0), 40, 24));
+        field.focused = true;
+        field.set_text("AV");
+        let left = TextInputEvent::Move {
+            motion: Motion::Left,
+            select: true,
+        };
+        field.apply(&left, &mut String::new());
+        assert_eq!(field.selection(), 1..2);
+        let mut dst = Image::blank(40, 24);
+        field.draw(&mut font_helper, &resource_manager, font, &style, &mut dst);
+        let v = (field.padding + 1) as i32 + advance - 3;
+        // a row above the glyphs
+        let pixel = |x: i32| dst.get_buf_u32()[(x + 4 * 40) as usize];
+        assert_eq!(pixel(v - 1), u32::from(field.background));
+        assert_eq!(pixel(v), u32::from(style.color));
+        assert_eq!(pixel(v + 1), u32::from(field.selection));
+        assert_eq!(pixel(v + advance - 1), u32::from(field.selection));
+        assert_eq!(pixel(v + advance), u32::from(field.background));
+    }
+
+    struct NameEntry {
+        ctx: Context,
+        field: TextField,
+        submitted: Vec<String>,
+        ime_allowed: Vec<bool>,
+    }
+
+    impl GameState for NameEntry {
+        fn on_update(&mut self, _elapsed_time: Duration, engine: &mut Engine) -> bool {
+            self.ime_allowed.push(engine.text_input.ime_allowed());
+            if self.field.update(engine) {
+                self.submitted.push(self.field.text().to_string());
+                self.field.set_text("");
+            }
+            true
+        }
+        fn context(&self) -> &Context {
+            &self.ctx
+        }
+    }
+
+    #[test]
+    fn test_headless_name_entry() {
+        use crate::types::MouseButton;
+        use ScriptedInput::*;
+        let mut game = NameEntry {
+            ctx: Context {
+                screen_width: 40,
+                screen_height: 20,
+                vsync_enabled: false,
+                fixed_timestep: None,
+            },
+            field: TextField::new(Rect::new(Vec2::new(0, 0), 40, 10)),
+            submitted: Vec::new(),
+            ime_allowed: Vec::new(),
+        };
+        let script = Script::new(6, Duration::from_millis(16))
+            // typed before the field has focus
+            .with_input(0, Character('x'))
+            .with_input(1, MouseMoved(5.0, 5.0))
+            .with_input(1, MousePressed(MouseButton::Left))
+            .with_input(2, Character('a'))
+            .with_input(2, Character('\u{8}'))
+            .with_input(2, KeyPressed(VirtualKeyCode::Back))
+            .with_input(2, Character('b'))
+            .with_input(2, KeyPressed(VirtualKeyCode::LShift))
+            .with_input(2, KeyPressed(VirtualKeyCode::Home))
+            .with_input(2, KeyReleased(VirtualKeyCode::LShift))
+            .with_input(2, KeyPressed(VirtualKeyCode::LControl))
+            .with_input(2, KeyPressed(VirtualKeyCode::C))
+            .with_input(2, Character('\u{3}'))
+            .with_input(2, KeyPressed(VirtualKeyCode::End))
+            .with_input(2, KeyPressed(VirtualKeyCode::V))
+            .with_input(2, KeyReleased(VirtualKeyCode::LControl))
+            .with_input(3, ImePreedit("c", Some((1, 1))))
+            .with_input(3, ImePreedit("", None))
+            .with_input(3, ImeCommit("ç"))
+            .with_input(3, KeyPressed(VirtualKeyCode::Return))
+            .with_input(4, MouseMoved(5.0, 15.0))
+            .with_input(4, MousePressed(MouseButton::Left))
+            .with_input(5, Character('y'));
+        let engine = run_headless(&mut game, &script);
+        assert_eq!(game.submitted, ["bbç"]);
+        assert_eq!(engine.text_input.clipboard, "b");
+        assert_eq!(game.field.text(), "");
+        assert!(!game.field.focused);
+        assert_eq!(game.ime_allowed, [false, false, true, true, true, false]);
+        assert!(!engine.text_input.ime_allowed());
+    }
+}
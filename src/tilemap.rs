@@ -0,0 +1,1658 @@
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+use std::fs::read_to_string;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde_json::Value;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::drawing::{blit_rect_tinted, blit_transformed, BlendMode, Flip, Transform};
+use crate::resource::{Image, ImageHandle, ImageResource, ResourceError, ResourceManager};
+use crate::types::{Color, Rect, Vec2, Vec2F};
+
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Only used by hexagonal maps, cleared along with the flip flags
+const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const GID_MASK: u32 =
+    !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120);
+
+const WHITE: Color = Color::new(255, 255, 255, 255);
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Orientation {
+    #[default]
+    Orthogonal,
+    /// Diamond shaped tiles, with tile (0, 0) at the top
+    Isometric,
+}
+
+/// A Tiled custom property
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color(Color),
+    /// Path as written in the map, relative to the map file
+    File(String),
+    /// Id of an object on the map, 0 for none
+    Object(u32),
+    Class(Properties),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TileAnimationFrame {
+    /// Id of the tile to show, local to its tileset
+    pub tile_id: u32,
+    pub duration: Duration,
+}
+
+/// Extra information Tiled stores for some tiles of a tileset
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileData {
+    pub class: String,
+    pub properties: Properties,
+    pub animation: Vec<TileAnimationFrame>,
+}
+
+/// Equally sized tiles cut from one image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    /// Global id of the first tile, gids below it belong to earlier tilesets
+    pub first_gid: u32,
+    pub name: String,
+    pub image: ImageHandle,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub spacing: u32,
+    pub margin: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Drawing offset applied to every tile of the set
+    pub tile_offset: Vec2,
+    pub properties: Properties,
+    /// Keyed by tile id local to the tileset
+    pub tiles: HashMap<u32, TileData>,
+}
+
+impl Tileset {
+    /// Where the tile with the local `id` is on the tileset image
+    pub fn tile_rect(&self, id: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let (column, row) = (id % columns, id / columns);
+        Rect::new(
+            Vec2::new(
+                (self.margin + column * (self.tile_width + self.spacing)) as i32,
+                (self.margin + row * (self.tile_height + self.spacing)) as i32,
+            ),
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+    pub fn tile(&self, id: u32) -> Option<&TileData> {
+        self.tiles.get(&id)
+    }
+    /// The tile shown in place of `id` at `time`, which differs from `id` for animated tiles
+    pub fn animated_tile(&self, id: u32, time: Duration) -> u32 {
+        let animation = match self.tiles.get(&id) {
+            Some(tile) if !tile.animation.is_empty() => &tile.animation,
+            _ => return id,
+        };
+        let total = animation
+            .iter()
+            .map(|frame| frame.duration.as_millis())
+            .sum::<u128>();
+        if total == 0 {
+            return animation[0].tile_id;
+        }
+        let mut at = time.as_millis() % total;
+        for frame in animation {
+            if at < frame.duration.as_millis() {
+                return frame.tile_id;
+            }
+            at -= frame.duration.as_millis();
+        }
+        id
+    }
+}
+
+/// A tile placed on a layer or used by a tile object
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LayerTile {
+    /// Global id, look it up with `Tilemap::tileset_for`
+    pub gid: u32,
+    pub flip: Flip,
+    /// Swap x and y before flipping, Tiled's way of rotating tiles by quarter turns
+    pub diagonal: bool,
+}
+
+impl LayerTile {
+    pub fn new(gid: u32) -> Self {
+        Self {
+            gid: gid & GID_MASK,
+            flip: Flip::default(),
+            diagonal: false,
+        }
+    }
+    /// Decode a gid with Tiled's flip flags in its top bits, `None` for an empty cell
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        let gid = raw & GID_MASK;
+        (gid != 0).then_some(Self {
+            gid,
+            flip: Flip {
+                horizontal: raw & FLIPPED_HORIZONTALLY != 0,
+                vertical: raw & FLIPPED_VERTICALLY != 0,
+            },
+            diagonal: raw & FLIPPED_DIAGONALLY != 0,
+        })
+    }
+    pub fn to_raw(self) -> u32 {
+        let mut raw = self.gid & GID_MASK;
+        if self.flip.horizontal {
+            raw |= FLIPPED_HORIZONTALLY;
+        }
+        if self.flip.vertical {
+            raw |= FLIPPED_VERTICALLY;
+        }
+        if self.diagonal {
+            raw |= FLIPPED_DIAGONALLY;
+        }
+        raw
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileLayer {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row, as stored by Tiled
+    tiles: Vec<u32>,
+}
+
+impl TileLayer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![0; width as usize * height as usize],
+        }
+    }
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height)
+            .then(|| y as usize * self.width as usize + x as usize)
+    }
+    pub fn tile(&self, x: i32, y: i32) -> Option<LayerTile> {
+        LayerTile::from_raw(self.tiles[self.index(x, y)?])
+    }
+    /// Place a tile, or clear the cell with `None`. Cells outside the layer are ignored.
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Option<LayerTile>) {
+        if let Some(index) = self.index(x, y) {
+            self.tiles[index] = tile.map_or(0, LayerTile::to_raw);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the object position
+    Polygon(Vec<Vec2F>),
+    Polyline(Vec<Vec2F>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    /// In map pixels, the bottom left corner for tile objects and the top left otherwise. On
+    /// isometric maps use `Tilemap::object_position` to get to world pixels.
+    pub position: Vec2F,
+    pub width: f32,
+    pub height: f32,
+    /// Degrees clockwise, not applied when drawing tile objects
+    pub rotation: f32,
+    pub visible: bool,
+    /// Set for tile objects, which get drawn along with the tile layers
+    pub tile: Option<LayerTile>,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerData {
+    Tiles(TileLayer),
+    Objects(Vec<MapObject>),
+}
+
+/// A tile or object layer. Group layers are flattened into the layers inside them, which get
+/// the group's offset, opacity, tint and visibility folded in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapLayer {
+    pub name: String,
+    pub class: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub tint: Color,
+    /// In pixels
+    pub offset: Vec2F,
+    pub properties: Properties,
+    pub data: LayerData,
+}
+
+/// A map made in the Tiled editor, loaded from `.tmx` or `.tmj`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tilemap {
+    pub orientation: Orientation,
+    /// In tiles
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub background_color: Option<Color>,
+    pub properties: Properties,
+    /// Sorted by `first_gid`
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<MapLayer>,
+}
+
+impl Tilemap {
+    /// Load a `.tmx` or `.tmj`/`.json` map along with its tilesets, external tilesets included.
+    /// Tileset images are added to `resource_manager` and removed again if loading fails.
+    pub fn open(
+        path: &Path,
+        resource_manager: &mut ResourceManager,
+    ) -> Result<Self, ResourceError> {
+        let mut loader = Loader {
+            resource_manager,
+            loaded: Vec::new(),
+        };
+        let result = loader.map(path);
+        if result.is_err() {
+            for handle in loader.loaded {
+                loader.resource_manager.delete_image(handle);
+            }
+        }
+        result
+    }
+    /// The tileset holding `gid` and the tile's id within it
+    pub fn tileset_for(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        let tileset = self
+            .tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.first_gid <= gid)?;
+        let id = gid - tileset.first_gid;
+        (id < tileset.tile_count).then_some((tileset, id))
+    }
+    pub fn layer(&self, name: &str) -> Option<&MapLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut MapLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+    /// Size of the whole map in world pixels
+    pub fn pixel_size(&self) -> (u32, u32) {
+        match self.orientation {
+            Orientation::Orthogonal => {
+                (self.width * self.tile_width, self.height * self.tile_height)
+            }
+            Orientation::Isometric => {
+                let tiles = self.width + self.height;
+                (tiles * self.tile_width / 2, tiles * self.tile_height / 2)
+            }
+        }
+    }
+    /// World pixel position of a tile coordinate, the top left corner of the cell on
+    /// orthogonal maps and the top corner of the diamond on isometric ones
+    pub fn tile_to_world(&self, tile: Vec2F) -> Vec2F {
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        match self.orientation {
+            Orientation::Orthogonal => Vec2F::new(tile.x * tile_width, tile.y * tile_height),
+            Orientation::Isometric => Vec2F::new(
+                (tile.x - tile.y + self.height as f32) * tile_width / 2.0,
+                (tile.x + tile.y) * tile_height / 2.0,
+            ),
+        }
+    }
+    /// Fractional tile coordinate under a world pixel position, floor it to get the cell
+    pub fn world_to_tile(&self, world: Vec2F) -> Vec2F {
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        match self.orientation {
+            Orientation::Orthogonal => Vec2F::new(world.x / tile_width, world.y / tile_height),
+            Orientation::Isometric => {
+                let across = world.x / (tile_width / 2.0) - self.height as f32;
+                let down = world.y / (tile_height / 2.0);
+                Vec2F::new((down + across) / 2.0, (down - across) / 2.0)
+            }
+        }
+    }
+    /// World pixel position of an object, which isometric maps store in projected tile units
+    pub fn object_position(&self, object: &MapObject) -> Vec2F {
+        match self.orientation {
+            Orientation::Orthogonal => object.position,
+            Orientation::Isometric => self.tile_to_world(object.position / self.tile_height as f32),
+        }
+    }
+    /// Draw every visible layer in order, with `camera` being the world position shown at the
+    /// top left of `dst`. `time` drives animated tiles.
+    pub fn draw(
+        &self,
+        resource_manager: &ResourceManager,
+        dst: &mut impl ImageResource,
+        camera: Vec2,
+        time: Duration,
+    ) {
+        for index in 0..self.layers.len() {
+            self.draw_layer(resource_manager, index, dst, camera, time);
+        }
+    }
+    /// Draw the layer at `index` if it is visible, skipping tiles outside the clip rect of `dst`
+    pub fn draw_layer(
+        &self,
+        resource_manager: &ResourceManager,
+        index: usize,
+        dst: &mut impl ImageResource,
+        camera: Vec2,
+        time: Duration,
+    ) {
+        let layer = match self.layers.get(index) {
+            Some(layer) if layer.visible && layer.opacity > 0.0 => layer,
+            _ => return,
+        };
+        let origin = Vec2F::from(camera) - layer.offset;
+        let style = TileStyle {
+            tint: layer.tint,
+            opacity: layer.opacity,
+            time,
+        };
+        match &layer.data {
+            LayerData::Tiles(tiles) => {
+                if tiles.width == 0 || tiles.height == 0 {
+                    return;
+                }
+                let (min, max) = self.visible_tiles(tiles, origin, dst);
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        let tile = match tiles.tile(x, y) {
+                            Some(tile) => tile,
+                            None => continue,
+                        };
+                        // tile images stand on the bottom left of their cell
+                        let bottom_left = match self.orientation {
+                            Orientation::Orthogonal => {
+                                self.tile_to_world(Vec2F::new(x as f32, y as f32 + 1.0))
+                            }
+                            Orientation::Isometric => {
+                                let top = self.tile_to_world(Vec2F::new(x as f32, y as f32));
+                                top + Vec2F::new(
+                                    -(self.tile_width as f32) / 2.0,
+                                    self.tile_height as f32,
+                                )
+                            }
+                        };
+                        let position = Vec2::from(bottom_left - origin);
+                        self.draw_tile(resource_manager, tile, None, dst, position, &style);
+                    }
+                }
+            }
+            LayerData::Objects(objects) => {
+                for object in objects.iter().filter(|object| object.visible) {
+                    let tile = match object.tile {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let mut bottom_left = self.object_position(object) - origin;
+                    if self.orientation == Orientation::Isometric {
+                        // isometric tile objects are anchored at their bottom centre
+                        bottom_left.x -= object.width / 2.0;
+                    }
+                    let size = Vec2F::new(object.width, object.height);
+                    let position =
+                        Vec2::new(bottom_left.x.round() as i32, bottom_left.y.round() as i32);
+                    self.draw_tile(resource_manager, tile, Some(size), dst, position, &style);
+                }
+            }
+        }
+    }
+    /// Inclusive range of cells that can show up on `dst`, padded for tiles bigger than a cell
+    fn visible_tiles(
+        &self,
+        tiles: &TileLayer,
+        origin: Vec2F,
+        dst: &impl ImageResource,
+    ) -> (Vec2, Vec2) {
+        let overhang = self
+            .tilesets
+            .iter()
+            .map(|tileset| {
+                tileset.tile_width.max(tileset.tile_height) as f32
+                    + tileset.tile_offset.x.abs().max(tileset.tile_offset.y.abs()) as f32
+            })
+            .fold(0.0, f32::max);
+        let clip = dst.clip_rect();
+        let (left, top) = (clip.left() as f32 - overhang, clip.top() as f32 - overhang);
+        let (right, bottom) = (
+            clip.right() as f32 + overhang,
+            clip.bottom() as f32 + overhang,
+        );
+        let corners = [
+            Vec2F::new(left, top),
+            Vec2F::new(right, top),
+            Vec2F::new(left, bottom),
+            Vec2F::new(right, bottom),
+        ]
+        .map(|corner| self.world_to_tile(corner + origin));
+        let min_x = corners.iter().map(|c| c.x).fold(f32::MAX, f32::min).floor();
+        let min_y = corners.iter().map(|c| c.y).fold(f32::MAX, f32::min).floor();
+        let max_x = corners.iter().map(|c| c.x).fold(f32::MIN, f32::max).ceil();
+        let max_y = corners.iter().map(|c| c.y).fold(f32::MIN, f32::max).ceil();
+        let clamp = |value: f32, size: u32| value.clamp(0.0, size as f32 - 1.0) as i32;
+        (
+            Vec2::new(clamp(min_x, tiles.width), clamp(min_y, tiles.height)),
+            Vec2::new(clamp(max_x, tiles.width), clamp(max_y, tiles.height)),
+        )
+    }
+    /// Draw `tile` standing on `bottom_left`, scaled to `size` when given
+    fn draw_tile(
+        &self,
+        resource_manager: &ResourceManager,
+        tile: LayerTile,
+        size: Option<Vec2F>,
+        dst: &mut impl ImageResource,
+        bottom_left: Vec2,
+        style: &TileStyle,
+    ) {
+        let (tileset, id) = match self.tileset_for(tile.gid) {
+            Some(found) => found,
+            None => return,
+        };
+        let image = match resource_manager.get_image(tileset.image) {
+            Some(image) => image,
+            None => return,
+        };
+        let src_rect = tileset.tile_rect(tileset.animated_tile(id, style.time));
+        let (width, height) = (src_rect.width as f32, src_rect.height as f32);
+        // size on screen, with width and height swapped by the diagonal flag
+        let (out_width, out_height) = if tile.diagonal {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let scale = size.map_or(Vec2F::new(1.0, 1.0), |size| {
+            Vec2F::new(size.x / out_width, size.y / out_height)
+        });
+        let top_left =
+            bottom_left + tileset.tile_offset - Vec2::new(0, (out_height * scale.y).round() as i32);
+        let unchanged = tile.flip == Flip::default() && scale == Vec2F::new(1.0, 1.0);
+        if unchanged && !tile.diagonal {
+            blit_rect_tinted(
+                image,
+                src_rect,
+                dst,
+                top_left,
+                style.tint,
+                style.opacity,
+                BlendMode::Alpha,
+            );
+            return;
+        }
+        let transform = Transform {
+            tint: style.tint,
+            opacity: style.opacity,
+            ..Transform::default()
+        };
+        let transform = if tile.diagonal {
+            // transposing and then flipping is the same as flipping the other way round and
+            // turning a quarter clockwise
+            Transform {
+                rotation: FRAC_PI_2,
+                scale: Vec2F::new(scale.y, scale.x),
+                flip: Flip {
+                    horizontal: tile.flip.vertical,
+                    vertical: !tile.flip.horizontal,
+                },
+                ..transform
+            }
+        } else {
+            Transform {
+                scale,
+                flip: tile.flip,
+                ..transform
+            }
+        };
+        let position = if tile.diagonal {
+            // the quarter turn around the top left corner swings the tile to its left
+            top_left + Vec2::new((out_width * scale.x).round() as i32, 0)
+        } else {
+            top_left
+        };
+        blit_transformed(image, src_rect, dst, position, &transform);
+    }
+}
+
+struct TileStyle {
+    tint: Color,
+    opacity: f32,
+    time: Duration,
+}
+
+/// Tracks the images added while loading, so they can be removed again on failure
+struct Loader<'a> {
+    resource_manager: &'a mut ResourceManager,
+    loaded: Vec<ImageHandle>,
+}
+
+fn parse_error(path: &Path, reason: impl Into<String>) -> ResourceError {
+    ResourceError::Parse {
+        path: path.to_path_buf(),
+        reason: reason.into(),
+    }
+}
+
+fn read_text(path: &Path) -> Result<String, ResourceError> {
+    read_to_string(path).map_err(|source| ResourceError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn is_json(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("tmj" | "tsj" | "json")
+    )
+}
+
+impl<'a> Loader<'a> {
+    fn map(&mut self, path: &Path) -> Result<Tilemap, ResourceError> {
+        let text = read_text(path)?;
+        let mut map = if is_json(path) {
+            let root = serde_json::from_str::<Value>(&text)
+                .map_err(|error| parse_error(path, error.to_string()))?;
+            self.json_map(path, &root)?
+        } else {
+            let root = parse_xml(&text).map_err(|reason| parse_error(path, reason))?;
+            self.tmx_map(path, &root)?
+        };
+        map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+        Ok(map)
+    }
+    fn image(
+        &mut self,
+        path: &Path,
+        transparent: Option<Color>,
+    ) -> Result<(ImageHandle, u32, u32), ResourceError> {
+        let mut image = Image::open(path)?;
+        if let Some(key) = transparent {
+            for pixel in image.buf.chunks_exact_mut(4) {
+                if pixel[..3] == [key.r, key.g, key.b] {
+                    pixel[3] = 0;
+                }
+            }
+        }
+        let (width, height) = (image.width(), image.height());
+        let handle = self.resource_manager.add_image(image);
+        self.loaded.push(handle);
+        Ok((handle, width, height))
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn tileset(
+        &mut self,
+        path: &Path,
+        first_gid: u32,
+        name: String,
+        tile_width: u32,
+        tile_height: u32,
+        spacing: u32,
+        margin: u32,
+        image: Option<(&str, Option<Color>)>,
+    ) -> Result<Tileset, ResourceError> {
+        let (image, transparent) = image
+            .ok_or_else(|| parse_error(path, "image collection tilesets are not supported"))?;
+        if tile_width == 0 || tile_height == 0 {
+            return Err(parse_error(path, "tileset has no tile size"));
+        }
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let (handle, width, height) = self.image(&directory.join(image), transparent)?;
+        let count =
+            |size: u32, tile: u32| (size.saturating_sub(margin * 2) + spacing) / (tile + spacing);
+        let columns = count(width, tile_width);
+        Ok(Tileset {
+            first_gid,
+            name,
+            image: handle,
+            tile_width,
+            tile_height,
+            spacing,
+            margin,
+            columns,
+            tile_count: columns * count(height, tile_height),
+            tile_offset: Vec2::new(0, 0),
+            properties: Properties::new(),
+            tiles: HashMap::new(),
+        })
+    }
+
+    fn tmx_map(&mut self, path: &Path, root: &XmlElement) -> Result<Tilemap, ResourceError> {
+        let error = |reason: &str| parse_error(path, reason);
+        if root.name != "map" {
+            return Err(error("root element is not a map"));
+        }
+        if root.attribute("infinite") == Some("1") {
+            return Err(error("infinite maps are not supported"));
+        }
+        let mut map = Tilemap {
+            orientation: orientation(root.attribute("orientation")).map_err(error)?,
+            width: root.number("width").map_err(error)?,
+            height: root.number("height").map_err(error)?,
+            tile_width: root.number("tilewidth").map_err(error)?,
+            tile_height: root.number("tileheight").map_err(error)?,
+            background_color: root
+                .attribute("backgroundcolor")
+                .map(parse_color)
+                .transpose()
+                .map_err(error)?,
+            properties: tmx_properties(root).map_err(error)?,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+        };
+        for child in &root.children {
+            if child.name == "tileset" {
+                let first_gid = child.number("firstgid").map_err(error)?;
+                let tileset = match child.attribute("source") {
+                    Some(source) => {
+                        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+                        self.external_tileset(&directory.join(source), first_gid)?
+                    }
+                    None => self.tmx_tileset(path, child, first_gid)?,
+                };
+                map.tilesets.push(tileset);
+            }
+        }
+        tmx_layers(root, &LayerCommon::root(), &mut map.layers).map_err(error)?;
+        Ok(map)
+    }
+    fn external_tileset(&mut self, path: &Path, first_gid: u32) -> Result<Tileset, ResourceError> {
+        let text = read_text(path)?;
+        if is_json(path) {
+            let root = serde_json::from_str::<Value>(&text)
+                .map_err(|error| parse_error(path, error.to_string()))?;
+            self.json_tileset(path, &root, first_gid)
+        } else {
+            let root = parse_xml(&text).map_err(|reason| parse_error(path, reason))?;
+            self.tmx_tileset(path, &root, first_gid)
+        }
+    }
+    /// `path` is the file the tileset is in, its image is relative to that
+    fn tmx_tileset(
+        &mut self,
+        path: &Path,
+        element: &XmlElement,
+        first_gid: u32,
+    ) -> Result<Tileset, ResourceError> {
+        let error = |reason: &str| parse_error(path, reason);
+        let image = element.child("image");
+        let transparent = image
+            .and_then(|image| image.attribute("trans"))
+            .map(parse_color)
+            .transpose()
+            .map_err(error)?;
+        let source = image
+            .map(|image| image.attribute("source").ok_or("image without a source"))
+            .transpose()
+            .map_err(error)?;
+        let mut tileset = self.tileset(
+            path,
+            first_gid,
+            element.attribute("name").unwrap_or_default().to_owned(),
+            element.number("tilewidth").map_err(error)?,
+            element.number("tileheight").map_err(error)?,
+            element.number_or("spacing", 0).map_err(error)?,
+            element.number_or("margin", 0).map_err(error)?,
+            source.map(|source| (source, transparent)),
+        )?;
+        if let Some(offset) = element.child("tileoffset") {
+            tileset.tile_offset = Vec2::new(
+                offset.number_or("x", 0).map_err(error)?,
+                offset.number_or("y", 0).map_err(error)?,
+            );
+        }
+        tileset.properties = tmx_properties(element).map_err(error)?;
+        for tile in element.children.iter().filter(|child| child.name == "tile") {
+            let id = tile.number("id").map_err(error)?;
+            let mut animation = Vec::new();
+            if let Some(frames) = tile.child("animation") {
+                for frame in &frames.children {
+                    animation.push(TileAnimationFrame {
+                        tile_id: frame.number("tileid").map_err(error)?,
+                        duration: Duration::from_millis(frame.number("duration").map_err(error)?),
+                    });
+                }
+            }
+            let class = tile.attribute("class").or_else(|| tile.attribute("type"));
+            tileset.tiles.insert(
+                id,
+                TileData {
+                    class: class.unwrap_or_default().to_owned(),
+                    properties: tmx_properties(tile).map_err(error)?,
+                    animation,
+                },
+            );
+        }
+        Ok(tileset)
+    }
+
+    fn json_map(&mut self, path: &Path, root: &Value) -> Result<Tilemap, ResourceError> {
+        let error = |reason: &str| parse_error(path, reason);
+        if root.get("infinite").and_then(Value::as_bool) == Some(true) {
+            return Err(error("infinite maps are not supported"));
+        }
+        let mut map = Tilemap {
+            orientation: orientation(root.get("orientation").and_then(Value::as_str))
+                .map_err(error)?,
+            width: json_number(root, "width").map_err(error)?,
+            height: json_number(root, "height").map_err(error)?,
+            tile_width: json_number(root, "tilewidth").map_err(error)?,
+            tile_height: json_number(root, "tileheight").map_err(error)?,
+            background_color: root
+                .get("backgroundcolor")
+                .and_then(Value::as_str)
+                .map(parse_color)
+                .transpose()
+                .map_err(error)?,
+            properties: json_properties(root).map_err(error)?,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+        };
+        for tileset in root
+            .get("tilesets")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            let first_gid = json_number(tileset, "firstgid").map_err(error)?;
+            let tileset = match tileset.get("source").and_then(Value::as_str) {
+                Some(source) => {
+                    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+                    self.external_tileset(&directory.join(source), first_gid)?
+                }
+                None => self.json_tileset(path, tileset, first_gid)?,
+            };
+            map.tilesets.push(tileset);
+        }
+        json_layers(root, &LayerCommon::root(), &mut map.layers).map_err(error)?;
+        Ok(map)
+    }
+    fn json_tileset(
+        &mut self,
+        path: &Path,
+        value: &Value,
+        first_gid: u32,
+    ) -> Result<Tileset, ResourceError> {
+        let error = |reason: &str| parse_error(path, reason);
+        let transparent = value
+            .get("transparentcolor")
+            .and_then(Value::as_str)
+            .map(parse_color)
+            .transpose()
+            .map_err(error)?;
+        let image = value.get("image").and_then(Value::as_str);
+        let mut tileset = self.tileset(
+            path,
+            first_gid,
+            value
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            json_number(value, "tilewidth").map_err(error)?,
+            json_number(value, "tileheight").map_err(error)?,
+            json_number_or(value, "spacing", 0).map_err(error)?,
+            json_number_or(value, "margin", 0).map_err(error)?,
+            image.map(|image| (image, transparent)),
+        )?;
+        if let Some(offset) = value.get("tileoffset") {
+            tileset.tile_offset = Vec2::new(
+                json_number_or(offset, "x", 0).map_err(error)?,
+                json_number_or(offset, "y", 0).map_err(error)?,
+            );
+        }
+        tileset.properties = json_properties(value).map_err(error)?;
+        for tile in value
+            .get("tiles")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+        {
+            let mut animation = Vec::new();
+            for frame in tile
+                .get("animation")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+            {
+                animation.push(TileAnimationFrame {
+                    tile_id: json_number(frame, "tileid").map_err(error)?,
+                    duration: Duration::from_millis(json_number(frame, "duration").map_err(error)?),
+                });
+            }
+            let class = tile
+                .get("class")
+                .or_else(|| tile.get("type"))
+                .and_then(Value::as_str);
+            tileset.tiles.insert(
+                json_number(tile, "id").map_err(error)?,
+                TileData {
+                    class: class.unwrap_or_default().to_owned(),
+                    properties: json_properties(tile).map_err(error)?,
+                    animation,
+                },
+            );
+        }
+        Ok(tileset)
+    }
+}
+
+fn orientation(name: Option<&str>) -> Result<Orientation, &'static str> {
+    match name {
+        None | Some("orthogonal") => Ok(Orientation::Orthogonal),
+        Some("isometric") => Ok(Orientation::Isometric),
+        Some(_) => Err("only orthogonal and isometric maps are supported"),
+    }
+}
+
+/// Tiled writes colors as `#RRGGBB` or `#AARRGGBB`
+fn parse_color(text: &str) -> Result<Color, &'static str> {
+    let hex = text.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).map_err(|_| "invalid color")?;
+    match hex.len() {
+        6 => Ok(Color::new(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+            255,
+        )),
+        8 => Ok(Color::new(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+            (value >> 24) as u8,
+        )),
+        _ => Err("invalid color"),
+    }
+}
+
+fn property_value(kind: &str, text: &str) -> Result<PropertyValue, &'static str> {
+    Ok(match kind {
+        "bool" => PropertyValue::Bool(text == "true"),
+        "int" => PropertyValue::Int(text.parse().map_err(|_| "invalid int property")?),
+        "float" => PropertyValue::Float(text.parse().map_err(|_| "invalid float property")?),
+        "color" if text.is_empty() => PropertyValue::Color(Color::new(0, 0, 0, 0)),
+        "color" => PropertyValue::Color(parse_color(text)?),
+        "file" => PropertyValue::File(text.to_owned()),
+        "object" => PropertyValue::Object(text.parse().map_err(|_| "invalid object property")?),
+        _ => PropertyValue::String(text.to_owned()),
+    })
+}
+
+/// Which of a group's attributes are passed down to the layers inside it
+#[derive(Clone)]
+struct LayerCommon {
+    visible: bool,
+    opacity: f32,
+    tint: Color,
+    offset: Vec2F,
+}
+
+impl LayerCommon {
+    fn root() -> Self {
+        Self {
+            visible: true,
+            opacity: 1.0,
+            tint: WHITE,
+            offset: Vec2F::new(0.0, 0.0),
+        }
+    }
+    fn child(&self, visible: bool, opacity: f32, tint: Color, offset: Vec2F) -> Self {
+        let channel = |a: u8, b: u8| (a as u32 * b as u32 / 255) as u8;
+        Self {
+            visible: self.visible && visible,
+            opacity: self.opacity * opacity,
+            tint: Color::new(
+                channel(self.tint.r, tint.r),
+                channel(self.tint.g, tint.g),
+                channel(self.tint.b, tint.b),
+                channel(self.tint.a, tint.a),
+            ),
+            offset: self.offset + offset,
+        }
+    }
+}
+
+fn check_gids(tiles: &[u32], width: u32, height: u32) -> Result<TileLayer, &'static str> {
+    if tiles.len() != width as usize * height as usize {
+        return Err("tile layer data does not match its size");
+    }
+    Ok(TileLayer {
+        width,
+        height,
+        tiles: tiles.to_vec(),
+    })
+}
+
+/// Decode base64 or csv layer data, decompressing base64 data first if needed
+fn decode_tiles(
+    encoding: Option<&str>,
+    compression: Option<&str>,
+    text: &str,
+) -> Result<Vec<u32>, &'static str> {
+    match encoding {
+        Some("csv") => text
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse().map_err(|_| "invalid gid"))
+            .collect(),
+        Some("base64") => {
+            let bytes = decode_base64(text)?;
+            let bytes = match compression {
+                None | Some("") => bytes,
+                Some("zlib") => decompress(ZlibDecoder::new(&bytes[..]))?,
+                Some("gzip") => decompress(GzDecoder::new(&bytes[..]))?,
+                Some(_) => return Err("unsupported layer compression"),
+            };
+            if bytes.len() % 4 != 0 {
+                return Err("layer data is not a whole number of gids");
+            }
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        _ => Err("unsupported layer encoding"),
+    }
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, &'static str> {
+    let text: String = text.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    STANDARD.decode(text).map_err(|_| "invalid base64")
+}
+
+fn decompress(mut decoder: impl Read) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = Vec::new();
+    decoder
+        .read_to_end(&mut bytes)
+        .map_err(|_| "could not decompress layer data")?;
+    Ok(bytes)
+}
+
+/// A parsed XML element, enough of a tree for map files
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlElement>,
+    text: String,
+}
+
+impl XmlElement {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<T, &'static str> {
+        self.attribute(name)
+            .ok_or("missing attribute")?
+            .parse()
+            .map_err(|_| "invalid number attribute")
+    }
+    fn number_or<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, &'static str> {
+        match self.attribute(name) {
+            Some(_) => self.number(name),
+            None => Ok(default),
+        }
+    }
+}
+
+fn parse_xml(text: &str) -> Result<XmlElement, String> {
+    let mut stack: Vec<XmlElement> = Vec::new();
+    for event in EventReader::from_str(text) {
+        match event.map_err(|error| error.to_string())? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => stack.push(XmlElement {
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+                ..XmlElement::default()
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().ok_or("unbalanced element")?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err("no root element".to_owned())
+}
+
+fn tmx_properties(element: &XmlElement) -> Result<Properties, &'static str> {
+    let mut properties = Properties::new();
+    let list = match element.child("properties") {
+        Some(list) => list,
+        None => return Ok(properties),
+    };
+    for property in &list.children {
+        let name = property
+            .attribute("name")
+            .ok_or("property without a name")?;
+        let kind = property.attribute("type").unwrap_or("string");
+        let value = if kind == "class" {
+            PropertyValue::Class(tmx_properties(property)?)
+        } else {
+            // multi-line strings are stored as text instead of an attribute
+            let text = property.attribute("value").unwrap_or(&property.text);
+            property_value(kind, text)?
+        };
+        properties.insert(name.to_owned(), value);
+    }
+    Ok(properties)
+}
+
+fn tmx_layer_common(
+    element: &XmlElement,
+    parent: &LayerCommon,
+) -> Result<LayerCommon, &'static str> {
+    let tint = match element.attribute("tintcolor") {
+        Some(tint) => parse_color(tint)?,
+        None => WHITE,
+    };
+    Ok(parent.child(
+        element.attribute("visible") != Some("0"),
+        element.number_or("opacity", 1.0)?,
+        tint,
+        Vec2F::new(
+            element.number_or("offsetx", 0.0)?,
+            element.number_or("offsety", 0.0)?,
+        ),
+    ))
+}
+
+fn tmx_layers(
+    element: &XmlElement,
+    parent: &LayerCommon,
+    layers: &mut Vec<MapLayer>,
+) -> Result<(), &'static str> {
+    for child in &element.children {
+        let common = match child.name.as_str() {
+            "layer" | "objectgroup" | "group" => tmx_layer_common(child, parent)?,
+            _ => continue,
+        };
+        let data = match child.name.as_str() {
+            "group" => {
+                tmx_layers(child, &common, layers)?;
+                continue;
+            }
+            "layer" => {
+                let (width, height) = (child.number("width")?, child.number("height")?);
+                let data = child.child("data").ok_or("tile layer without data")?;
+                let gids = match data.attribute("encoding") {
+                    None => data
+                        .children
+                        .iter()
+                        .filter(|tile| tile.name == "tile")
+                        .map(|tile| tile.number_or("gid", 0))
+                        .collect::<Result<Vec<u32>, _>>()?,
+                    encoding => decode_tiles(encoding, data.attribute("compression"), &data.text)?,
+                };
+                LayerData::Tiles(check_gids(&gids, width, height)?)
+            }
+            _ => LayerData::Objects(
+                child
+                    .children
+                    .iter()
+                    .filter(|object| object.name == "object")
+                    .map(tmx_object)
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        layers.push(MapLayer {
+            name: child.attribute("name").unwrap_or_default().to_owned(),
+            class: child.attribute("class").unwrap_or_default().to_owned(),
+            visible: common.visible,
+            opacity: common.opacity,
+            tint: common.tint,
+            offset: common.offset,
+            properties: tmx_properties(child)?,
+            data,
+        });
+    }
+    Ok(())
+}
+
+fn parse_points(text: &str) -> Result<Vec<Vec2F>, &'static str> {
+    text.split_whitespace()
+        .map(|point| {
+            let (x, y) = point.split_once(',').ok_or("invalid point")?;
+            Ok(Vec2F::new(
+                x.parse().map_err(|_| "invalid point")?,
+                y.parse().map_err(|_| "invalid point")?,
+            ))
+        })
+        .collect()
+}
+
+fn tmx_object(element: &XmlElement) -> Result<MapObject, &'static str> {
+    let shape = if element.child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if element.child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = element.child("polygon") {
+        ObjectShape::Polygon(parse_points(
+            polygon.attribute("points").unwrap_or_default(),
+        )?)
+    } else if let Some(polyline) = element.child("polyline") {
+        ObjectShape::Polyline(parse_points(
+            polyline.attribute("points").unwrap_or_default(),
+        )?)
+    } else {
+        ObjectShape::Rectangle
+    };
+    let class = element
+        .attribute("class")
+        .or_else(|| element.attribute("type"));
+    Ok(MapObject {
+        id: element.number_or("id", 0)?,
+        name: element.attribute("name").unwrap_or_default().to_owned(),
+        class: class.unwrap_or_default().to_owned(),
+        position: Vec2F::new(element.number_or("x", 0.0)?, element.number_or("y", 0.0)?),
+        width: element.number_or("width", 0.0)?,
+        height: element.number_or("height", 0.0)?,
+        rotation: element.number_or("rotation", 0.0)?,
+        visible: element.attribute("visible") != Some("0"),
+        tile: LayerTile::from_raw(element.number_or("gid", 0)?),
+        shape,
+        properties: tmx_properties(element)?,
+    })
+}
+
+fn json_number<T: TryFrom<i64>>(value: &Value, key: &str) -> Result<T, &'static str> {
+    value
+        .get(key)
+        .ok_or("missing number")?
+        .as_i64()
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or("invalid number")
+}
+
+fn json_number_or<T: TryFrom<i64>>(
+    value: &Value,
+    key: &str,
+    default: T,
+) -> Result<T, &'static str> {
+    match value.get(key) {
+        Some(_) => json_number(value, key),
+        None => Ok(default),
+    }
+}
+
+fn json_float(value: &Value, key: &str, default: f32) -> Result<f32, &'static str> {
+    match value.get(key) {
+        Some(number) => number
+            .as_f64()
+            .map(|number| number as f32)
+            .ok_or("invalid number"),
+        None => Ok(default),
+    }
+}
+
+fn json_str<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+/// Class members in `.tmj` files carry no type, so it is guessed from the JSON value
+fn json_untyped_value(value: &Value) -> PropertyValue {
+    match value {
+        Value::Bool(b) => PropertyValue::Bool(*b),
+        Value::Number(_) => match value.as_i64() {
+            Some(int) => PropertyValue::Int(int),
+            None => PropertyValue::Float(value.as_f64().unwrap_or_default()),
+        },
+        Value::Object(members) => PropertyValue::Class(
+            members
+                .iter()
+                .map(|(name, value)| (name.clone(), json_untyped_value(value)))
+                .collect(),
+        ),
+        Value::String(text) => PropertyValue::String(text.clone()),
+        Value::Null | Value::Array(_) => PropertyValue::String(String::new()),
+    }
+}
+
+fn json_properties(value: &Value) -> Result<Properties, &'static str> {
+    let mut properties = Properties::new();
+    for property in value
+        .get("properties")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        let name = property
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or("property without a name")?;
+        let kind = property
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("string");
+        let raw = property.get("value").unwrap_or(&Value::Null);
+        let value = match (kind, raw) {
+            ("class", raw) => json_untyped_value(raw),
+            (_, Value::String(text)) => property_value(kind, text)?,
+            (_, raw) => property_value(kind, &raw.to_string())?,
+        };
+        properties.insert(name.to_owned(), value);
+    }
+    Ok(properties)
+}
+
+fn json_layers(
+    value: &Value,
+    parent: &LayerCommon,
+    layers: &mut Vec<MapLayer>,
+) -> Result<(), &'static str> {
+    for layer in value
+        .get("layers")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+    {
+        let kind = json_str(layer, "type");
+        if !matches!(kind, "tilelayer" | "objectgroup" | "group") {
+            continue;
+        }
+        let tint = match layer.get("tintcolor").and_then(Value::as_str) {
+            Some(tint) => parse_color(tint)?,
+            None => WHITE,
+        };
+        let common = parent.child(
+            layer.get("visible").and_then(Value::as_bool) != Some(false),
+            json_float(layer, "opacity", 1.0)?,
+            tint,
+            Vec2F::new(
+                json_float(layer, "offsetx", 0.0)?,
+                json_float(layer, "offsety", 0.0)?,
+            ),
+        );
+        let data = match kind {
+            "group" => {
+                json_layers(layer, &common, layers)?;
+                continue;
+            }
+            "tilelayer" => {
+                let (width, height) = (json_number(layer, "width")?, json_number(layer, "height")?);
+                let gids = match layer.get("data").ok_or("tile layer without data")? {
+                    Value::Array(gids) => gids
+                        .iter()
+                        .map(|gid| {
+                            gid.as_i64()
+                                .and_then(|gid| u32::try_from(gid).ok())
+                                .ok_or("invalid gid")
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                    Value::String(text) => decode_tiles(
+                        layer.get("encoding").and_then(Value::as_str),
+                        layer.get("compression").and_then(Value::as_str),
+                        text,
+                    )?,
+                    _ => return Err("invalid tile layer data"),
+                };
+                LayerData::Tiles(check_gids(&gids, width, height)?)
+            }
+            _ => LayerData::Objects(
+                layer
+                    .get("objects")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(json_object)
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        layers.push(MapLayer {
+            name: json_str(layer, "name").to_owned(),
+            class: json_str(layer, "class").to_owned(),
+            visible: common.visible,
+            opacity: common.opacity,
+            tint: common.tint,
+            offset: common.offset,
+            properties: json_properties(layer)?,
+            data,
+        });
+    }
+    Ok(())
+}
+
+fn json_points(points: &Value) -> Result<Vec<Vec2F>, &'static str> {
+    points
+        .as_array()
+        .ok_or("invalid points")?
+        .iter()
+        .map(|point| {
+            Ok(Vec2F::new(
+                json_float(point, "x", 0.0)?,
+                json_float(point, "y", 0.0)?,
+            ))
+        })
+        .collect()
+}
+
+fn json_object(object: &Value) -> Result<MapObject, &'static str> {
+    let flag = |key: &str| object.get(key).and_then(Value::as_bool) == Some(true);
+    let shape = if flag("ellipse") {
+        ObjectShape::Ellipse
+    } else if flag("point") {
+        ObjectShape::Point
+    } else if let Some(polygon) = object.get("polygon") {
+        ObjectShape::Polygon(json_points(polygon)?)
+    } else if let Some(polyline) = object.get("polyline") {
+        ObjectShape::Polyline(json_points(polyline)?)
+    } else {
+        ObjectShape::Rectangle
+    };
+    let class = match json_str(object, "class") {
+        "" => json_str(object, "type"),
+        class => class,
+    };
+    Ok(MapObject {
+        id: json_number_or(object, "id", 0)?,
+        name: json_str(object, "name").to_owned(),
+        class: class.to_owned(),
+        position: Vec2F::new(json_float(object, "x", 0.0)?, json_float(object, "y", 0.0)?),
+        width: json_float(object, "width", 0.0)?,
+        height: json_float(object, "height", 0.0)?,
+        rotation: json_float(object, "rotation", 0.0)?,
+        visible: object.get("visible").and_then(Value::as_bool) != Some(false),
+        tile: LayerTile::from_raw(json_number_or(object, "gid", 0)?),
+        shape,
+        properties: json_properties(object)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * image.width() + x) * 4) as usize;
+        [
+            image.buf[i],
+            image.buf[i + 1],
+            image.buf[i + 2],
+            image.buf[i + 3],
+        ]
+    }
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    #[test]
+    fn test_tmx_and_tmj_match() {
+        let mut resource_manager = ResourceManager::new();
+        let tmx = Tilemap::open(
+            Path::new("resources/maps/orthogonal.tmx"),
+            &mut resource_manager,
+        )
+        .unwrap();
+        let mut tmj = Tilemap::open(
+            Path::new("resources/maps/orthogonal.tmj"),
+            &mut resource_manager,
+        )
+        .unwrap();
+        assert_ne!(tmx.tilesets[0].image, tmj.tilesets[0].image);
+        tmj.tilesets[0].image = tmx.tilesets[0].image;
+        assert_eq!(tmx, tmj);
+    }
+
+    #[test]
+    fn test_map_contents() {
+        let mut resource_manager = ResourceManager::new();
+        let map = Tilemap::open(
+            Path::new("resources/maps/orthogonal.tmx"),
+            &mut resource_manager,
+        )
+        .unwrap();
+        assert_eq!((map.width, map.height, map.pixel_size()), (8, 6, (128, 96)));
+        assert_eq!(
+            map.background_color,
+            Some(Color::new(0x20, 0x20, 0x40, 255))
+        );
+        assert_eq!(
+            map.properties["tint"],
+            PropertyValue::Color(Color::new(255, 0, 0, 128))
+        );
+        assert_eq!(
+            map.properties["music"],
+            PropertyValue::File("music.ogg".to_owned())
+        );
+        let spawn = Properties::from([("count".to_owned(), PropertyValue::Int(3))]);
+        assert_eq!(map.properties["spawn"], PropertyValue::Class(spawn));
+
+        let tileset = &map.tilesets[0];
+        assert_eq!((tileset.columns, tileset.tile_count), (4, 8));
+        assert_eq!(tileset.tile_rect(5), Rect::new(Vec2::new(18, 18), 16, 16));
+        assert_eq!(tileset.tile(1).unwrap().class, "wall");
+        assert_eq!(
+            tileset.tile(1).unwrap().properties["solid"],
+            PropertyValue::Bool(true)
+        );
+        let frames = [0, 250, 450].map(|ms| tileset.animated_tile(3, Duration::from_millis(ms)));
+        assert_eq!(frames, [3, 4, 3]);
+        assert_eq!(map.tileset_for(8).map(|(_, id)| id), Some(7));
+        assert!(map.tileset_for(9).is_none());
+        assert!(map.tileset_for(0).is_none());
+
+        let ground = match &map.layer("ground").unwrap().data {
+            LayerData::Tiles(tiles) => tiles,
+            _ => panic!("ground is not a tile layer"),
+        };
+        assert_eq!(ground.tile(0, 0), Some(LayerTile::new(7)));
+        assert_eq!(ground.tile(8, 0), None);
+        let decor = map.layer("decor").unwrap();
+        assert_eq!(decor.opacity, 0.5);
+        let decor = match &decor.data {
+            LayerData::Tiles(tiles) => tiles,
+            _ => panic!("decor is not a tile layer"),
+        };
+        assert_eq!(decor.tile(0, 0), None);
+        let flipped = decor.tile(5, 2).unwrap();
+        assert!(flipped.diagonal && flipped.flip.horizontal && flipped.flip.vertical);
+        assert_eq!(LayerTile::from_raw(flipped.to_raw()), Some(flipped));
+
+        // the group's offset is folded into the layer inside it
+        let objects = map.layer("objects").unwrap();
+        assert_eq!(objects.offset, Vec2F::new(5.0, 3.0));
+        let objects = match &objects.data {
+            LayerData::Objects(objects) => objects,
+            _ => panic!("objects is not an object layer"),
+        };
+        assert_eq!(objects[0].shape, ObjectShape::Point);
+        assert_eq!(objects[0].class, "marker");
+        assert_eq!(objects[1].properties["target"], PropertyValue::Object(1));
+        assert_eq!(objects[1].rotation, 45.0);
+        assert!(matches!(&objects[2].shape, ObjectShape::Polyline(points) if points.len() == 3));
+        let crate_tile = objects[3].tile.unwrap();
+        assert_eq!((crate_tile.gid, crate_tile.flip.horizontal), (2, true));
+    }
+
+    #[test]
+    fn test_draw_orthogonal() {
+        let mut resource_manager = ResourceManager::new();
+        let mut map = Tilemap::open(
+            Path::new("resources/maps/orthogonal.tmx"),
+            &mut resource_manager,
+        )
+        .unwrap();
+        // ground, scrolled half a tile, with the animated tile at (1, 1)
+        let mut dst = Image::blank(64, 48);
+        map.draw_layer(
+            &resource_manager,
+            0,
+            &mut dst,
+            Vec2::new(8, 0),
+            Duration::ZERO,
+        );
+        assert_eq!(pixel(&dst, 0, 10), [140, 140, 140, 255]);
+        assert_eq!(pixel(&dst, 16, 26), [220, 200, 60, 255]);
+        let later = Duration::from_millis(250);
+        map.draw_layer(&resource_manager, 0, &mut dst, Vec2::new(8, 0), later);
+        assert_eq!(pixel(&dst, 16, 26), [200, 60, 200, 255]);
+
+        // white tiles with a black 4x2 marker in their top left, flipped every which way
+        map.layers[1].opacity = 1.0;
+        let mut dst = Image::blank(144, 96);
+        map.draw_layer(
+            &resource_manager,
+            1,
+            &mut dst,
+            Vec2::new(0, 0),
+            Duration::ZERO,
+        );
+        assert_eq!(pixel(&dst, 32, 32), [255, 255, 255, 255]);
+        assert_eq!(pixel(&dst, 47, 33), BLACK);
+        assert_eq!(pixel(&dst, 48, 47), BLACK);
+        // diagonal alone transposes the marker to 2x4
+        assert_eq!(pixel(&dst, 65, 35), BLACK);
+        assert_eq!(pixel(&dst, 66, 32), [255, 255, 255, 255]);
+        assert_eq!(pixel(&dst, 95, 44), BLACK);
+        assert_eq!(pixel(&dst, 93, 47), [255, 255, 255, 255]);
+
+        // the crate is scaled up to its object size and mirrored
+        let mut dst = Image::blank(144, 96);
+        map.draw_layer(
+            &resource_manager,
+            2,
+            &mut dst,
+            Vec2::new(0, 0),
+            Duration::ZERO,
+        );
+        assert_eq!(pixel(&dst, 103, 60), [60, 200, 60, 255]);
+        assert_eq!(pixel(&dst, 130, 54), BLACK);
+        assert_eq!(pixel(&dst, 100, 60), [0, 0, 0, 0]);
+
+        // nothing on screen, nothing drawn
+        let mut dst = Image::blank(32, 32);
+        for camera in [Vec2::new(10_000, 0), Vec2::new(-500, -500)] {
+            map.draw(&resource_manager, &mut dst, camera, Duration::ZERO);
+        }
+        // empty layers are valid in .tmx files
+        for (width, height) in [(0, 0), (0, 3), (3, 0)] {
+            map.layers[0].data = LayerData::Tiles(TileLayer::new(width, height));
+            map.draw_layer(
+                &resource_manager,
+                0,
+                &mut dst,
+                Vec2::new(0, 0),
+                Duration::ZERO,
+            );
+        }
+        assert!(dst.buf.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_isometric() {
+        let mut resource_manager = ResourceManager::new();
+        let map = Tilemap::open(
+            Path::new("resources/maps/isometric.tmx"),
+            &mut resource_manager,
+        )
+        .unwrap();
+        assert_eq!(map.orientation, Orientation::Isometric);
+        assert_eq!(map.pixel_size(), (192, 96));
+        assert_eq!(
+            map.tile_to_world(Vec2F::new(0.0, 0.0)),
+            Vec2F::new(96.0, 0.0)
+        );
+        let tile = Vec2F::new(2.5, 1.25);
+        assert_eq!(map.world_to_tile(map.tile_to_world(tile)), tile);
+
+        let mut dst = Image::blank(192, 96);
+        map.draw_layer(
+            &resource_manager,
+            0,
+            &mut dst,
+            Vec2::new(0, 0),
+            Duration::ZERO,
+        );
+        assert_eq!(pixel(&dst, 96, 8), [90, 160, 90, 255]);
+        assert_eq!(pixel(&dst, 112, 50), [90, 90, 180, 255]);
+        assert_eq!(pixel(&dst, 112, 30), [200, 200, 220, 255]);
+        assert_eq!(pixel(&dst, 2, 2), [0, 0, 0, 0]);
+        // the tile object stands on tile (1, 1), drawn bottom centred
+        map.draw_layer(
+            &resource_manager,
+            1,
+            &mut dst,
+            Vec2::new(0, 0),
+            Duration::ZERO,
+        );
+        assert_eq!(pixel(&dst, 96, 2), [200, 200, 220, 255]);
+        assert_eq!(pixel(&dst, 96, 8), [90, 90, 180, 255]);
+    }
+
+    #[test]
+    fn test_bad_maps() {
+        let mut resource_manager = ResourceManager::new();
+        let result = Tilemap::open(Path::new("resources/maps/nope.tmx"), &mut resource_manager);
+        assert!(matches!(result, Err(ResourceError::Io { .. })));
+        let result = Tilemap::open(Path::new("resources/maps/tiles.tsx"), &mut resource_manager);
+        assert!(matches!(result, Err(ResourceError::Parse { .. })));
+        assert!(decode_tiles(Some("base64"), Some("zstd"), "AAAA").is_err());
+        assert_eq!(decode_base64(" AQID\n BA== "), Ok(vec![1, 2, 3, 4]));
+        assert!(decode_base64("AQ*D").is_err());
+        let gids = [1u32, 2, 0x8000_0003];
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&gids.map(u32::to_le_bytes).concat())
+            .unwrap();
+        let text = STANDARD.encode(gzip.finish().unwrap());
+        assert_eq!(
+            decode_tiles(Some("base64"), Some("gzip"), &text),
+            Ok(gids.to_vec())
+        );
+        assert!(decode_tiles(Some("base64"), Some("gzip"), "AQIDBA==").is_err());
+        assert_eq!(
+            decode_tiles(Some("csv"), None, " 1,2,\n3 "),
+            Ok(vec![1, 2, 3])
+        );
+    }
+}
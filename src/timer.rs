@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+#[derive(Debug, Clone)]
 pub struct Timer {
     pub acc: Duration,
     pub length: Duration,
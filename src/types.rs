@@ -1,6 +1,6 @@
 pub use fontdue::FontSettings;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
-pub use winit::event::VirtualKeyCode;
+pub use winit::event::{MouseButton, VirtualKeyCode};
 pub use winit_input_helper::WinitInputHelper;
 
 macro_rules! impl_common_vec_traits {
@@ -161,6 +161,20 @@ impl Vec2F {
             Self { x: 0.0, y: 0.0 }
         }
     }
+    pub fn dot(&self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+    /// The z component of the 3d cross product, positive when `other` turns clockwise on screen
+    pub fn cross(&self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+    /// Rotated a quarter turn clockwise on screen
+    pub fn perpendicular(&self) -> Self {
+        Self {
+            x: -self.y,
+            y: self.x,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -190,6 +204,21 @@ impl Rect {
             && self.top_left.y < other.top_left.y + other.height as i32
             && self.height as i32 + self.top_left.y > other.top_left.y
     }
+    /// The area covered by both rects, `None` when they don't overlap
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let left = self.left().max(other.left());
+        let top = self.top().max(other.top());
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if left >= right || top >= bottom {
+            return None;
+        }
+        Some(Self::new(
+            Vec2::new(left, top),
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ))
+    }
     pub fn offset(&mut self, vector: Vec2) {
         self.top_left.x += vector.x;
         self.top_left.y += vector.y;
@@ -309,4 +338,19 @@ mod tests {
         assert_eq!(width, 10);
         assert_eq!(height, 10);
     }
+
+    #[test]
+    fn test_rect_intersection() {
+        let rect = Rect::new(Vec2::new(0, 0), 10, 10);
+        assert_eq!(
+            rect.intersection(&Rect::new(Vec2::new(6, -2), 10, 5)),
+            Some(Rect::new(Vec2::new(6, 0), 4, 3))
+        );
+        assert_eq!(
+            rect.intersection(&Rect::new(Vec2::new(2, 2), 3, 3)),
+            Some(Rect::new(Vec2::new(2, 2), 3, 3))
+        );
+        // touching edges don't overlap
+        assert_eq!(rect.intersection(&Rect::new(Vec2::new(10, 0), 5, 5)), None);
+    }
 }